@@ -0,0 +1,859 @@
+use crate::error::{ApiError, ErrorCode, ErrorResponse};
+use crate::openapi::ShortLinkPath;
+use crate::service::{Golink, Storage};
+use crate::storage::{
+    ClickCount, ClickSource, ClientClass, Granularity, StorageError, StorageResult,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+// How often buffered clicks are written to storage
+pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+// How long click buckets are kept before being pruned. Hourly buckets are
+// rolled up into daily ones as they are written, so pruning them only loses
+// resolution, not clicks.
+pub const HOURLY_RETENTION_DAYS: i64 = 7;
+pub const DAILY_RETENTION_DAYS: i64 = 400;
+
+// How often the flusher prunes expired buckets
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+// Upper bound on the number of buckets a single stats request may span
+const MAX_STATS_BUCKETS: i64 = 2000;
+
+// Number of referrers listed in stats responses
+const TOP_REFERRERS: usize = 10;
+
+// How long top and trending responses are served from cache
+pub const LEADERBOARD_CACHE_TTL: Duration = Duration::from_secs(60);
+
+// Minimum clicks in the current window for a golink to count as trending
+const MIN_TRENDING_CLICKS: i64 = 3;
+
+// Buffers redirect clicks in memory so the redirect path never waits on a
+// storage write. Buffered clicks are written in batches by `flush`, which
+// `spawn_flusher` calls periodically.
+#[derive(Clone)]
+pub struct ClickRecorder {
+    pending: Arc<Mutex<HashMap<String, ClickCount>>>,
+    storage: Storage,
+}
+
+impl ClickRecorder {
+    pub fn new(storage: Storage) -> Self {
+        Self {
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            storage,
+        }
+    }
+
+    pub fn record(&self, short_link: &str, source: &ClickSource) {
+        self.record_at(short_link, Utc::now(), source);
+    }
+
+    pub fn record_at(&self, short_link: &str, at: DateTime<Utc>, source: &ClickSource) {
+        let mut pending = self.pending.lock().unwrap();
+        pending
+            .entry(short_link.to_string())
+            .or_insert_with(|| ClickCount::new(short_link))
+            .add(at, source);
+    }
+
+    pub async fn flush(&self) -> StorageResult<()> {
+        let batch: Vec<ClickCount> = {
+            let mut pending = self.pending.lock().unwrap();
+            pending.drain().map(|(_, clicks)| clicks).collect()
+        };
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        if let Err(e) = self.storage.record_clicks(batch.clone()).await {
+            // Put the clicks back so the next flush retries them
+            let mut pending = self.pending.lock().unwrap();
+            for clicks in batch {
+                pending
+                    .entry(clicks.short_link.clone())
+                    .or_insert_with(|| ClickCount::new(&clicks.short_link))
+                    .merge(clicks);
+            }
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    // Drops click buckets that are past their retention period
+    pub async fn prune(&self, now: DateTime<Utc>) -> StorageResult<()> {
+        let now = now.timestamp();
+        self.storage
+            .prune_click_buckets(Granularity::Hour, now - HOURLY_RETENTION_DAYS * 86400)
+            .await?;
+        self.storage
+            .prune_click_buckets(Granularity::Day, now - DAILY_RETENTION_DAYS * 86400)
+            .await
+    }
+
+    pub fn spawn_flusher(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let recorder = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            let mut prune_ticker = tokio::time::interval(PRUNE_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {
+                        if let Err(e) = recorder.flush().await {
+                            eprintln!("Error: Failed to flush click counts: {:?}", e);
+                        }
+                    }
+                    _ = prune_ticker.tick() => {
+                        if let Err(e) = recorder.prune(Utc::now()).await {
+                            eprintln!("Error: Failed to prune click buckets: {:?}", e);
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StatsBucket {
+    pub start: String,
+    pub clicks: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ReferrerStats {
+    // `None` for clicks without a `Referer` header
+    pub referrer: Option<String>,
+    pub clicks: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ClientStats {
+    pub client: String,
+    pub clicks: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct GolinkStats {
+    pub short_link: String,
+    pub granularity: String,
+    pub from: String,
+    pub to: String,
+    // Bot clicks are excluded from `total` and `buckets`
+    pub total: i64,
+    pub bot_clicks: i64,
+    pub buckets: Vec<StatsBucket>,
+    // Breakdowns cover the days overlapping the range; referrers exclude bots
+    pub top_referrers: Vec<ReferrerStats>,
+    pub clients: Vec<ClientStats>,
+}
+
+// Accepts RFC 3339 timestamps or plain dates (midnight UTC)
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .map(|date| date.and_hms_opt(0, 0, 0).unwrap().and_utc())
+}
+
+fn invalid_parameter(message: impl Into<String>) -> ApiError {
+    ApiError::new(ErrorCode::InvalidParameter, message)
+}
+
+// Click time series for one golink. Every bucket in the requested range is
+// returned, including empty ones.
+#[utoipa::path(
+    get,
+    path = "/golinks/{prefix}/{name}/stats",
+    tag = "analytics",
+    params(
+        ShortLinkPath,
+        ("granularity" = Option<String>, Query, description = "`hour` or `day` (default)"),
+        ("from" = Option<String>, Query, description = "RFC 3339 timestamp or YYYY-MM-DD"),
+        ("to" = Option<String>, Query, description = "RFC 3339 timestamp or YYYY-MM-DD, defaults to now"),
+    ),
+    responses(
+        (status = 200, description = "Click time series and breakdowns", body = GolinkStats),
+        (status = 400, description = "Invalid range or granularity", body = ErrorResponse),
+        (status = 404, description = "Golink not found", body = ErrorResponse),
+    )
+)]
+pub async fn get_golink_stats(
+    short_link: String,
+    params: HashMap<String, String>,
+    storage: Storage,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, ApiError> {
+    let granularity = match params.get("granularity") {
+        Some(value) => match Granularity::parse(value) {
+            Some(granularity) => granularity,
+            None => return Err(invalid_parameter("Invalid granularity. Must be 'hour' or 'day'")),
+        },
+        None => Granularity::Day,
+    };
+
+    let to = match params.get("to") {
+        Some(value) => match parse_time(value) {
+            Some(to) => to,
+            None => return Err(invalid_parameter("Invalid 'to'. Expected an RFC 3339 timestamp or YYYY-MM-DD")),
+        },
+        None => Utc::now(),
+    };
+    let from = match params.get("from") {
+        Some(value) => match parse_time(value) {
+            Some(from) => from,
+            None => return Err(invalid_parameter("Invalid 'from'. Expected an RFC 3339 timestamp or YYYY-MM-DD")),
+        },
+        // Default to the last 30 days, or the last 2 days when hourly
+        None => match granularity {
+            Granularity::Hour => to - chrono::Duration::days(2),
+            Granularity::Day => to - chrono::Duration::days(30),
+        },
+    };
+    if from > to {
+        return Err(invalid_parameter("'from' must not be after 'to'"));
+    }
+
+    // Align the range to whole buckets, including the bucket `to` falls in
+    let size = granularity.seconds();
+    let start = granularity.bucket_start(from.timestamp());
+    let end = granularity.bucket_start(to.timestamp()) + size;
+    if (end - start) / size > MAX_STATS_BUCKETS {
+        return Err(invalid_parameter("Requested range has too many buckets, narrow 'from' and 'to'"));
+    }
+
+    storage.get(&short_link).await?;
+
+    let stored = storage
+        .get_click_buckets(&short_link, granularity, start, end)
+        .await?;
+    let stored: HashMap<i64, i64> = stored.into_iter().map(|b| (b.start, b.clicks)).collect();
+
+    let buckets: Vec<StatsBucket> = (start..end)
+        .step_by(size as usize)
+        .map(|bucket| StatsBucket {
+            start: DateTime::from_timestamp(bucket, 0).unwrap().to_rfc3339(),
+            clicks: stored.get(&bucket).copied().unwrap_or(0),
+        })
+        .collect();
+
+    let sources = storage
+        .get_click_sources(
+            &short_link,
+            Granularity::Day.bucket_start(start),
+            Granularity::Day.bucket_start(end - 1) + Granularity::Day.seconds(),
+        )
+        .await?;
+
+    let mut referrers: HashMap<Option<String>, i64> = HashMap::new();
+    let mut clients: HashMap<ClientClass, i64> = HashMap::new();
+    for source in sources {
+        *clients.entry(source.client).or_insert(0) += source.clicks;
+        if source.client != ClientClass::Bot {
+            *referrers.entry(source.referrer).or_insert(0) += source.clicks;
+        }
+    }
+
+    let mut top_referrers: Vec<ReferrerStats> = referrers
+        .into_iter()
+        .map(|(referrer, clicks)| ReferrerStats { referrer, clicks })
+        .collect();
+    top_referrers.sort_by(|a, b| b.clicks.cmp(&a.clicks).then_with(|| a.referrer.cmp(&b.referrer)));
+    top_referrers.truncate(TOP_REFERRERS);
+
+    let bot_clicks = clients.get(&ClientClass::Bot).copied().unwrap_or(0);
+    let mut clients: Vec<(ClientClass, i64)> = clients.into_iter().collect();
+    clients.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let stats = GolinkStats {
+        short_link,
+        granularity: granularity.as_str().to_string(),
+        from: DateTime::from_timestamp(start, 0).unwrap().to_rfc3339(),
+        to: DateTime::from_timestamp(end, 0).unwrap().to_rfc3339(),
+        total: buckets.iter().map(|b| b.clicks).sum(),
+        bot_clicks,
+        buckets,
+        top_referrers,
+        clients: clients
+            .into_iter()
+            .map(|(client, clicks)| ClientStats {
+                client: client.as_str().to_string(),
+                clicks,
+            })
+            .collect(),
+    };
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&stats),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+// A trailing window of whole buckets ending with the current one, like `7d`
+// or `24h`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    pub granularity: Granularity,
+    pub buckets: i64,
+}
+
+impl Window {
+    // Windows can't reach further back than the buckets are retained
+    pub fn parse(value: &str) -> Option<Self> {
+        let (count, granularity) = if let Some(count) = value.strip_suffix('h') {
+            (count, Granularity::Hour)
+        } else if let Some(count) = value.strip_suffix('d') {
+            (count, Granularity::Day)
+        } else {
+            return None;
+        };
+        let buckets = count.parse::<i64>().ok()?;
+        (1..=Self::max_buckets(granularity))
+            .contains(&buckets)
+            .then_some(Self {
+                granularity,
+                buckets,
+            })
+    }
+
+    // Number of buckets of this granularity that are retained
+    pub fn max_buckets(granularity: Granularity) -> i64 {
+        match granularity {
+            Granularity::Hour => HOURLY_RETENTION_DAYS * 24,
+            Granularity::Day => DAILY_RETENTION_DAYS,
+        }
+    }
+
+    // Bucket range `windows_back` windows before the one ending at `now`
+    pub fn range(&self, now: i64, windows_back: i64) -> (i64, i64) {
+        let size = self.granularity.seconds();
+        let end = self.granularity.bucket_start(now) + size - windows_back * self.buckets * size;
+        (end - self.buckets * size, end)
+    }
+}
+
+impl std::fmt::Display for Window {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let unit = match self.granularity {
+            Granularity::Hour => 'h',
+            Granularity::Day => 'd',
+        };
+        write!(f, "{}{}", self.buckets, unit)
+    }
+}
+
+// Short-lived cache for responses that are expensive to compute and polled
+// often, like the top and trending leaderboards
+#[derive(Clone)]
+pub struct ResponseCache {
+    ttl: Duration,
+    entries: Arc<Mutex<HashMap<String, (Instant, serde_json::Value)>>>,
+}
+
+impl ResponseCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<serde_json::Value> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(key)
+            .filter(|(cached_at, _)| cached_at.elapsed() < self.ttl)
+            .map(|(_, value)| value.clone())
+    }
+
+    pub fn insert(&self, key: String, value: serde_json::Value) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (cached_at, _)| cached_at.elapsed() < self.ttl);
+        entries.insert(key, (Instant::now(), value));
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RankedGolink {
+    #[serde(flatten)]
+    pub golink: Golink,
+    pub window_clicks: i64,
+    // Only set for trending golinks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_clicks: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Leaderboard {
+    pub window: String,
+    pub from: String,
+    pub to: String,
+    pub links: Vec<RankedGolink>,
+}
+
+fn leaderboard_params(params: &HashMap<String, String>) -> Result<(Window, usize), ApiError> {
+    let window = match params.get("window") {
+        Some(value) => Window::parse(value).ok_or_else(|| {
+            invalid_parameter(format!(
+                "Invalid window. Expected e.g. '24h' (up to {}h) or '7d' (up to {}d)",
+                HOURLY_RETENTION_DAYS * 24,
+                DAILY_RETENTION_DAYS
+            ))
+        })?,
+        None => Window {
+            granularity: Granularity::Day,
+            buckets: 7,
+        },
+    };
+    let limit = params
+        .get("limit")
+        .and_then(|l| l.parse::<usize>().ok())
+        .unwrap_or(10)
+        .clamp(1, 100);
+    Ok((window, limit))
+}
+
+fn cached_response(value: serde_json::Value) -> warp::reply::Response {
+    use warp::Reply;
+
+    warp::reply::with_header(
+        warp::reply::json(&value),
+        "cache-control",
+        format!("max-age={}", LEADERBOARD_CACHE_TTL.as_secs()),
+    )
+    .into_response()
+}
+
+// Looks up the golinks for ranked (short_link, clicks, previous) entries,
+// skipping any deleted in the meantime
+async fn ranked_golinks(
+    ranked: Vec<(String, i64, Option<i64>)>,
+    storage: &Storage,
+) -> Result<Vec<RankedGolink>, StorageError> {
+    let mut links = Vec::with_capacity(ranked.len());
+    for (short_link, window_clicks, previous_clicks) in ranked {
+        match storage.get(&short_link).await {
+            Ok(golink) => links.push(RankedGolink {
+                golink,
+                window_clicks,
+                previous_clicks,
+            }),
+            Err(StorageError::NotFound) => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(links)
+}
+
+// Most clicked golinks over a trailing window
+#[utoipa::path(
+    get,
+    path = "/golinks/top",
+    tag = "analytics",
+    params(
+        ("window" = Option<String>, Query, description = "Trailing window like `24h` or `7d` (default)"),
+        ("limit" = Option<usize>, Query, description = "Between 1 and 100, defaults to 10"),
+    ),
+    responses(
+        (status = 200, description = "Most clicked golinks", body = Leaderboard),
+        (status = 400, description = "Invalid window", body = ErrorResponse),
+    )
+)]
+pub async fn get_top_golinks(
+    params: HashMap<String, String>,
+    cache: ResponseCache,
+    storage: Storage,
+) -> Result<warp::reply::Response, ApiError> {
+    let (window, limit) = leaderboard_params(&params)?;
+
+    let key = format!("top:{}:{}", window, limit);
+    if let Some(cached) = cache.get(&key) {
+        return Ok(cached_response(cached));
+    }
+
+    let (from, to) = window.range(Utc::now().timestamp(), 0);
+    let totals = storage
+        .get_click_totals(window.granularity, from, to)
+        .await?;
+
+    let mut ranked: Vec<(String, i64, Option<i64>)> = totals
+        .into_iter()
+        .map(|(short_link, clicks)| (short_link, clicks, None))
+        .collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked.truncate(limit);
+
+    let links = ranked_golinks(ranked, &storage).await?;
+
+    let leaderboard = Leaderboard {
+        window: window.to_string(),
+        from: DateTime::from_timestamp(from, 0).unwrap().to_rfc3339(),
+        to: DateTime::from_timestamp(to, 0).unwrap().to_rfc3339(),
+        links,
+    };
+    let value = serde_json::to_value(&leaderboard).unwrap();
+    cache.insert(key, value.clone());
+    Ok(cached_response(value))
+}
+
+// Golinks whose clicks grew the most compared to the previous window of the
+// same length, relative to how many clicks they had before
+#[utoipa::path(
+    get,
+    path = "/golinks/trending",
+    tag = "analytics",
+    params(
+        ("window" = Option<String>, Query, description = "Trailing window like `24h` or `7d` (default)"),
+        ("limit" = Option<usize>, Query, description = "Between 1 and 100, defaults to 10"),
+    ),
+    responses(
+        (status = 200, description = "Golinks gaining clicks fastest", body = Leaderboard),
+        (status = 400, description = "Invalid window, longer than half the retention", body = ErrorResponse),
+    )
+)]
+pub async fn get_trending_golinks(
+    params: HashMap<String, String>,
+    cache: ResponseCache,
+    storage: Storage,
+) -> Result<warp::reply::Response, ApiError> {
+    let (window, limit) = leaderboard_params(&params)?;
+    // The previous window has to be retained too, otherwise every golink
+    // looks like it's trending against clicks that were pruned
+    if window.buckets > Window::max_buckets(window.granularity) / 2 {
+        return Err(invalid_parameter(format!(
+            "Invalid window. Trending compares with the previous window, so it goes up to {}h or {}d",
+            Window::max_buckets(Granularity::Hour) / 2,
+            Window::max_buckets(Granularity::Day) / 2
+        )));
+    }
+
+    let key = format!("trending:{}:{}", window, limit);
+    if let Some(cached) = cache.get(&key) {
+        return Ok(cached_response(cached));
+    }
+
+    let now = Utc::now().timestamp();
+    let (from, to) = window.range(now, 0);
+    let (previous_from, previous_to) = window.range(now, 1);
+    let current = storage
+        .get_click_totals(window.granularity, from, to)
+        .await?;
+    let previous = storage
+        .get_click_totals(window.granularity, previous_from, previous_to)
+        .await?;
+
+    let mut ranked: Vec<(String, i64, Option<i64>)> = current
+        .into_iter()
+        .filter_map(|(short_link, clicks)| {
+            let before = previous.get(&short_link).copied().unwrap_or(0);
+            (clicks >= MIN_TRENDING_CLICKS && clicks > before)
+                .then_some((short_link, clicks, Some(before)))
+        })
+        .collect();
+    let growth = |clicks: i64, before: Option<i64>| {
+        let before = before.unwrap_or(0);
+        (clicks - before) as f64 / (before + 1) as f64
+    };
+    ranked.sort_by(|a, b| {
+        growth(b.1, b.2)
+            .total_cmp(&growth(a.1, a.2))
+            .then_with(|| b.1.cmp(&a.1))
+            .then_with(|| a.0.cmp(&b.0))
+    });
+    ranked.truncate(limit);
+
+    let links = ranked_golinks(ranked, &storage).await?;
+
+    let leaderboard = Leaderboard {
+        window: window.to_string(),
+        from: DateTime::from_timestamp(from, 0).unwrap().to_rfc3339(),
+        to: DateTime::from_timestamp(to, 0).unwrap().to_rfc3339(),
+        links,
+    };
+    let value = serde_json::to_value(&leaderboard).unwrap();
+    cache.insert(key, value.clone());
+    Ok(cached_response(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::Golink;
+    use crate::storage::HashMapStorage;
+
+    async fn create_test_storage() -> Storage {
+        let storage: Storage = Arc::new(HashMapStorage::new());
+        storage
+            .create(Golink {
+                id: uuid::Uuid::new_v4().to_string(),
+                short_link: "go/test".to_string(),
+                url: "https://example.com".to_string(),
+                created_at: chrono::Utc::now().to_rfc3339(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        storage
+    }
+
+    #[tokio::test]
+    async fn test_clicks_are_buffered_until_flush() {
+        let storage = create_test_storage().await;
+        let recorder = ClickRecorder::new(storage.clone());
+
+        let source = ClickSource::from_headers(None, None);
+        recorder.record("go/test", &source);
+        recorder.record("go/test", &source);
+        recorder.record("go/test", &source);
+
+        let golink = storage.get("go/test").await.unwrap();
+        assert_eq!(golink.clicks, 0);
+        assert!(golink.last_used_at.is_none());
+
+        recorder.flush().await.unwrap();
+
+        let golink = storage.get("go/test").await.unwrap();
+        assert_eq!(golink.clicks, 3);
+        assert!(golink.last_used_at.is_some());
+
+        // Flushing again doesn't double count
+        recorder.flush().await.unwrap();
+        assert_eq!(storage.get("go/test").await.unwrap().clicks, 3);
+    }
+
+    #[tokio::test]
+    async fn test_get_golink_stats() {
+        use warp::Reply;
+
+        let storage = create_test_storage().await;
+        let recorder = ClickRecorder::new(storage.clone());
+
+        let chat = ClickSource::from_headers(
+            Some("https://chat.example.com/channel"),
+            Some("Mozilla/5.0 (X11; Linux x86_64)"),
+        );
+        let cli = ClickSource::from_headers(None, Some("curl/8.4.0"));
+        let bot = ClickSource::from_headers(None, Some("Slackbot-LinkExpanding 1.0"));
+        recorder.record_at("go/test", parse_time("2024-01-01T10:15:00Z").unwrap(), &chat);
+        recorder.record_at("go/test", parse_time("2024-01-01T10:45:00Z").unwrap(), &chat);
+        recorder.record_at("go/test", parse_time("2024-01-01T10:50:00Z").unwrap(), &bot);
+        recorder.record_at("go/test", parse_time("2024-01-03T08:00:00Z").unwrap(), &cli);
+        recorder.flush().await.unwrap();
+
+        let params: HashMap<String, String> = [("from", "2024-01-01"), ("to", "2024-01-03")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let response = get_golink_stats("go/test".to_string(), params, storage.clone())
+            .await
+            .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+
+        let body = warp::hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let stats: GolinkStats = serde_json::from_slice(&body).unwrap();
+        assert_eq!(stats.total, 3);
+        assert_eq!(stats.bot_clicks, 1);
+        let clicks: Vec<i64> = stats.buckets.iter().map(|b| b.clicks).collect();
+        assert_eq!(clicks, vec![2, 0, 1]);
+
+        let referrers: Vec<(Option<&str>, i64)> = stats
+            .top_referrers
+            .iter()
+            .map(|r| (r.referrer.as_deref(), r.clicks))
+            .collect();
+        assert_eq!(referrers, vec![(Some("chat.example.com"), 2), (None, 1)]);
+        let clients: Vec<(&str, i64)> = stats
+            .clients
+            .iter()
+            .map(|c| (c.client.as_str(), c.clicks))
+            .collect();
+        assert_eq!(clients, vec![("browser", 2), ("cli", 1), ("bot", 1)]);
+
+        let params: HashMap<String, String> = [
+            ("from", "2024-01-01T10:00:00Z"),
+            ("to", "2024-01-01T11:30:00Z"),
+            ("granularity", "hour"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let response = get_golink_stats("go/test".to_string(), params, storage)
+            .await
+            .into_response();
+        let body = warp::hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let stats: GolinkStats = serde_json::from_slice(&body).unwrap();
+        let clicks: Vec<i64> = stats.buckets.iter().map(|b| b.clicks).collect();
+        assert_eq!(clicks, vec![2, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_get_golink_stats_invalid_params() {
+        use warp::Reply;
+
+        let storage = create_test_storage().await;
+
+        for (key, value) in [("granularity", "week"), ("from", "yesterday")] {
+            let params = HashMap::from([(key.to_string(), value.to_string())]);
+            let response = get_golink_stats("go/test".to_string(), params, storage.clone())
+                .await
+                .into_response();
+            assert_eq!(response.status(), warp::http::StatusCode::BAD_REQUEST);
+        }
+
+        let response = get_golink_stats("go/nonexistent".to_string(), HashMap::new(), storage)
+            .await
+            .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_window_parse() {
+        let window = Window::parse("7d").unwrap();
+        assert_eq!(window.granularity, Granularity::Day);
+        assert_eq!(window.buckets, 7);
+        assert_eq!(window.to_string(), "7d");
+        assert_eq!(Window::parse("24h").unwrap().granularity, Granularity::Hour);
+
+        assert!(Window::parse("0d").is_none());
+        assert!(Window::parse("7w").is_none());
+        assert!(Window::parse("1000h").is_none());
+
+        // The window ends with the bucket containing `now`
+        let now = 1_704_067_200 + 3600;
+        assert_eq!(
+            window.range(now, 0),
+            (1_704_067_200 - 6 * 86400, 1_704_067_200 + 86400)
+        );
+        assert_eq!(
+            window.range(now, 1),
+            (1_704_067_200 - 13 * 86400, 1_704_067_200 - 6 * 86400)
+        );
+    }
+
+    async fn leaderboard(response: warp::reply::Response) -> Vec<(String, i64)> {
+        let body = warp::hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let leaderboard: Leaderboard = serde_json::from_slice(&body).unwrap();
+        leaderboard
+            .links
+            .into_iter()
+            .map(|link| (link.golink.short_link, link.window_clicks))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_top_and_trending_golinks() {
+        let storage = create_test_storage().await;
+        for name in ["go/steady", "go/rising"] {
+            storage
+                .create(Golink {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    short_link: name.to_string(),
+                    url: "https://example.com".to_string(),
+                    created_at: Utc::now().to_rfc3339(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        let recorder = ClickRecorder::new(storage.clone());
+        let source = ClickSource::from_headers(None, None);
+        let now = Utc::now();
+        let last_week = now - chrono::Duration::days(8);
+        for _ in 0..10 {
+            recorder.record_at("go/steady", now, &source);
+            recorder.record_at("go/steady", last_week, &source);
+        }
+        for _ in 0..4 {
+            recorder.record_at("go/rising", now, &source);
+        }
+        recorder.record_at("go/test", now, &source);
+        recorder.flush().await.unwrap();
+
+        let cache = ResponseCache::new(LEADERBOARD_CACHE_TTL);
+        let response = get_top_golinks(HashMap::new(), cache.clone(), storage.clone())
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["cache-control"], "max-age=60");
+        assert_eq!(
+            leaderboard(response).await,
+            vec![
+                ("go/steady".to_string(), 10),
+                ("go/rising".to_string(), 4),
+                ("go/test".to_string(), 1),
+            ]
+        );
+
+        let params = HashMap::from([("limit".to_string(), "1".to_string())]);
+        let response = get_top_golinks(params, cache.clone(), storage.clone())
+            .await
+            .unwrap();
+        assert_eq!(leaderboard(response).await, vec![("go/steady".to_string(), 10)]);
+
+        // Steady clicks don't trend, and go/test has too few clicks to count
+        let response = get_trending_golinks(HashMap::new(), cache.clone(), storage.clone())
+            .await
+            .unwrap();
+        assert_eq!(leaderboard(response).await, vec![("go/rising".to_string(), 4)]);
+
+        // Trending windows leave room for the previous window in the retention
+        for (window, allowed) in [("84h", true), ("85h", false), ("200d", true), ("201d", false)] {
+            let params = HashMap::from([("window".to_string(), window.to_string())]);
+            let result = get_trending_golinks(params, cache.clone(), storage.clone()).await;
+            match result {
+                Ok(_) => assert!(allowed, "{}", window),
+                Err(error) => {
+                    assert!(!allowed, "{}", window);
+                    assert_eq!(error.code, ErrorCode::InvalidParameter);
+                }
+            }
+        }
+        let params = HashMap::from([("window".to_string(), "400d".to_string())]);
+        assert!(get_top_golinks(params, cache.clone(), storage.clone()).await.is_ok());
+
+        // Cached responses don't see new clicks until they expire
+        for _ in 0..20 {
+            recorder.record_at("go/rising", now, &source);
+        }
+        recorder.flush().await.unwrap();
+        let response = get_top_golinks(HashMap::new(), cache, storage.clone())
+            .await
+            .unwrap();
+        assert_eq!(leaderboard(response).await[0], ("go/steady".to_string(), 10));
+
+        let response = get_top_golinks(
+            HashMap::new(),
+            ResponseCache::new(LEADERBOARD_CACHE_TTL),
+            storage,
+        )
+        .await
+        .unwrap();
+        assert_eq!(leaderboard(response).await[0], ("go/rising".to_string(), 24));
+    }
+
+    #[tokio::test]
+    async fn test_clicks_for_deleted_golinks_are_dropped() {
+        let storage = create_test_storage().await;
+        let recorder = ClickRecorder::new(storage.clone());
+
+        let source = ClickSource::from_headers(None, None);
+        recorder.record("go/test", &source);
+        recorder.record("go/deleted", &source);
+        recorder.flush().await.unwrap();
+
+        assert_eq!(storage.get("go/test").await.unwrap().clicks, 1);
+        assert!(matches!(
+            storage.get("go/deleted").await,
+            Err(crate::storage::StorageError::NotFound)
+        ));
+    }
+}
@@ -0,0 +1,429 @@
+use crate::error::{ApiError, ErrorCode, ErrorResponse};
+use crate::service::{Golink, Role, Storage, validate_golink_pattern};
+use crate::storage::{BatchOperation, BatchResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+// Upper bound on operations per batch request
+pub const MAX_BATCH_OPERATIONS: usize = 100;
+
+const NOT_APPLIED: &str = "Not applied, another operation in the atomic batch failed";
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum BatchItem {
+    Create {
+        short_link: String,
+        url: String,
+        #[serde(default)]
+        owner: Option<String>,
+    },
+    Update {
+        short_link: String,
+        url: String,
+    },
+    Delete {
+        short_link: String,
+    },
+}
+
+impl BatchItem {
+    fn op(&self) -> &'static str {
+        match self {
+            BatchItem::Create { .. } => "create",
+            BatchItem::Update { .. } => "update",
+            BatchItem::Delete { .. } => "delete",
+        }
+    }
+
+    fn short_link(&self) -> &str {
+        match self {
+            BatchItem::Create { short_link, .. }
+            | BatchItem::Update { short_link, .. }
+            | BatchItem::Delete { short_link } => short_link,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BatchRequest {
+    pub operations: Vec<BatchItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BatchItemResult {
+    pub index: usize,
+    pub op: String,
+    pub short_link: String,
+    // HTTP status the operation would have gotten as a single request
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub golink: Option<Golink>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BatchResponse {
+    pub atomic: bool,
+    // False when an atomic batch was rolled back
+    pub applied: bool,
+    pub results: Vec<BatchItemResult>,
+}
+
+fn item_result(index: usize, item: &BatchItem, result: BatchResult) -> BatchItemResult {
+    let (status, golink, error) = match result {
+        Ok(Some(golink)) if matches!(item, BatchItem::Create { .. }) => (201, Some(golink), None),
+        Ok(golink) => (200, golink, None),
+        Err(e) => {
+            let error = ApiError::from(e);
+            (error.status().as_u16(), None, Some(error.message))
+        }
+    };
+    BatchItemResult {
+        index,
+        op: item.op().to_string(),
+        short_link: item.short_link().to_string(),
+        status,
+        golink,
+        error,
+    }
+}
+
+fn rejected_item(index: usize, item: &BatchItem, status: u16, error: &str) -> BatchItemResult {
+    BatchItemResult {
+        index,
+        op: item.op().to_string(),
+        short_link: item.short_link().to_string(),
+        status,
+        golink: None,
+        error: Some(error.to_string()),
+    }
+}
+
+// Checks an item before it reaches storage, the same way the single-item
+// handlers do
+fn check_item(item: &BatchItem, role: Role) -> Result<BatchOperation, ApiError> {
+    match item {
+        BatchItem::Create {
+            short_link,
+            url,
+            owner,
+        } => {
+            validate_golink_pattern(short_link)
+                .map_err(|e| ApiError::new(ErrorCode::InvalidPattern, e))?;
+            let now = chrono::Utc::now().to_rfc3339();
+            Ok(BatchOperation::Create(Golink {
+                id: Uuid::new_v4().to_string(),
+                short_link: short_link.clone(),
+                url: url.clone(),
+                owner: owner.clone(),
+                created_at: now.clone(),
+                updated_at: now,
+                version: 1,
+                ..Default::default()
+            }))
+        }
+        // Storage checks the lock as it writes, so a lock taken in between
+        // still stops a non-admin
+        BatchItem::Update { short_link, url } => Ok(BatchOperation::Update {
+            short_link: short_link.clone(),
+            url: url.clone(),
+            unlocked_only: role != Role::Admin,
+        }),
+        BatchItem::Delete { short_link } => Ok(BatchOperation::Delete {
+            short_link: short_link.clone(),
+            unlocked_only: role != Role::Admin,
+        }),
+    }
+}
+
+// Runs a list of create/update/delete operations and reports a status per
+// operation. With `atomic=true` either all of them are applied or none.
+#[utoipa::path(
+    post,
+    path = "/golinks/batch",
+    tag = "golinks",
+    params(("atomic" = Option<bool>, Query, description = "Apply all operations or none")),
+    request_body = BatchRequest,
+    responses(
+        (status = 200, description = "Status of every operation", body = BatchResponse),
+        (status = 400, description = "Invalid batch", body = ErrorResponse),
+    )
+)]
+pub async fn apply_golink_batch(
+    params: HashMap<String, String>,
+    role: Role,
+    request: BatchRequest,
+    storage: Storage,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, ApiError> {
+    let atomic = match params.get("atomic").map(String::as_str) {
+        None | Some("false") => false,
+        Some("true") => true,
+        Some(_) => {
+            return Err(ApiError::new(
+                ErrorCode::InvalidParameter,
+                "Invalid atomic. Must be 'true' or 'false'",
+            ));
+        }
+    };
+    if request.operations.is_empty() || request.operations.len() > MAX_BATCH_OPERATIONS {
+        return Err(ApiError::new(
+            ErrorCode::InvalidBody,
+            format!(
+                "A batch must contain between 1 and {} operations",
+                MAX_BATCH_OPERATIONS
+            ),
+        ));
+    }
+
+    let mut results: Vec<Option<BatchItemResult>> = Vec::with_capacity(request.operations.len());
+    let mut operations = Vec::new();
+    let mut indexes = Vec::new();
+    for (index, item) in request.operations.iter().enumerate() {
+        match check_item(item, role) {
+            Ok(operation) => {
+                operations.push(operation);
+                indexes.push(index);
+                results.push(None);
+            }
+            Err(error) => results.push(Some(rejected_item(
+                index,
+                item,
+                error.status().as_u16(),
+                &error.message,
+            ))),
+        }
+    }
+
+    let rejected = results.iter().any(Option::is_some);
+    // An atomic batch with invalid items is never sent to storage
+    let outcomes = if atomic && rejected {
+        Vec::new()
+    } else {
+        storage.apply_batch(operations, atomic).await?
+    };
+    for (index, outcome) in indexes.iter().zip(outcomes) {
+        results[*index] = Some(item_result(*index, &request.operations[*index], outcome));
+    }
+
+    let mut results: Vec<BatchItemResult> = results
+        .into_iter()
+        .enumerate()
+        .map(|(index, result)| {
+            result.unwrap_or_else(|| {
+                let item = &request.operations[index];
+                rejected_item(index, item, 424, NOT_APPLIED)
+            })
+        })
+        .collect();
+
+    let failed = results.iter().any(|result| result.status >= 400);
+    let applied = !(atomic && failed);
+    if !applied {
+        // Operations that would have succeeded were rolled back with the rest
+        for result in results.iter_mut().filter(|result| result.status < 400) {
+            result.status = 424;
+            result.golink = None;
+            result.error = Some(NOT_APPLIED.to_string());
+        }
+    }
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&BatchResponse {
+            atomic,
+            applied,
+            results,
+        }),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::HashMapStorage;
+    use std::sync::Arc;
+    use warp::Reply;
+
+    async fn create_test_storage() -> Storage {
+        let storage: Storage = Arc::new(HashMapStorage::new());
+        for (short_link, locked) in [("go/existing", false), ("go/locked", true)] {
+            storage
+                .create(Golink {
+                    id: Uuid::new_v4().to_string(),
+                    short_link: short_link.to_string(),
+                    url: "https://example.com".to_string(),
+                    created_at: chrono::Utc::now().to_rfc3339(),
+                    locked,
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+        storage
+    }
+
+    fn batch_request(operations: serde_json::Value) -> BatchRequest {
+        serde_json::from_value(serde_json::json!({ "operations": operations })).unwrap()
+    }
+
+    async fn run_batch(
+        atomic: bool,
+        operations: serde_json::Value,
+        storage: &Storage,
+    ) -> BatchResponse {
+        let params = HashMap::from([("atomic".to_string(), atomic.to_string())]);
+        let response = apply_golink_batch(
+            params,
+            Role::User,
+            batch_request(operations),
+            storage.clone(),
+        )
+        .await
+        .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn statuses(response: &BatchResponse) -> Vec<u16> {
+        response
+            .results
+            .iter()
+            .map(|result| result.status)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_batch_reports_each_operation() {
+        let storage = create_test_storage().await;
+
+        let response = run_batch(
+            false,
+            serde_json::json!([
+                {"op": "create", "short_link": "go/new", "url": "https://new.example.com"},
+                {"op": "create", "short_link": "go/existing", "url": "https://example.com"},
+                {"op": "create", "short_link": "invalid", "url": "https://example.com"},
+                {"op": "update", "short_link": "go/existing", "url": "https://updated.example.com"},
+                {"op": "update", "short_link": "go/locked", "url": "https://updated.example.com"},
+                {"op": "delete", "short_link": "go/missing"}
+            ]),
+            &storage,
+        )
+        .await;
+
+        assert!(response.applied);
+        assert_eq!(statuses(&response), vec![201, 409, 400, 200, 403, 404]);
+        assert_eq!(
+            response.results[3].golink.as_ref().unwrap().url,
+            "https://updated.example.com"
+        );
+        assert!(storage.exists("go/new").await.unwrap());
+        assert_eq!(
+            storage.get("go/locked").await.unwrap().url,
+            "https://example.com"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lock_taken_after_check_is_honored() {
+        let storage = create_test_storage().await;
+        let items = batch_request(serde_json::json!([
+            {"op": "update", "short_link": "go/existing", "url": "https://updated.example.com"},
+            {"op": "delete", "short_link": "go/existing"}
+        ]));
+
+        // An admin locks the golink between a user's check and write
+        let operations = items
+            .operations
+            .iter()
+            .map(|item| check_item(item, Role::User).unwrap())
+            .collect();
+        storage.set_locked("go/existing", true).await.unwrap();
+        let outcomes = storage.apply_batch(operations, false).await.unwrap();
+        let results: Vec<BatchItemResult> = items
+            .operations
+            .iter()
+            .zip(outcomes)
+            .enumerate()
+            .map(|(index, (item, outcome))| item_result(index, item, outcome))
+            .collect();
+        assert!(results.iter().all(|result| result.status == 403));
+        assert_eq!(
+            storage.get("go/existing").await.unwrap().url,
+            "https://example.com"
+        );
+
+        // Admins still get through
+        let operations = items
+            .operations
+            .iter()
+            .map(|item| check_item(item, Role::Admin).unwrap())
+            .collect();
+        let outcomes = storage.apply_batch(operations, false).await.unwrap();
+        assert!(outcomes.iter().all(Result::is_ok));
+        assert!(!storage.exists("go/existing").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_atomic_batch_is_all_or_nothing() {
+        let storage = create_test_storage().await;
+        let operations = serde_json::json!([
+            {"op": "create", "short_link": "go/new", "url": "https://new.example.com"},
+            {"op": "delete", "short_link": "go/existing"},
+            {"op": "delete", "short_link": "go/missing"}
+        ]);
+
+        let response = run_batch(true, operations, &storage).await;
+        assert!(!response.applied);
+        assert_eq!(statuses(&response), vec![424, 424, 404]);
+        assert!(!storage.exists("go/new").await.unwrap());
+        assert!(storage.exists("go/existing").await.unwrap());
+
+        // Invalid items also stop the whole batch
+        let operations = serde_json::json!([
+            {"op": "create", "short_link": "go/new", "url": "https://new.example.com"},
+            {"op": "create", "short_link": "invalid", "url": "https://example.com"}
+        ]);
+        let response = run_batch(true, operations, &storage).await;
+        assert_eq!(statuses(&response), vec![424, 400]);
+        assert!(!storage.exists("go/new").await.unwrap());
+
+        let operations = serde_json::json!([
+            {"op": "create", "short_link": "go/new", "url": "https://new.example.com"},
+            {"op": "delete", "short_link": "go/existing"}
+        ]);
+        let response = run_batch(true, operations, &storage).await;
+        assert!(response.applied);
+        assert_eq!(statuses(&response), vec![201, 200]);
+        assert!(storage.exists("go/new").await.unwrap());
+        assert!(!storage.exists("go/existing").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_batch_rejects_invalid_requests() {
+        let storage = create_test_storage().await;
+
+        let response = apply_golink_batch(
+            HashMap::new(),
+            Role::User,
+            batch_request(serde_json::json!([])),
+            storage.clone(),
+        )
+        .await
+        .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::BAD_REQUEST);
+
+        let params = HashMap::from([("atomic".to_string(), "yes".to_string())]);
+        let operations = serde_json::json!([{"op": "delete", "short_link": "go/existing"}]);
+        let response = apply_golink_batch(params, Role::User, batch_request(operations), storage)
+            .await
+            .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,685 @@
+use crate::collections::escape_html;
+use crate::error::{ApiError, ErrorCode, ErrorResponse};
+use crate::service::{
+    Golink, Role, Storage, normalize_tag, normalize_tags, validate_golink_pattern,
+};
+use crate::storage::{BatchOperation, StorageError};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use warp::hyper::body::Bytes;
+
+// A link found in a bookmark file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bookmark {
+    pub title: String,
+    pub url: String,
+    // Unix seconds, as written by browsers
+    pub add_date: Option<i64>,
+    // Enclosing folders, outermost first, followed by any TAGS attribute
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BookmarkAction {
+    // The bookmark name already is a valid golink
+    Create,
+    // The bookmark name had to be normalized into a golink
+    Rename,
+    Reject,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BookmarkEntry {
+    pub title: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub short_link: Option<String>,
+    pub tags: Vec<String>,
+    pub action: BookmarkAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BookmarkImport {
+    pub dry_run: bool,
+    pub created: usize,
+    pub renamed: usize,
+    pub rejected: usize,
+    pub entries: Vec<BookmarkEntry>,
+}
+
+enum Token {
+    Open {
+        name: String,
+        attributes: HashMap<String, String>,
+    },
+    Close(String),
+    Text(String),
+}
+
+fn decode_entities(value: &str) -> String {
+    let mut decoded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';').filter(|end| *end <= 10) else {
+            decoded.push('&');
+            rest = &rest[1..];
+            continue;
+        };
+        let entity = &rest[1..end];
+        let c = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match c {
+            Some(c) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+// Reads `NAME="value"` pairs, quoted or not, with lowercased names
+fn parse_attributes(source: &str) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    let mut chars = source.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let name: String =
+            std::iter::from_fn(|| chars.next_if(|c| *c != '=' && !c.is_whitespace())).collect();
+        if name.is_empty() {
+            break;
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut value = String::new();
+        if chars.next_if_eq(&'=').is_some() {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            match chars.next_if(|c| *c == '"' || *c == '\'') {
+                Some(quote) => value.extend(std::iter::from_fn(|| chars.next_if(|c| *c != quote))),
+                None => value.extend(std::iter::from_fn(|| chars.next_if(|c| !c.is_whitespace()))),
+            }
+            chars.next_if(|c| *c == '"' || *c == '\'');
+        }
+        attributes.insert(name.to_ascii_lowercase(), decode_entities(&value));
+    }
+    attributes
+}
+
+// Splits the document into tags and text. Comments and declarations are dropped.
+fn tokenize(html: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            tokens.push(Token::Text(decode_entities(rest)));
+            break;
+        };
+        if start > 0 {
+            tokens.push(Token::Text(decode_entities(&rest[..start])));
+        }
+        rest = &rest[start..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+
+        // Find the closing '>' outside of quoted attribute values
+        let mut quote = None;
+        let end = rest.char_indices().skip(1).find(|(_, c)| match quote {
+            Some(q) if *c == q => {
+                quote = None;
+                false
+            }
+            Some(_) => false,
+            None if *c == '"' || *c == '\'' => {
+                quote = Some(*c);
+                false
+            }
+            None => *c == '>',
+        });
+        let Some((end, _)) = end else {
+            break;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        if tag.starts_with('!') || tag.starts_with('?') {
+            continue;
+        }
+        if let Some(name) = tag.strip_prefix('/') {
+            tokens.push(Token::Close(name.trim().to_ascii_lowercase()));
+            continue;
+        }
+        let tag = tag.trim_end_matches('/');
+        let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+        tokens.push(Token::Open {
+            name: tag[..name_end].to_ascii_lowercase(),
+            attributes: parse_attributes(&tag[name_end..]),
+        });
+    }
+    tokens
+}
+
+// Collects the text up to the closing tag `name`
+fn element_text(tokens: &mut impl Iterator<Item = Token>, name: &str) -> String {
+    let mut text = String::new();
+    for token in tokens {
+        match token {
+            Token::Text(value) => text.push_str(&value),
+            Token::Close(close) if close == name => break,
+            _ => {}
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Parses a Netscape bookmark file, the format every major browser exports.
+// Folders are `<H3>` headings followed by a nested `<DL>` list.
+pub fn parse_bookmarks(html: &str) -> Vec<Bookmark> {
+    let mut bookmarks = Vec::new();
+    let mut folders: Vec<Option<String>> = Vec::new();
+    let mut pending_folder = None;
+    let mut tokens = tokenize(html).into_iter();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Open { name, .. } if name == "h3" => {
+                pending_folder = Some(element_text(&mut tokens, "h3"));
+            }
+            Token::Open { name, .. } if name == "dl" => folders.push(pending_folder.take()),
+            Token::Close(name) if name == "dl" => {
+                folders.pop();
+            }
+            Token::Open { name, attributes } if name == "a" => {
+                let title = element_text(&mut tokens, "a");
+                let Some(url) = attributes.get("href") else {
+                    continue;
+                };
+                let mut tags: Vec<String> = folders.iter().flatten().cloned().collect();
+                if let Some(extra) = attributes.get("tags") {
+                    tags.extend(extra.split(',').map(str::to_string));
+                }
+                bookmarks.push(Bookmark {
+                    title,
+                    url: url.trim().to_string(),
+                    add_date: attributes
+                        .get("add_date")
+                        .and_then(|date| date.parse().ok()),
+                    tags,
+                });
+            }
+            _ => {}
+        }
+    }
+    bookmarks
+}
+
+// Turns a bookmark name into a golink: an optional `go/` prefix is dropped and
+// the rest normalized like a tag, so "Team Wiki" becomes `go/team-wiki`
+pub fn bookmark_short_link(title: &str) -> Option<String> {
+    let title = title.trim();
+    let name = match title.get(..3) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("go/") => &title[3..],
+        _ => title,
+    };
+    let name = normalize_tag(name);
+    if name.is_empty() {
+        None
+    } else {
+        Some(format!("go/{}", name))
+    }
+}
+
+fn bookmark_entry(bookmark: &Bookmark) -> BookmarkEntry {
+    let tags = normalize_tags(&bookmark.tags);
+    let reject = |error: &str| BookmarkEntry {
+        title: bookmark.title.clone(),
+        url: bookmark.url.clone(),
+        short_link: None,
+        tags: tags.clone(),
+        action: BookmarkAction::Reject,
+        error: Some(error.to_string()),
+    };
+
+    if !(bookmark.url.starts_with("http://") || bookmark.url.starts_with("https://")) {
+        return reject("Only http and https bookmarks can be imported");
+    }
+    let Some(short_link) = bookmark_short_link(&bookmark.title) else {
+        return reject("Bookmark name has no characters usable in a golink");
+    };
+    if let Err(e) = validate_golink_pattern(&short_link) {
+        return reject(e);
+    }
+
+    let action = if short_link == bookmark.title.trim() {
+        BookmarkAction::Create
+    } else {
+        BookmarkAction::Rename
+    };
+    BookmarkEntry {
+        title: bookmark.title.clone(),
+        url: bookmark.url.clone(),
+        short_link: Some(short_link),
+        tags,
+        action,
+        error: None,
+    }
+}
+
+fn reject_entry(entry: &mut BookmarkEntry, error: &str) {
+    entry.action = BookmarkAction::Reject;
+    entry.error = Some(error.to_string());
+}
+
+fn bookmark_golink(entry: &BookmarkEntry, bookmark: &Bookmark) -> Golink {
+    let now = chrono::Utc::now().to_rfc3339();
+    let created_at = bookmark
+        .add_date
+        .and_then(|seconds| chrono::DateTime::from_timestamp(seconds, 0))
+        .map_or_else(|| now.clone(), |date| date.to_rfc3339());
+    Golink {
+        id: Uuid::new_v4().to_string(),
+        short_link: entry.short_link.clone().unwrap_or_default(),
+        url: entry.url.clone(),
+        created_at,
+        updated_at: now,
+        tags: entry.tags.clone(),
+        version: 1,
+        ..Default::default()
+    }
+}
+
+// Imports golinks from a browser bookmark file. Folders become tags and
+// bookmark names become golinks. With `dry_run=true` nothing is written and
+// the response previews what would be created, renamed or rejected. Admin only.
+#[utoipa::path(
+    post,
+    path = "/golinks/import/bookmarks",
+    tag = "transfer",
+    params(("dry_run" = Option<bool>, Query, description = "Preview without writing anything")),
+    request_body(content = String, description = "Netscape bookmark file", content_type = "text/html"),
+    responses(
+        (status = 200, description = "What was or would be imported", body = BookmarkImport),
+        (status = 400, description = "Invalid bookmark file", body = ErrorResponse),
+        (status = 403, description = "Admin token required", body = ErrorResponse),
+    )
+)]
+pub async fn import_bookmarks(
+    params: HashMap<String, String>,
+    role: Role,
+    body: Bytes,
+    storage: Storage,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, ApiError> {
+    if role != Role::Admin {
+        return Err(ApiError::new(
+            ErrorCode::AdminRequired,
+            "Admin token required to import golinks",
+        ));
+    }
+
+    let dry_run = match params.get("dry_run").map(String::as_str) {
+        None | Some("false") => false,
+        Some("true") => true,
+        Some(_) => {
+            return Err(ApiError::new(
+                ErrorCode::InvalidParameter,
+                "Invalid dry_run. Must be 'true' or 'false'",
+            ));
+        }
+    };
+    let Ok(html) = std::str::from_utf8(&body) else {
+        return Err(ApiError::new(
+            ErrorCode::InvalidBody,
+            "Bookmark file must be UTF-8",
+        ));
+    };
+
+    let bookmarks = parse_bookmarks(html);
+    let mut entries: Vec<BookmarkEntry> = bookmarks.iter().map(bookmark_entry).collect();
+
+    // Earlier bookmarks win when two normalize to the same golink
+    let mut seen = HashSet::new();
+    for entry in entries.iter_mut() {
+        if let Some(short_link) = entry.short_link.clone()
+            && entry.action != BookmarkAction::Reject
+        {
+            if !seen.insert(short_link.clone()) {
+                reject_entry(entry, "Another bookmark maps to the same golink");
+            } else if dry_run && storage.name_taken(&short_link).await? {
+                reject_entry(entry, &ApiError::from(StorageError::AlreadyExists).message);
+            }
+        }
+    }
+
+    if !dry_run {
+        let pending: Vec<usize> = (0..entries.len())
+            .filter(|index| entries[*index].action != BookmarkAction::Reject)
+            .collect();
+        let operations = pending
+            .iter()
+            .map(|index| {
+                BatchOperation::Create(bookmark_golink(&entries[*index], &bookmarks[*index]))
+            })
+            .collect();
+        let outcomes = storage.apply_batch(operations, false).await?;
+        for (index, outcome) in pending.into_iter().zip(outcomes) {
+            if let Err(e) = outcome {
+                reject_entry(&mut entries[index], &ApiError::from(e).message);
+            }
+        }
+    }
+
+    let count = |action| {
+        entries
+            .iter()
+            .filter(|entry| entry.action == action)
+            .count()
+    };
+    let import = BookmarkImport {
+        dry_run,
+        created: count(BookmarkAction::Create),
+        renamed: count(BookmarkAction::Rename),
+        rejected: count(BookmarkAction::Reject),
+        entries,
+    };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&import),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+fn bookmark_line(golink: &Golink, indent: &str) -> String {
+    let add_date = chrono::DateTime::parse_from_rfc3339(&golink.created_at)
+        .map(|date| date.timestamp())
+        .unwrap_or_default();
+    let tags = if golink.tags.is_empty() {
+        String::new()
+    } else {
+        format!(" TAGS=\"{}\"", escape_html(&golink.tags.join(",")))
+    };
+    format!(
+        "{}<DT><A HREF=\"{}\" ADD_DATE=\"{}\"{}>{}</A>\n",
+        indent,
+        escape_html(&golink.url),
+        add_date,
+        tags,
+        escape_html(&golink.short_link)
+    )
+}
+
+// Renders golinks as a Netscape bookmark file. Each golink is filed in a
+// folder named after its first tag and keeps the rest in a TAGS attribute.
+pub fn render_bookmarks(golinks: &[Golink]) -> String {
+    let mut folders: BTreeMap<&str, Vec<&Golink>> = BTreeMap::new();
+    let mut untagged = Vec::new();
+    for golink in golinks {
+        match golink.tags.first() {
+            Some(tag) => folders.entry(tag).or_default().push(golink),
+            None => untagged.push(golink),
+        }
+    }
+
+    let mut html = String::from(
+        "<!DOCTYPE NETSCAPE-Bookmark-file-1>\n\
+         <META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">\n\
+         <TITLE>Bookmarks</TITLE>\n\
+         <H1>Bookmarks</H1>\n\
+         <DL><p>\n",
+    );
+    for (folder, golinks) in folders {
+        html.push_str(&format!(
+            "    <DT><H3>{}</H3>\n    <DL><p>\n",
+            escape_html(folder)
+        ));
+        for golink in golinks {
+            html.push_str(&bookmark_line(golink, "        "));
+        }
+        html.push_str("    </DL><p>\n");
+    }
+    for golink in untagged {
+        html.push_str(&bookmark_line(golink, "    "));
+    }
+    html.push_str("</DL><p>\n");
+    html
+}
+
+// Every golink as a bookmark file that browsers can import
+#[utoipa::path(
+    get,
+    path = "/golinks/export/bookmarks",
+    tag = "transfer",
+    responses(
+        (status = 200, description = "Netscape bookmark file", body = String, content_type = "text/html"),
+    )
+)]
+pub async fn export_bookmarks(storage: Storage) -> Result<warp::reply::Response, ApiError> {
+    use warp::Reply;
+
+    let mut golinks = storage.get_all().await?;
+    golinks.sort_by(|a, b| a.short_link.cmp(&b.short_link));
+
+    let response = warp::reply::with_header(
+        render_bookmarks(&golinks),
+        "content-type",
+        "text/html; charset=utf-8",
+    );
+    Ok(warp::reply::with_header(
+        response,
+        "content-disposition",
+        "attachment; filename=\"bookmarks.html\"",
+    )
+    .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::Collection;
+    use crate::storage::HashMapStorage;
+    use std::sync::Arc;
+    use warp::Reply;
+
+    const BOOKMARKS: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<!-- This is an automatically generated file. -->
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+<DL><p>
+    <DT><H3 ADD_DATE="1700000000">Engineering Tools</H3>
+    <DL><p>
+        <DT><A HREF="https://ci.example.com/?a=1&amp;b=2" ADD_DATE="1700000000">go/ci</A>
+        <DT><H3>On Call</H3>
+        <DL><p>
+            <DT><A HREF="https://pager.example.com" TAGS="alerts,Ops">Pager Duty</A>
+        </DL><p>
+    </DL><p>
+    <DT><A HREF="javascript:alert(1)">Bookmarklet</A>
+    <DT><A HREF="https://docs.example.com">Docs &amp; Guides</A>
+    <DT><A HREF="https://other.example.com">docs-guides</A>
+</DL><p>
+"#;
+
+    async fn run_import(dry_run: bool, storage: &Storage) -> BookmarkImport {
+        let params = HashMap::from([("dry_run".to_string(), dry_run.to_string())]);
+        let response =
+            import_bookmarks(params, Role::Admin, Bytes::from(BOOKMARKS), storage.clone())
+                .await
+                .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[test]
+    fn test_parse_bookmarks() {
+        let bookmarks = parse_bookmarks(BOOKMARKS);
+        assert_eq!(bookmarks.len(), 5);
+        assert_eq!(bookmarks[0].url, "https://ci.example.com/?a=1&b=2");
+        assert_eq!(bookmarks[0].add_date, Some(1_700_000_000));
+        assert_eq!(bookmarks[0].tags, vec!["Engineering Tools"]);
+        assert_eq!(
+            bookmarks[1].tags,
+            vec!["Engineering Tools", "On Call", "alerts", "Ops"]
+        );
+        assert_eq!(bookmarks[3].title, "Docs & Guides");
+        assert!(bookmarks[3].tags.is_empty());
+    }
+
+    #[test]
+    fn test_bookmark_short_link() {
+        assert_eq!(bookmark_short_link("go/ci").as_deref(), Some("go/ci"));
+        assert_eq!(
+            bookmark_short_link("Team Wiki!").as_deref(),
+            Some("go/team-wiki")
+        );
+        assert_eq!(bookmark_short_link("GO/Docs").as_deref(), Some("go/docs"));
+        assert_eq!(bookmark_short_link(" ★ "), None);
+    }
+
+    #[tokio::test]
+    async fn test_import_bookmarks_dry_run_and_apply() {
+        let storage: Storage = Arc::new(HashMapStorage::new());
+
+        let preview = run_import(true, &storage).await;
+        assert!(preview.dry_run);
+        assert_eq!(
+            (preview.created, preview.renamed, preview.rejected),
+            (1, 2, 2)
+        );
+        let actions: Vec<BookmarkAction> =
+            preview.entries.iter().map(|entry| entry.action).collect();
+        assert_eq!(
+            actions,
+            vec![
+                BookmarkAction::Create,
+                BookmarkAction::Rename,
+                BookmarkAction::Reject,
+                BookmarkAction::Rename,
+                BookmarkAction::Reject,
+            ]
+        );
+        assert_eq!(
+            preview.entries[1].tags,
+            vec!["engineering-tools", "on-call", "alerts", "ops"]
+        );
+        assert!(storage.get_all().await.unwrap().is_empty());
+
+        let import = run_import(false, &storage).await;
+        assert_eq!((import.created, import.renamed, import.rejected), (1, 2, 2));
+        let golink = storage.get("go/pager-duty").await.unwrap();
+        assert_eq!(
+            golink.tags,
+            vec!["engineering-tools", "on-call", "alerts", "ops"]
+        );
+        let golink = storage.get("go/ci").await.unwrap();
+        assert_eq!(golink.created_at, "2023-11-14T22:13:20+00:00");
+
+        // Everything exists now
+        let preview = run_import(true, &storage).await;
+        assert_eq!(preview.rejected, 5);
+    }
+
+    #[tokio::test]
+    async fn test_import_bookmarks_dry_run_sees_aliases_and_collections() {
+        let storage: Storage = Arc::new(HashMapStorage::new());
+        let golink = Golink {
+            id: Uuid::new_v4().to_string(),
+            short_link: "go/ci".to_string(),
+            url: "https://builds.example.com".to_string(),
+            version: 1,
+            ..Default::default()
+        };
+        storage.create(golink).await.unwrap();
+        storage.rename("go/ci", "go/builds", true).await.unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        storage
+            .create_collection(Collection {
+                id: Uuid::new_v4().to_string(),
+                name: "go/pager-duty".to_string(),
+                title: "Paging".to_string(),
+                description: None,
+                links: Vec::new(),
+                created_at: now.clone(),
+                updated_at: now,
+            })
+            .await
+            .unwrap();
+
+        // The preview rejects exactly what the import then rejects
+        let preview = run_import(true, &storage).await;
+        let import = run_import(false, &storage).await;
+        for entries in [&preview.entries, &import.entries] {
+            assert_eq!(entries[0].action, BookmarkAction::Reject);
+            assert_eq!(entries[0].error.as_deref(), Some("Golink already exists"));
+            assert_eq!(entries[1].action, BookmarkAction::Reject);
+            assert_eq!(entries[1].error.as_deref(), Some("Golink already exists"));
+        }
+        assert_eq!(
+            (preview.created, preview.renamed, preview.rejected),
+            (import.created, import.renamed, import.rejected)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_bookmarks_round_trip() {
+        let storage: Storage = Arc::new(HashMapStorage::new());
+        run_import(false, &storage).await;
+
+        let response = export_bookmarks(storage.clone()).await.unwrap();
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let html = String::from_utf8(body.to_vec()).unwrap();
+
+        let mut bookmarks = parse_bookmarks(&html);
+        bookmarks.sort_by(|a, b| a.title.cmp(&b.title));
+        let exported: Vec<(String, Vec<String>)> = bookmarks
+            .iter()
+            .map(|bookmark| (bookmark.title.clone(), normalize_tags(&bookmark.tags)))
+            .collect();
+        let mut golinks = storage.get_all().await.unwrap();
+        golinks.sort_by(|a, b| a.short_link.cmp(&b.short_link));
+        let stored: Vec<(String, Vec<String>)> = golinks
+            .into_iter()
+            .map(|golink| (golink.short_link, golink.tags))
+            .collect();
+        assert_eq!(exported, stored);
+        assert_eq!(bookmarks[0].url, "https://ci.example.com/?a=1&b=2");
+    }
+
+    #[tokio::test]
+    async fn test_import_bookmarks_requires_admin() {
+        let storage: Storage = Arc::new(HashMapStorage::new());
+        let response =
+            import_bookmarks(HashMap::new(), Role::User, Bytes::from(BOOKMARKS), storage)
+                .await
+                .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,444 @@
+use crate::error::{ApiError, ErrorCode, ErrorResponse};
+use crate::openapi::{MessageResponse, ShortLinkPath};
+use crate::service::{Golink, Storage, validate_golink_pattern};
+use crate::storage::StorageError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+// Upper bound on members, a landing page with more isn't curated anymore
+const MAX_COLLECTION_LINKS: usize = 200;
+
+// A named, ordered group of golinks. The name lives in the golink namespace
+// and resolves to a landing page listing the members.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct Collection {
+    pub id: String,
+    pub name: String,
+    pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    // Short links of the members, in display order
+    #[sqlx(skip)]
+    pub links: Vec<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct CreateCollection {
+    pub name: String,
+    pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub links: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct UpdateCollection {
+    pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub links: Vec<String>,
+}
+
+// Checks that members are unique existing golinks
+async fn validate_links(links: &[String], storage: &Storage) -> Result<(), ApiError> {
+    if links.len() > MAX_COLLECTION_LINKS {
+        return Err(ApiError::new(
+            ErrorCode::InvalidBody,
+            format!(
+                "A collection can contain at most {} golinks",
+                MAX_COLLECTION_LINKS
+            ),
+        ));
+    }
+
+    let mut seen = HashSet::new();
+    for short_link in links {
+        if !seen.insert(short_link) {
+            return Err(ApiError::new(
+                ErrorCode::InvalidBody,
+                format!("Duplicate golink: {}", short_link),
+            ));
+        }
+        if !storage.exists(short_link).await? {
+            return Err(ApiError::new(
+                ErrorCode::InvalidBody,
+                format!("Unknown golink: {}", short_link),
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections",
+    tag = "collections",
+    request_body = CreateCollection,
+    responses(
+        (status = 201, description = "Collection created", body = Collection),
+        (status = 400, description = "Invalid name or members", body = ErrorResponse),
+        (status = 409, description = "Name is taken", body = ErrorResponse),
+    )
+)]
+pub async fn create_collection(
+    create: CreateCollection,
+    storage: Storage,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, ApiError> {
+    validate_golink_pattern(&create.name)
+        .map_err(|e| ApiError::new(ErrorCode::InvalidPattern, e))?;
+    validate_links(&create.links, &storage).await?;
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let collection = Collection {
+        id: Uuid::new_v4().to_string(),
+        name: create.name,
+        title: create.title,
+        description: create.description,
+        links: create.links,
+        created_at: now.clone(),
+        updated_at: now,
+    };
+
+    match storage.create_collection(collection.clone()).await {
+        Ok(_) => Ok(warp::reply::with_status(
+            warp::reply::json(&collection),
+            warp::http::StatusCode::CREATED,
+        )),
+        Err(StorageError::AlreadyExists) => Err(ApiError::new(
+            ErrorCode::GolinkConflict,
+            "A golink or collection with this name already exists",
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/collections/{prefix}/{name}",
+    tag = "collections",
+    params(ShortLinkPath),
+    responses(
+        (status = 200, description = "The collection", body = Collection),
+        (status = 404, description = "Collection not found", body = ErrorResponse),
+    )
+)]
+pub async fn get_collection(
+    name: String,
+    storage: Storage,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, ApiError> {
+    match storage.get_collection(&name).await {
+        Ok(collection) => Ok(warp::reply::with_status(
+            warp::reply::json(&collection),
+            warp::http::StatusCode::OK,
+        )),
+        Err(StorageError::NotFound) => Err(collection_not_found()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/collections",
+    tag = "collections",
+    responses((status = 200, description = "All collections", body = Vec<Collection>))
+)]
+pub async fn get_all_collections(
+    storage: Storage,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, ApiError> {
+    let collections = storage.get_collections().await?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&collections),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+#[utoipa::path(
+    put,
+    path = "/collections/{prefix}/{name}",
+    tag = "collections",
+    params(ShortLinkPath),
+    request_body = UpdateCollection,
+    responses(
+        (status = 200, description = "Collection updated", body = Collection),
+        (status = 400, description = "Invalid members", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse),
+    )
+)]
+pub async fn update_collection(
+    name: String,
+    update: UpdateCollection,
+    storage: Storage,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, ApiError> {
+    validate_links(&update.links, &storage).await?;
+
+    match storage
+        .update_collection(&name, update.title, update.description, update.links)
+        .await
+    {
+        Ok(collection) => Ok(warp::reply::with_status(
+            warp::reply::json(&collection),
+            warp::http::StatusCode::OK,
+        )),
+        Err(StorageError::NotFound) => Err(collection_not_found()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/collections/{prefix}/{name}",
+    tag = "collections",
+    params(ShortLinkPath),
+    responses(
+        (status = 200, description = "Collection deleted", body = MessageResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse),
+    )
+)]
+pub async fn delete_collection(
+    name: String,
+    storage: Storage,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, ApiError> {
+    match storage.delete_collection(&name).await {
+        Ok(_) => Ok(warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({"message": "Collection deleted successfully"})),
+            warp::http::StatusCode::OK,
+        )),
+        Err(StorageError::NotFound) => Err(collection_not_found()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn collection_not_found() -> ApiError {
+    ApiError::new(ErrorCode::CollectionNotFound, "Collection not found")
+}
+
+pub(crate) fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn render_landing_page(collection: &Collection, members: &[Golink]) -> String {
+    let mut items = String::new();
+    for golink in members {
+        // Members link through the redirect so their clicks are counted
+        items.push_str(&format!(
+            "    <li><a href=\"/{name}\">{name}</a>{deprecated}<br><small>{url}</small></li>\n",
+            name = escape_html(&golink.short_link),
+            url = escape_html(&golink.url),
+            deprecated = if golink.deprecated {
+                " (deprecated)"
+            } else {
+                ""
+            },
+        ));
+    }
+    if members.is_empty() {
+        items.push_str("    <li>This collection is empty.</li>\n");
+    }
+
+    let description = collection
+        .description
+        .as_deref()
+        .map(|description| format!("  <p>{}</p>\n", escape_html(description)))
+        .unwrap_or_default();
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n  <meta charset=\"utf-8\">\n  <title>{title}</title>\n</head>\n\
+         <body>\n  <h1>{title}</h1>\n{description}  <ul>\n{items}  </ul>\n</body>\n</html>\n",
+        title = escape_html(&collection.title),
+        description = description,
+        items = items,
+    )
+}
+
+// Landing page for a collection resolved through the redirect route, or None
+// if no collection has this name
+pub(crate) async fn collection_landing_page(
+    name: &str,
+    storage: &Storage,
+) -> Option<warp::reply::Response> {
+    use warp::Reply;
+
+    let collection = match storage.get_collection(name).await {
+        Ok(collection) => collection,
+        Err(StorageError::NotFound) => return None,
+        Err(e) => return Some(ApiError::from(e).into_response()),
+    };
+
+    let mut members = Vec::with_capacity(collection.links.len());
+    for short_link in &collection.links {
+        match storage.get(short_link).await {
+            Ok(golink) => members.push(golink),
+            Err(StorageError::NotFound) => continue,
+            Err(e) => return Some(ApiError::from(e).into_response()),
+        }
+    }
+
+    Some(warp::reply::html(render_landing_page(&collection, &members)).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::HashMapStorage;
+    use std::sync::Arc;
+    use warp::Reply;
+
+    async fn create_test_storage() -> Storage {
+        let storage: Storage = Arc::new(HashMapStorage::new());
+        for (short_link, url) in [
+            ("go/docs", "https://docs.example.com"),
+            ("go/chat", "https://chat.example.com/?a=1&b=2"),
+        ] {
+            storage
+                .create(Golink {
+                    id: Uuid::new_v4().to_string(),
+                    short_link: short_link.to_string(),
+                    url: url.to_string(),
+                    created_at: chrono::Utc::now().to_rfc3339(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+        storage
+    }
+
+    fn create_request(name: &str, links: &[&str]) -> CreateCollection {
+        CreateCollection {
+            name: name.to_string(),
+            title: "New engineer essentials".to_string(),
+            links: links.iter().map(|link| link.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_collection() {
+        let storage = create_test_storage().await;
+
+        let response = create_collection(
+            create_request("go/onboarding", &["go/docs"]),
+            storage.clone(),
+        )
+        .await
+        .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::CREATED);
+
+        // Names are shared with golinks
+        let response = create_collection(create_request("go/docs", &[]), storage)
+            .await
+            .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_create_collection_validates_links() {
+        let storage = create_test_storage().await;
+
+        let cases = [
+            create_request("invalid", &[]),
+            create_request("go/onboarding", &["go/missing"]),
+            create_request("go/onboarding", &["go/docs", "go/docs"]),
+        ];
+        for request in cases {
+            let response = create_collection(request, storage.clone())
+                .await
+                .into_response();
+            assert_eq!(response.status(), warp::http::StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_and_delete_collection() {
+        let storage = create_test_storage().await;
+        create_collection(
+            create_request("go/onboarding", &["go/docs"]),
+            storage.clone(),
+        )
+        .await
+        .unwrap();
+
+        let update = UpdateCollection {
+            title: "Day one".to_string(),
+            links: vec!["go/chat".to_string(), "go/docs".to_string()],
+            ..Default::default()
+        };
+        let response = update_collection("go/onboarding".to_string(), update, storage.clone())
+            .await
+            .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let collection: Collection = serde_json::from_slice(&body).unwrap();
+        assert_eq!(collection.title, "Day one");
+        assert_eq!(collection.links, vec!["go/chat", "go/docs"]);
+
+        let response = delete_collection("go/onboarding".to_string(), storage.clone())
+            .await
+            .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+
+        let response = get_collection("go/onboarding".to_string(), storage)
+            .await
+            .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_collection_landing_page() {
+        let storage = create_test_storage().await;
+        create_collection(
+            create_request("go/onboarding", &["go/chat", "go/docs"]),
+            storage.clone(),
+        )
+        .await
+        .unwrap();
+
+        assert!(
+            collection_landing_page("go/missing", &storage)
+                .await
+                .is_none()
+        );
+
+        let response = collection_landing_page("go/onboarding", &storage)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(html.contains("<h1>New engineer essentials</h1>"));
+        assert!(html.contains("https://chat.example.com/?a=1&amp;b=2"));
+        // Members keep their order
+        assert!(html.find("go/chat").unwrap() < html.find("go/docs").unwrap());
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html("<a href=\"x\">Tom & Jerry's</a>"),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;"
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use chrono::{DateTime, Utc};
+use warp::Filter;
+
+// Validators a client sends to skip downloading a representation it already has
+#[derive(Debug, Clone, Default)]
+pub struct Conditional {
+    pub if_none_match: Option<String>,
+    pub if_modified_since: Option<String>,
+}
+
+impl Conditional {
+    // Whether the client's copy, identified by `etag` and last changed at
+    // `last_modified`, is still current. As in RFC 9110, `If-None-Match` is
+    // compared weakly and `If-Modified-Since` is ignored when both are sent.
+    pub fn is_fresh(&self, etag: &str, last_modified: Option<DateTime<Utc>>) -> bool {
+        if let Some(if_none_match) = &self.if_none_match {
+            return if_none_match
+                .split(',')
+                .map(str::trim)
+                .any(|tag| tag == "*" || opaque_tag(tag) == opaque_tag(etag));
+        }
+        match (&self.if_modified_since, last_modified) {
+            (Some(since), Some(last_modified)) => parse_http_date(since)
+                .is_some_and(|since| last_modified.timestamp() <= since.timestamp()),
+            _ => false,
+        }
+    }
+}
+
+pub fn with_conditional() -> impl Filter<Extract = (Conditional,), Error = warp::Rejection> + Clone
+{
+    warp::header::optional::<String>("if-none-match")
+        .and(warp::header::optional::<String>("if-modified-since"))
+        .map(|if_none_match, if_modified_since| Conditional {
+            if_none_match,
+            if_modified_since,
+        })
+}
+
+fn opaque_tag(tag: &str) -> &str {
+    tag.strip_prefix("W/").unwrap_or(tag)
+}
+
+// IMF-fixdate, the format of `Last-Modified`
+pub fn http_date(time: DateTime<Utc>) -> String {
+    time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+fn parse_http_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(value.trim())
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+// Stored timestamps are RFC 3339
+pub fn parse_rfc3339(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+// Adds `ETag` and `Last-Modified` so the client can revalidate later
+pub fn with_validators(
+    mut response: warp::reply::Response,
+    etag: &str,
+    last_modified: Option<DateTime<Utc>>,
+) -> warp::reply::Response {
+    let headers = response.headers_mut();
+    if let Ok(etag) = warp::http::HeaderValue::from_str(etag) {
+        headers.insert(warp::http::header::ETAG, etag);
+    }
+    if let Some(last_modified) = last_modified
+        && let Ok(last_modified) = warp::http::HeaderValue::from_str(&http_date(last_modified))
+    {
+        headers.insert(warp::http::header::LAST_MODIFIED, last_modified);
+    }
+    response
+}
+
+// Empty 304 telling the client to keep using its copy
+pub fn not_modified(etag: &str, last_modified: Option<DateTime<Utc>>) -> warp::reply::Response {
+    let mut response = warp::reply::Response::default();
+    *response.status_mut() = warp::http::StatusCode::NOT_MODIFIED;
+    with_validators(response, etag, last_modified)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conditional(if_none_match: Option<&str>, if_modified_since: Option<&str>) -> Conditional {
+        Conditional {
+            if_none_match: if_none_match.map(String::from),
+            if_modified_since: if_modified_since.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_is_fresh() {
+        let etag = "\"abc-3\"";
+        let changed = parse_rfc3339("2025-08-15T17:04:29.533+00:00");
+
+        assert!(!conditional(None, None).is_fresh(etag, changed));
+        assert!(conditional(Some("\"abc-3\""), None).is_fresh(etag, changed));
+        assert!(conditional(Some("W/\"abc-3\""), None).is_fresh(etag, changed));
+        assert!(conditional(Some("\"abc-2\", \"abc-3\""), None).is_fresh(etag, changed));
+        assert!(conditional(Some("*"), None).is_fresh(etag, changed));
+        assert!(!conditional(Some("\"abc-2\""), None).is_fresh(etag, changed));
+
+        let since = http_date(changed.unwrap());
+        assert_eq!(since, "Fri, 15 Aug 2025 17:04:29 GMT");
+        assert!(conditional(None, Some(&since)).is_fresh(etag, changed));
+        assert!(!conditional(None, Some("Fri, 15 Aug 2025 17:04:28 GMT")).is_fresh(etag, changed));
+        assert!(!conditional(None, Some("yesterday")).is_fresh(etag, changed));
+        assert!(!conditional(None, Some(&since)).is_fresh(etag, None));
+
+        // A stale ETag isn't rescued by a recent date
+        assert!(!conditional(Some("\"abc-2\""), Some(&since)).is_fresh(etag, changed));
+    }
+}
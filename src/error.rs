@@ -0,0 +1,316 @@
+use crate::storage::StorageError;
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::error::Error;
+use utoipa::ToSchema;
+use warp::http::StatusCode;
+use warp::http::header::{ACCEPT, CONTENT_TYPE, HeaderMap, HeaderValue};
+
+// Machine-readable reason of an error response. Clients match on these, so a
+// code never changes meaning once released; new situations get new codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    // A golink or collection name doesn't match `go/[a-zA-Z0-9_-]+`
+    InvalidPattern,
+    InvalidParameter,
+    InvalidBody,
+    // A request header couldn't be read
+    InvalidHeader,
+    MissingToken,
+    InvalidToken,
+    // The endpoint needs a personal token from USER_TOKENS
+    IdentityRequired,
+    AdminRequired,
+    GolinkLocked,
+    RouteNotFound,
+    GolinkNotFound,
+    CollectionNotFound,
+    FavoriteNotFound,
+    // The path exists but not with this method, see the `Allow` header
+    MethodNotAllowed,
+    // The name is taken by a golink, alias or collection
+    GolinkConflict,
+    // `If-Match` didn't match the golink's current ETag
+    GolinkChanged,
+    // The body needs a `Content-Length` header
+    LengthRequired,
+    PayloadTooLarge,
+    // The body's `Content-Type` isn't one the endpoint reads
+    UnsupportedMediaType,
+    // The stored URL can't be sent as a `Location` header
+    InvalidRedirect,
+    Internal,
+}
+
+impl ErrorCode {
+    pub fn status(self) -> StatusCode {
+        match self {
+            ErrorCode::InvalidPattern
+            | ErrorCode::InvalidParameter
+            | ErrorCode::InvalidBody
+            | ErrorCode::InvalidHeader => StatusCode::BAD_REQUEST,
+            ErrorCode::MissingToken | ErrorCode::InvalidToken | ErrorCode::IdentityRequired => {
+                StatusCode::UNAUTHORIZED
+            }
+            ErrorCode::AdminRequired | ErrorCode::GolinkLocked => StatusCode::FORBIDDEN,
+            ErrorCode::RouteNotFound
+            | ErrorCode::GolinkNotFound
+            | ErrorCode::CollectionNotFound
+            | ErrorCode::FavoriteNotFound => StatusCode::NOT_FOUND,
+            ErrorCode::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+            ErrorCode::GolinkConflict => StatusCode::CONFLICT,
+            ErrorCode::GolinkChanged => StatusCode::PRECONDITION_FAILED,
+            ErrorCode::LengthRequired => StatusCode::LENGTH_REQUIRED,
+            ErrorCode::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ErrorCode::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ErrorCode::InvalidRedirect => StatusCode::BAD_GATEWAY,
+            ErrorCode::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+// The one error type of the HTTP API. Handlers return it as their error
+// reply, filters reject with it, and both end up as the same JSON body.
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.code.status()
+    }
+
+    fn problem(&self) -> ProblemDetails {
+        let status = self.status();
+        ProblemDetails {
+            problem_type: "about:blank".to_string(),
+            title: status.canonical_reason().unwrap_or_default().to_string(),
+            status: status.as_u16(),
+            detail: self.message.clone(),
+            code: self.code,
+        }
+    }
+}
+
+// Database errors are logged here and never shown to clients
+impl From<StorageError> for ApiError {
+    fn from(error: StorageError) -> Self {
+        match error {
+            StorageError::NotFound => ApiError::new(ErrorCode::GolinkNotFound, "Golink not found"),
+            StorageError::AlreadyExists => {
+                ApiError::new(ErrorCode::GolinkConflict, "Golink already exists")
+            }
+            StorageError::VersionMismatch => ApiError::new(
+                ErrorCode::GolinkChanged,
+                "Golink was changed by someone else",
+            ),
+            StorageError::Locked => ApiError::new(
+                ErrorCode::GolinkLocked,
+                "Golink is locked and can only be changed by an admin",
+            ),
+            StorageError::DatabaseError(e) => {
+                eprintln!("Database error: {}", e);
+                ApiError::new(ErrorCode::Internal, "Internal server error")
+            }
+        }
+    }
+}
+
+impl warp::Reply for ApiError {
+    fn into_response(self) -> warp::reply::Response {
+        let body = ErrorResponse {
+            error: self.message.clone(),
+            code: self.code,
+        };
+        let mut response =
+            warp::reply::with_status(warp::reply::json(&body), self.status()).into_response();
+        // Kept for `negotiate`, which may render it as problem details instead
+        response.extensions_mut().insert(self);
+        response
+    }
+}
+
+impl warp::reject::Reject for ApiError {}
+
+// Turns whatever rejected the request into an error reply. Handlers and the
+// auth filters reject with an `ApiError` already, the rest comes from warp's
+// own filters.
+pub async fn handle_rejection(err: warp::Rejection) -> Result<ApiError, Infallible> {
+    Ok(rejection_error(&err))
+}
+
+fn rejection_error(err: &warp::Rejection) -> ApiError {
+    if let Some(error) = err.find::<ApiError>() {
+        return error.clone();
+    }
+    // The serde error says where in the body parsing stopped
+    if let Some(error) = err.find::<warp::body::BodyDeserializeError>() {
+        let cause = error
+            .source()
+            .map_or_else(|| error.to_string(), |cause| cause.to_string());
+        return ApiError::new(
+            ErrorCode::InvalidBody,
+            format!("Invalid JSON body: {}", cause),
+        );
+    }
+    if err.find::<warp::reject::LengthRequired>().is_some() {
+        return ApiError::new(
+            ErrorCode::LengthRequired,
+            "A Content-Length header is required",
+        );
+    }
+    if err.find::<warp::reject::PayloadTooLarge>().is_some() {
+        return ApiError::new(ErrorCode::PayloadTooLarge, "Request body is too large");
+    }
+    if err.find::<warp::reject::UnsupportedMediaType>().is_some() {
+        return ApiError::new(
+            ErrorCode::UnsupportedMediaType,
+            "Unsupported Content-Type. Expected application/json",
+        );
+    }
+    if err.find::<warp::reject::InvalidQuery>().is_some() {
+        return ApiError::new(ErrorCode::InvalidParameter, "Invalid query string");
+    }
+    if let Some(error) = err.find::<warp::reject::InvalidHeader>() {
+        return ApiError::new(ErrorCode::InvalidHeader, error.to_string());
+    }
+    if let Some(error) = err.find::<warp::reject::MissingHeader>() {
+        return ApiError::new(ErrorCode::InvalidHeader, error.to_string());
+    }
+    // Only some route with this path refused the method, `Allow` is added by
+    // `routes::routes` which knows the path
+    if err.find::<warp::reject::MethodNotAllowed>().is_some() {
+        return ApiError::new(
+            ErrorCode::MethodNotAllowed,
+            "Method not allowed for this path",
+        );
+    }
+    if err.is_not_found() {
+        return ApiError::new(ErrorCode::RouteNotFound, "No route matches the path");
+    }
+
+    eprintln!("Unhandled rejection: {:?}", err);
+    ApiError::new(ErrorCode::Internal, "Internal server error")
+}
+
+// Body of every error response
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: ErrorCode,
+}
+
+// RFC 7807 body of error responses for clients accepting `application/problem+json`
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    pub code: ErrorCode,
+}
+
+const PROBLEM_JSON: &str = "application/problem+json";
+
+fn accepts_problem_json(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|media| {
+            let media = media.split(';').next().unwrap_or_default().trim();
+            media.eq_ignore_ascii_case(PROBLEM_JSON)
+        })
+}
+
+// Turns API errors into problem details when the request's `Accept` lists
+// `application/problem+json`. Other responses pass through untouched.
+pub fn negotiate(headers: &HeaderMap, reply: impl warp::Reply) -> warp::reply::Response {
+    let response = reply.into_response();
+    let problem = match response.extensions().get::<ApiError>() {
+        Some(error) if accepts_problem_json(headers) => error.problem(),
+        _ => return response,
+    };
+
+    let (mut parts, _) = response.into_parts();
+    parts
+        .headers
+        .insert(CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+    let body = serde_json::to_vec(&problem).unwrap_or_default();
+    warp::reply::Response::from_parts(parts, body.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn body(response: warp::reply::Response) -> serde_json::Value {
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[test]
+    fn test_storage_errors() {
+        let error = ApiError::from(StorageError::NotFound);
+        assert_eq!(error.code, ErrorCode::GolinkNotFound);
+        assert_eq!(error.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            ApiError::from(StorageError::AlreadyExists).status(),
+            StatusCode::CONFLICT
+        );
+        assert_eq!(
+            ApiError::from(StorageError::VersionMismatch).status(),
+            StatusCode::PRECONDITION_FAILED
+        );
+
+        // Database details stay in the log
+        let error = ApiError::from(StorageError::DatabaseError("disk I/O error".to_string()));
+        assert_eq!(error.code, ErrorCode::Internal);
+        assert!(!error.message.contains("disk"));
+    }
+
+    #[tokio::test]
+    async fn test_negotiate() {
+        let error = ApiError::new(ErrorCode::InvalidPattern, "Invalid golink pattern");
+
+        let mut headers = HeaderMap::new();
+        let response = negotiate(&headers, error.clone());
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+        let json = body(response).await;
+        assert_eq!(json["error"], "Invalid golink pattern");
+        assert_eq!(json["code"], "invalid_pattern");
+
+        headers.insert(
+            ACCEPT,
+            HeaderValue::from_static("application/json;q=0.5, Application/Problem+JSON"),
+        );
+        let response = negotiate(&headers, error);
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.headers()[CONTENT_TYPE], PROBLEM_JSON);
+        let json = body(response).await;
+        assert_eq!(json["type"], "about:blank");
+        assert_eq!(json["title"], "Bad Request");
+        assert_eq!(json["status"], 400);
+        assert_eq!(json["detail"], "Invalid golink pattern");
+        assert_eq!(json["code"], "invalid_pattern");
+
+        // Successful responses are left alone
+        let response = negotiate(&headers, warp::reply::json(&"ok"));
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+    }
+}
@@ -0,0 +1,154 @@
+use crate::error::{ApiError, ErrorCode, ErrorResponse};
+use crate::openapi::{MessageResponse, ShortLinkPath};
+use crate::service::{Favorite, Storage};
+use crate::storage::StorageError;
+
+// Stars a golink for the calling user. Starring it again is a no-op.
+#[utoipa::path(
+    put,
+    path = "/golinks/{prefix}/{name}/favorite",
+    tag = "favorites",
+    params(ShortLinkPath),
+    responses(
+        (status = 200, description = "Golink starred", body = Favorite),
+        (status = 401, description = "Personal token required", body = ErrorResponse),
+        (status = 404, description = "Golink not found", body = ErrorResponse),
+    )
+)]
+pub async fn favorite_golink(
+    short_link: String,
+    user: String,
+    storage: Storage,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, ApiError> {
+    let favorite = storage.add_favorite(&user, &short_link).await?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&favorite),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/golinks/{prefix}/{name}/favorite",
+    tag = "favorites",
+    params(ShortLinkPath),
+    responses(
+        (status = 200, description = "Star removed", body = MessageResponse),
+        (status = 401, description = "Personal token required", body = ErrorResponse),
+        (status = 404, description = "Favorite not found", body = ErrorResponse),
+    )
+)]
+pub async fn unfavorite_golink(
+    short_link: String,
+    user: String,
+    storage: Storage,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, ApiError> {
+    match storage.remove_favorite(&user, &short_link).await {
+        Ok(_) => Ok(warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({"message": "Favorite removed successfully"})),
+            warp::http::StatusCode::OK,
+        )),
+        Err(StorageError::NotFound) => Err(ApiError::new(
+            ErrorCode::FavoriteNotFound,
+            "Favorite not found",
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// The calling user's starred golinks, most recently starred first
+#[utoipa::path(
+    get,
+    path = "/me/favorites",
+    tag = "favorites",
+    responses(
+        (status = 200, description = "Starred golinks, most recent first", body = Vec<Favorite>),
+        (status = 401, description = "Personal token required", body = ErrorResponse),
+    )
+)]
+pub async fn get_my_favorites(
+    user: String,
+    storage: Storage,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, ApiError> {
+    let favorites = storage.get_favorites(&user).await?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&favorites),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::Golink;
+    use crate::storage::HashMapStorage;
+    use std::sync::Arc;
+    use warp::Reply;
+
+    async fn create_test_storage() -> Storage {
+        let storage: Storage = Arc::new(HashMapStorage::new());
+        storage
+            .create(Golink {
+                id: uuid::Uuid::new_v4().to_string(),
+                short_link: "go/test".to_string(),
+                url: "https://example.com".to_string(),
+                created_at: chrono::Utc::now().to_rfc3339(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        storage
+    }
+
+    async fn favorites_of(user: &str, storage: Storage) -> Vec<Favorite> {
+        let response = get_my_favorites(user.to_string(), storage)
+            .await
+            .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_favorite_and_unfavorite() {
+        let storage = create_test_storage().await;
+
+        let response = favorite_golink("go/test".to_string(), "alice".to_string(), storage.clone())
+            .await
+            .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+
+        let favorites = favorites_of("alice", storage.clone()).await;
+        assert_eq!(favorites.len(), 1);
+        assert_eq!(favorites[0].golink.url, "https://example.com");
+        assert!(favorites_of("bob", storage.clone()).await.is_empty());
+
+        let response =
+            unfavorite_golink("go/test".to_string(), "alice".to_string(), storage.clone())
+                .await
+                .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+        assert!(favorites_of("alice", storage).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_favorite_not_found() {
+        let storage = create_test_storage().await;
+
+        let response = favorite_golink(
+            "go/nonexistent".to_string(),
+            "alice".to_string(),
+            storage.clone(),
+        )
+        .await
+        .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::NOT_FOUND);
+
+        let response = unfavorite_golink("go/test".to_string(), "alice".to_string(), storage)
+            .await
+            .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::NOT_FOUND);
+    }
+}
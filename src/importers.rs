@@ -0,0 +1,569 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+// Separates tags within the CSV `tags` column
+pub(crate) const CSV_TAG_SEPARATOR: char = ';';
+
+// One row of an import. Everything but the name and URL is optional so that
+// hand-written files work as well as our own exports.
+#[derive(Debug, Default, Deserialize)]
+pub struct ImportRecord {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub short_link: String,
+    pub url: String,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+    #[serde(default)]
+    pub owner: Option<String>,
+    #[serde(default)]
+    pub locked: bool,
+    #[serde(default)]
+    pub deprecated: bool,
+    #[serde(default)]
+    pub clicks: i64,
+    #[serde(default)]
+    pub last_used_at: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+// 1-based row number and the parsed record, or why the row couldn't be read
+pub type ImportRow = (usize, Result<ImportRecord, String>);
+
+// Turns an import file into rows. Implement this to load golinks from another
+// tool; `transfer::import_with` takes care of validation and conflicts.
+pub trait Importer: Send + Sync {
+    // Value of the `format` query parameter that selects this importer
+    fn format(&self) -> &'static str;
+
+    // Parses the whole input. A malformed document fails as a whole, a
+    // malformed row only fails that row.
+    fn parse(&self, input: &str) -> Result<Vec<ImportRow>, String>;
+}
+
+// Every importer available through `POST /golinks/import`
+pub static IMPORTERS: &[&dyn Importer] = &[
+    &JsonImporter,
+    &CsvImporter,
+    &NdjsonImporter,
+    &GolinkImporter,
+    &YamlImporter,
+];
+
+pub fn find_importer(format: &str) -> Option<&'static dyn Importer> {
+    IMPORTERS
+        .iter()
+        .copied()
+        .find(|importer| importer.format() == format)
+}
+
+// Splits CSV text into records. Handles quoted fields with embedded
+// separators, doubled quotes and line breaks.
+fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err("Invalid CSV: unterminated quoted field".to_string());
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    // Blank lines carry no data
+    records.retain(|record| !(record.len() == 1 && record[0].is_empty()));
+    Ok(records)
+}
+
+fn optional_field(value: &str) -> Option<String> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+fn csv_record(columns: &[String], values: &[String]) -> Result<ImportRecord, String> {
+    if values.len() != columns.len() {
+        return Err(format!(
+            "Expected {} fields, found {}",
+            columns.len(),
+            values.len()
+        ));
+    }
+
+    let mut record = ImportRecord::default();
+    for (column, value) in columns.iter().zip(values) {
+        match column.as_str() {
+            "id" => record.id = optional_field(value),
+            "short_link" => record.short_link = value.clone(),
+            "url" => record.url = value.clone(),
+            "created_at" => record.created_at = optional_field(value),
+            "updated_at" => record.updated_at = optional_field(value),
+            "owner" => record.owner = optional_field(value),
+            "last_used_at" => record.last_used_at = optional_field(value),
+            "description" => record.description = optional_field(value),
+            "tags" => record.tags = value.split(CSV_TAG_SEPARATOR).map(str::to_string).collect(),
+            "locked" | "deprecated" => {
+                let flag = match value.as_str() {
+                    "" | "false" => false,
+                    "true" => true,
+                    _ => return Err(format!("Invalid {}. Must be 'true' or 'false'", column)),
+                };
+                if column == "locked" {
+                    record.locked = flag;
+                } else {
+                    record.deprecated = flag;
+                }
+            }
+            "clicks" => {
+                record.clicks = match value.as_str() {
+                    "" => 0,
+                    _ => value
+                        .parse()
+                        .map_err(|_| "Invalid clicks. Must be a number".to_string())?,
+                }
+            }
+            // Unknown columns are ignored so exports from newer versions still load
+            _ => {}
+        }
+    }
+    Ok(record)
+}
+
+// Our own JSON export: an array of golinks
+pub struct JsonImporter;
+
+impl Importer for JsonImporter {
+    fn format(&self) -> &'static str {
+        "json"
+    }
+
+    fn parse(&self, input: &str) -> Result<Vec<ImportRow>, String> {
+        let values: Vec<serde_json::Value> = serde_json::from_str(input)
+            .map_err(|_| "Invalid JSON. Expected an array of golinks".to_string())?;
+        Ok(values
+            .into_iter()
+            .enumerate()
+            .map(|(index, value)| {
+                let record = serde_json::from_value(value).map_err(|e| e.to_string());
+                (index + 1, record)
+            })
+            .collect())
+    }
+}
+
+// Our own NDJSON export: one golink per line
+pub struct NdjsonImporter;
+
+impl Importer for NdjsonImporter {
+    fn format(&self) -> &'static str {
+        "ndjson"
+    }
+
+    fn parse(&self, input: &str) -> Result<Vec<ImportRow>, String> {
+        Ok(input
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                let record = serde_json::from_str(line).map_err(|e| e.to_string());
+                (index + 1, record)
+            })
+            .collect())
+    }
+}
+
+// Our own CSV export, or any CSV with at least `short_link` and `url` columns
+pub struct CsvImporter;
+
+impl Importer for CsvImporter {
+    fn format(&self) -> &'static str {
+        "csv"
+    }
+
+    fn parse(&self, input: &str) -> Result<Vec<ImportRow>, String> {
+        let mut records = parse_csv(input)?.into_iter();
+        let columns = records.next().ok_or("Invalid CSV: missing header row")?;
+        for required in ["short_link", "url"] {
+            if !columns.iter().any(|column| column == required) {
+                return Err(format!("Invalid CSV: missing '{}' column", required));
+            }
+        }
+        Ok(records
+            .enumerate()
+            .map(|(index, values)| (index + 1, csv_record(&columns, &values)))
+            .collect())
+    }
+}
+
+// Other tools store names without our `go/` prefix
+fn prefixed_short_link(name: &str) -> String {
+    let name = name.trim();
+    if name.starts_with("go/") {
+        name.to_string()
+    } else {
+        format!("go/{}", name)
+    }
+}
+
+// Normalizes a timestamp to the UTC RFC 3339 form that stored timestamps are
+// compared in
+pub(crate) fn rfc3339(value: &str, field: &str) -> Result<String, String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc).to_rfc3339())
+        .map_err(|_| format!("Invalid {}. Must be an RFC 3339 timestamp", field))
+}
+
+// One link in the export of the open-source golink service (tailscale/golink)
+#[derive(Debug, Deserialize)]
+struct GolinkExportEntry {
+    #[serde(rename = "Short", alias = "short")]
+    short: String,
+    #[serde(rename = "Long", alias = "long")]
+    long: String,
+    #[serde(default, rename = "Owner", alias = "owner")]
+    owner: Option<String>,
+    #[serde(default, rename = "Created", alias = "created")]
+    created: Option<String>,
+    #[serde(default, rename = "LastEdit", alias = "last_edit")]
+    last_edit: Option<String>,
+    #[serde(default, rename = "Clicks", alias = "clicks")]
+    clicks: i64,
+}
+
+impl GolinkExportEntry {
+    fn into_record(self) -> Result<ImportRecord, String> {
+        Ok(ImportRecord {
+            short_link: prefixed_short_link(&self.short),
+            url: self.long,
+            owner: self.owner.filter(|owner| !owner.is_empty()),
+            created_at: self
+                .created
+                .map(|created| rfc3339(&created, "Created"))
+                .transpose()?,
+            updated_at: self
+                .last_edit
+                .map(|last_edit| rfc3339(&last_edit, "LastEdit"))
+                .transpose()?,
+            clicks: self.clicks,
+            ..Default::default()
+        })
+    }
+}
+
+// The `/.export` dump of the open-source golink service: one JSON object per
+// line with `Short`, `Long`, `Owner`, `Created` and `LastEdit`, plus `Clicks`
+// where the dump has them. A JSON array of the same objects works too.
+pub struct GolinkImporter;
+
+impl GolinkImporter {
+    fn entry(value: serde_json::Value) -> Result<ImportRecord, String> {
+        serde_json::from_value::<GolinkExportEntry>(value)
+            .map_err(|e| e.to_string())
+            .and_then(GolinkExportEntry::into_record)
+    }
+}
+
+impl Importer for GolinkImporter {
+    fn format(&self) -> &'static str {
+        "golink"
+    }
+
+    fn parse(&self, input: &str) -> Result<Vec<ImportRow>, String> {
+        if input.trim_start().starts_with('[') {
+            let values: Vec<serde_json::Value> = serde_json::from_str(input)
+                .map_err(|_| "Invalid JSON. Expected an array of links".to_string())?;
+            return Ok(values
+                .into_iter()
+                .enumerate()
+                .map(|(index, value)| (index + 1, Self::entry(value)))
+                .collect());
+        }
+        Ok(input
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                let record = serde_json::from_str(line)
+                    .map_err(|e| e.to_string())
+                    .and_then(Self::entry);
+                (index + 1, record)
+            })
+            .collect())
+    }
+}
+
+// Drops a trailing `# comment`. A `#` only starts a comment at the beginning
+// or after whitespace, so URL fragments survive.
+fn strip_yaml_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (index, c) in line.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' && previous.is_whitespace() => return &line[..index],
+            None => {}
+        }
+        previous = c;
+    }
+    line
+}
+
+fn yaml_scalar(value: &str) -> String {
+    let value = value.trim();
+    for quote in ['"', '\''] {
+        if value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote) {
+            return value[1..value.len() - 1].to_string();
+        }
+    }
+    value.to_string()
+}
+
+// `[a, b]` flow sequences, or a single value
+fn yaml_list(value: &str) -> Vec<String> {
+    let value = value.trim();
+    match value
+        .strip_prefix('[')
+        .and_then(|list| list.strip_suffix(']'))
+    {
+        Some(list) => list
+            .split(',')
+            .map(yaml_scalar)
+            .filter(|item| !item.is_empty())
+            .collect(),
+        None => vec![yaml_scalar(value)],
+    }
+}
+
+// Splits `key: value` on the first colon followed by whitespace or the end of
+// the line, so values like `https://...` stay intact
+fn yaml_key_value(line: &str) -> Option<(String, &str)> {
+    let mut quote = None;
+    for (index, c) in line.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == ':' => {
+                let rest = &line[index + 1..];
+                if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+                    return Some((yaml_scalar(&line[..index]), rest.trim()));
+                }
+            }
+            None => {}
+        }
+    }
+    None
+}
+
+// A hand-written YAML mapping from names to URLs. A name either maps straight
+// to a URL or to a block with `url` and optional `owner`, `description` and `tags`:
+//
+//     docs: https://docs.example.com
+//     go/oncall:
+//       url: https://pager.example.com
+//       owner: sre
+//       tags: [ops, alerts]
+//
+// Only this subset of YAML is understood. Rows are numbered by line.
+pub struct YamlImporter;
+
+impl Importer for YamlImporter {
+    fn format(&self) -> &'static str {
+        "yaml"
+    }
+
+    fn parse(&self, input: &str) -> Result<Vec<ImportRow>, String> {
+        let mut rows: Vec<ImportRow> = Vec::new();
+        // The block currently being filled in, if its row is still valid
+        let mut block: Option<usize> = None;
+
+        for (index, raw_line) in input.lines().enumerate() {
+            let line_number = index + 1;
+            let line = strip_yaml_comment(raw_line).trim_end();
+            if line.trim().is_empty() || line == "---" {
+                continue;
+            }
+
+            let nested = line.starts_with(char::is_whitespace);
+            let Some((key, value)) = yaml_key_value(line.trim_start()) else {
+                let error = Err(format!(
+                    "Invalid line {}. Expected 'name: url'",
+                    line_number
+                ));
+                if nested && let Some(row) = block {
+                    rows[row].1 = error;
+                } else {
+                    rows.push((line_number, error));
+                    block = None;
+                }
+                continue;
+            };
+
+            if !nested {
+                let record = ImportRecord {
+                    short_link: prefixed_short_link(&key),
+                    url: yaml_scalar(value),
+                    ..Default::default()
+                };
+                block = value.is_empty().then_some(rows.len());
+                rows.push((line_number, Ok(record)));
+                continue;
+            }
+
+            let Some(row) = block else {
+                rows.push((
+                    line_number,
+                    Err(format!(
+                        "Line {} is indented but not inside a golink",
+                        line_number
+                    )),
+                ));
+                continue;
+            };
+            let Ok(record) = &mut rows[row].1 else {
+                continue;
+            };
+            match key.as_str() {
+                "url" => record.url = yaml_scalar(value),
+                "owner" => record.owner = Some(yaml_scalar(value)).filter(|o| !o.is_empty()),
+                "tags" => record.tags = yaml_list(value),
+                "description" => {
+                    record.description = Some(yaml_scalar(value)).filter(|d| !d.is_empty())
+                }
+                _ => rows[row].1 = Err(format!("Unknown field '{}'", key)),
+            }
+        }
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv() {
+        let records =
+            parse_csv("a,b,c\r\n\"x, y\",\"say \"\"hi\"\"\",\"multi\nline\"\n\n1,,3").unwrap();
+        assert_eq!(
+            records,
+            vec![
+                vec!["a", "b", "c"],
+                vec!["x, y", "say \"hi\"", "multi\nline"],
+                vec!["1", "", "3"],
+            ]
+        );
+        assert!(parse_csv("a,\"b").is_err());
+    }
+
+    #[test]
+    fn test_find_importer() {
+        for importer in IMPORTERS {
+            let found = find_importer(importer.format()).unwrap();
+            assert_eq!(found.format(), importer.format());
+        }
+        assert!(find_importer("xml").is_none());
+    }
+
+    #[test]
+    fn test_golink_importer() {
+        let input = r#"{"Short":"docs","Long":"https://docs.example.com","Created":"2022-06-02T19:16:58.927876Z","LastEdit":"2023-01-05T10:00:00-05:00","Owner":"alice@example.com","Clicks":12}
+{"Short":"bad name","Long":"https://example.com"}
+{"Short":"wiki","Long":"https://wiki.example.com","Created":"yesterday"}
+"#;
+        let rows = GolinkImporter.parse(input).unwrap();
+        assert_eq!(rows.len(), 3);
+
+        let (row, record) = &rows[0];
+        let record = record.as_ref().unwrap();
+        assert_eq!(*row, 1);
+        assert_eq!(record.short_link, "go/docs");
+        assert_eq!(record.url, "https://docs.example.com");
+        assert_eq!(record.owner.as_deref(), Some("alice@example.com"));
+        assert_eq!(record.clicks, 12);
+        assert_eq!(
+            record.created_at.as_deref(),
+            Some("2022-06-02T19:16:58.927876+00:00")
+        );
+        assert_eq!(
+            record.updated_at.as_deref(),
+            Some("2023-01-05T15:00:00+00:00")
+        );
+
+        // Invalid names are caught by the import itself, not the parser
+        assert_eq!(rows[1].1.as_ref().unwrap().short_link, "go/bad name");
+        assert!(rows[2].1.is_err());
+
+        let array = r#"[{"short": "docs", "long": "https://docs.example.com"}]"#;
+        let rows = GolinkImporter.parse(array).unwrap();
+        assert_eq!(rows[0].1.as_ref().unwrap().short_link, "go/docs");
+    }
+
+    #[test]
+    fn test_yaml_importer() {
+        let input = "# Team links\n\
+            docs: https://docs.example.com/#/start # the docs\n\
+            \n\
+            \"go/oncall\":\n\
+            \x20 url: 'https://pager.example.com'\n\
+            \x20 owner: sre\n\
+            \x20 tags: [ops, \"alerts\"]\n\
+            broken:\n\
+            \x20 color: blue\n\
+            not yaml at all\n";
+        let rows = YamlImporter.parse(input).unwrap();
+        assert_eq!(rows.len(), 4);
+
+        let docs = rows[0].1.as_ref().unwrap();
+        assert_eq!(rows[0].0, 2);
+        assert_eq!(docs.short_link, "go/docs");
+        assert_eq!(docs.url, "https://docs.example.com/#/start");
+
+        let oncall = rows[1].1.as_ref().unwrap();
+        assert_eq!(rows[1].0, 4);
+        assert_eq!(oncall.short_link, "go/oncall");
+        assert_eq!(oncall.url, "https://pager.example.com");
+        assert_eq!(oncall.owner.as_deref(), Some("sre"));
+        assert_eq!(oncall.tags, vec!["ops", "alerts"]);
+
+        assert_eq!(rows[2].1.as_ref().unwrap_err(), "Unknown field 'color'");
+        assert_eq!(rows[3].0, 10);
+        assert!(rows[3].1.is_err());
+    }
+}
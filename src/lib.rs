@@ -1,2 +1,16 @@
+pub mod analytics;
+pub mod batch;
+pub mod bookmarks;
+pub mod collections;
+pub mod conditional;
+pub mod error;
+pub mod favorites;
+pub mod importers;
+pub mod openapi;
+pub mod reports;
+pub mod routes;
+pub mod search;
 pub mod service;
-pub mod storage;
\ No newline at end of file
+pub mod storage;
+pub mod transfer;
+pub mod ui;
\ No newline at end of file
@@ -1,13 +1,8 @@
-mod service;
-mod storage;
-
-use service::{
-    Storage, UpdateGolink, create_golink, delete_golink, get_all_golinks, get_golink,
-    update_golink, with_storage, with_auth, handle_auth_rejection,
-};
+use golink::analytics::{ClickRecorder, DEFAULT_FLUSH_INTERVAL};
+use golink::routes::routes;
+use golink::service::Storage;
+use golink::storage::{HashMapStorage, SqliteStorage};
 use std::sync::Arc;
-use storage::{HashMapStorage, SqliteStorage};
-use warp::Filter;
 
 #[tokio::main]
 async fn main() {
@@ -25,75 +20,25 @@ async fn main() {
     } else {
         Arc::new(HashMapStorage::new())
     };
-    
+
     // Log authentication status
-    if std::env::var("AUTH_TOKEN").is_ok() {
+    if std::env::var("AUTH_TOKEN").is_ok() || std::env::var("USER_TOKENS").is_ok() {
         println!("Authentication: ENABLED");
     } else {
         println!("Authentication: DISABLED");
     }
+    if std::env::var("ADMIN_TOKEN").is_ok() {
+        println!("Admin token: CONFIGURED");
+    }
+    if std::env::var("USER_TOKENS").is_ok() {
+        println!("Personal tokens: CONFIGURED");
+    }
 
-    let create_route = warp::path("golinks")
-        .and(warp::post())
-        .and(with_auth()) // Require authentication for creating golinks
-        .and(warp::body::json())
-        .and(with_storage(storage.clone()))
-        .and_then(create_golink);
-
-    let get_all_route = warp::path("golinks")
-        .and(warp::path::end())
-        .and(warp::get())
-        .and(with_auth()) // Require authentication for getting all golinks
-        .and(warp::query::<std::collections::HashMap<String, String>>())
-        .and(with_storage(storage.clone()))
-        .and_then(get_all_golinks);
-
-    let get_route = warp::path("golinks")
-        .and(warp::path::param::<String>())
-        .and(warp::path::param::<String>())
-        .and(warp::path::end())
-        .and(warp::get())
-        .and(with_auth()) // Require authentication for getting specific golinks
-        .and(with_storage(storage.clone()))
-        .and_then(|prefix: String, name: String, storage: Storage| {
-            get_golink(format!("{}/{}", prefix, name), storage)
-        });
-
-    let update_route = warp::path("golinks")
-        .and(warp::path::param::<String>())
-        .and(warp::path::param::<String>())
-        .and(warp::path::end())
-        .and(warp::put())
-        .and(with_auth()) // Require authentication for updating golinks
-        .and(warp::body::json())
-        .and(with_storage(storage.clone()))
-        .and_then(
-            |prefix: String, name: String, update_data: UpdateGolink, storage: Storage| {
-                update_golink(format!("{}/{}", prefix, name), update_data, storage)
-            },
-        );
-
-    let delete_route = warp::path("golinks")
-        .and(warp::path::param::<String>())
-        .and(warp::path::param::<String>())
-        .and(warp::path::end())
-        .and(warp::delete())
-        .and(with_auth()) // Require authentication for deleting golinks
-        .and(with_storage(storage.clone()))
-        .and_then(|prefix: String, name: String, storage: Storage| {
-            delete_golink(format!("{}/{}", prefix, name), storage)
-        });
+    // Clicks are buffered in memory and written to storage in the background
+    let clicks = ClickRecorder::new(storage.clone());
+    clicks.spawn_flusher(DEFAULT_FLUSH_INTERVAL);
 
-    // IMPORTANT: Route order matters! Specific routes must come before general routes.
-    // get_route (/golinks/{prefix}/{name}) must come before get_all_route (/golinks)
-    // to prevent the general route from matching specific golink requests.
-    let routes = create_route
-        .or(get_route)        // Specific: /golinks/{prefix}/{name}
-        .or(update_route)     // Specific: /golinks/{prefix}/{name}
-        .or(delete_route)     // Specific: /golinks/{prefix}/{name}
-        .or(get_all_route)    // General: /golinks (must be last)
-        .with(warp::cors().allow_any_origin())
-        .recover(handle_auth_rejection);
+    let routes = routes(storage, clicks.clone());
 
     let host = std::env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
     let port = std::env::var("PORT")
@@ -106,5 +51,43 @@ async fn main() {
         .expect("Unable to parse socket address");
 
     println!("Golink service running on http://{}", addr);
-    warp::serve(routes).run(addr).await;
+    println!("Web UI: http://{}/ui", addr);
+    let (_, server) = warp::serve(routes).bind_with_graceful_shutdown(addr, shutdown_signal());
+    server.await;
+
+    // Clicks since the last background flush would be lost otherwise
+    println!("Shutting down, flushing click counts");
+    if let Err(e) = clicks.flush().await {
+        eprintln!("Error: Failed to flush click counts: {:?}", e);
+    }
+}
+
+// Resolves on Ctrl-C, or on SIGTERM as sent by `docker stop` and systemd
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            eprintln!("Error: Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                eprintln!("Error: Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
 }
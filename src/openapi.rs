@@ -0,0 +1,111 @@
+use crate::error::{ErrorResponse, ProblemDetails};
+use crate::service::{Golink, PaginatedResponse};
+use serde::{Deserialize, Serialize};
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{IntoParams, Modify, OpenApi, ToSchema};
+
+// OpenAPI 3 description of the HTTP API, served at `/api/v1/openapi.json`.
+// Paths come from the `#[utoipa::path]` attributes on the handlers and schemas
+// from the request and response types, so a new route only needs to be listed
+// here.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "golink", description = "Short links under go/ for the whole team"),
+    servers((url = "/api/v1", description = "Version 1 of the API")),
+    paths(
+        crate::service::create_golink,
+        crate::service::get_all_golinks,
+        crate::service::get_golink,
+        crate::service::update_golink,
+        crate::service::patch_golink,
+        crate::service::delete_golink,
+        crate::service::rename_golink,
+        crate::service::redirect_golink,
+        crate::service::set_golink_lock,
+        crate::batch::apply_golink_batch,
+        crate::transfer::export_golinks,
+        crate::transfer::import_golinks,
+        crate::bookmarks::export_bookmarks,
+        crate::bookmarks::import_bookmarks,
+        crate::search::search_golinks,
+        crate::search::suggest_golinks,
+        crate::analytics::get_golink_stats,
+        crate::analytics::get_top_golinks,
+        crate::analytics::get_trending_golinks,
+        crate::favorites::favorite_golink,
+        crate::favorites::unfavorite_golink,
+        crate::favorites::get_my_favorites,
+        crate::collections::create_collection,
+        crate::collections::get_all_collections,
+        crate::collections::get_collection,
+        crate::collections::update_collection,
+        crate::collections::delete_collection,
+        crate::reports::get_stale_golinks,
+        crate::reports::deprecate_stale_golinks,
+        get_openapi,
+    ),
+    components(schemas(ErrorResponse, ProblemDetails, MessageResponse)),
+    modifiers(&BearerAuth),
+    security(("bearer_auth" = [])),
+    tags(
+        (name = "golinks", description = "Creating, reading and changing golinks"),
+        (name = "transfer", description = "Export and import of the link database"),
+        (name = "analytics", description = "Click statistics and leaderboards"),
+        (name = "favorites", description = "Golinks starred with a personal token"),
+        (name = "collections", description = "Named groups of golinks"),
+        (name = "admin", description = "Operations that need the admin token"),
+        (name = "meta", description = "The API description itself"),
+    )
+)]
+pub struct ApiDoc;
+
+// Every token from AUTH_TOKEN, ADMIN_TOKEN or USER_TOKENS is a bearer token
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+// Body of successful deletes
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MessageResponse {
+    pub message: String,
+}
+
+// `GET /golinks` returns a plain array unless one of the pagination
+// parameters is given
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum GolinkList {
+    All(Vec<Golink>),
+    Page(PaginatedResponse<Golink>),
+}
+
+// A golink or collection name in the path, `{prefix}/{name}` for go/name.
+// The prefix is always `go`.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct ShortLinkPath {
+    #[param(example = "go")]
+    pub prefix: String,
+    #[param(example = "docs")]
+    pub name: String,
+}
+
+// This document. Served without authentication so clients can generate bindings.
+#[utoipa::path(
+    get,
+    path = "/openapi.json",
+    tag = "meta",
+    security(()),
+    responses((status = 200, description = "OpenAPI 3 document", content_type = "application/json"))
+)]
+pub async fn get_openapi() -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(warp::reply::json(&ApiDoc::openapi()))
+}
@@ -0,0 +1,367 @@
+use crate::error::{ApiError, ErrorCode, ErrorResponse};
+use crate::service::{Golink, Role, Storage};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use utoipa::ToSchema;
+
+// How long a golink has to go unused before it shows up in the stale report
+pub const DEFAULT_STALE_DAYS: i64 = 90;
+
+// Upper bound for `days`, well past anything the click history can tell apart
+const MAX_STALE_DAYS: i64 = 3650;
+
+// Owner label for golinks created before owners were recorded
+const UNOWNED: &str = "(none)";
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OwnerStaleLinks {
+    pub owner: String,
+    pub links: Vec<Golink>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StaleReport {
+    pub days: i64,
+    pub cutoff: String,
+    pub total: usize,
+    pub owners: Vec<OwnerStaleLinks>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct DeprecateResult {
+    pub days: i64,
+    pub cutoff: String,
+    pub deprecated: usize,
+    pub short_links: Vec<String>,
+}
+
+enum ReportFormat {
+    Json,
+    Csv,
+}
+
+fn stale_days(params: &HashMap<String, String>) -> Result<i64, ApiError> {
+    match params.get("days") {
+        None => Ok(DEFAULT_STALE_DAYS),
+        Some(value) => match value.parse::<i64>() {
+            Ok(days) if (1..=MAX_STALE_DAYS).contains(&days) => Ok(days),
+            _ => Err(ApiError::new(
+                ErrorCode::InvalidParameter,
+                format!(
+                    "Invalid days. Must be a number between 1 and {}",
+                    MAX_STALE_DAYS
+                ),
+            )),
+        },
+    }
+}
+
+fn report_format(params: &HashMap<String, String>) -> Result<ReportFormat, ApiError> {
+    match params.get("format").map(String::as_str) {
+        None | Some("json") => Ok(ReportFormat::Json),
+        Some("csv") => Ok(ReportFormat::Csv),
+        Some(_) => Err(ApiError::new(
+            ErrorCode::InvalidParameter,
+            "Invalid format. Must be one of 'json', 'csv'",
+        )),
+    }
+}
+
+fn stale_cutoff(days: i64) -> String {
+    (Utc::now() - chrono::Duration::days(days)).to_rfc3339()
+}
+
+// Quotes a CSV field if it contains a separator, quote or line break
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn group_by_owner(golinks: Vec<Golink>) -> Vec<OwnerStaleLinks> {
+    let mut owners: BTreeMap<String, Vec<Golink>> = BTreeMap::new();
+    for golink in golinks {
+        let owner = golink.owner.clone().unwrap_or_else(|| UNOWNED.to_string());
+        owners.entry(owner).or_default().push(golink);
+    }
+    owners
+        .into_iter()
+        .map(|(owner, links)| OwnerStaleLinks { owner, links })
+        .collect()
+}
+
+fn stale_csv(report: &StaleReport) -> String {
+    let mut csv =
+        String::from("owner,short_link,url,created_at,updated_at,last_used_at,clicks,deprecated\n");
+    for group in &report.owners {
+        for golink in &group.links {
+            let fields = [
+                csv_field(&group.owner),
+                csv_field(&golink.short_link),
+                csv_field(&golink.url),
+                csv_field(&golink.created_at),
+                csv_field(&golink.updated_at),
+                csv_field(golink.last_used_at.as_deref().unwrap_or("")),
+                golink.clicks.to_string(),
+                golink.deprecated.to_string(),
+            ];
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+    }
+    csv
+}
+
+// Golinks that haven't been clicked or modified in the last `days` days,
+// grouped by owner. Admin only.
+#[utoipa::path(
+    get,
+    path = "/admin/golinks/stale",
+    tag = "admin",
+    params(
+        ("days" = Option<i64>, Query, description = "Days without clicks or edits, defaults to 90"),
+        ("format" = Option<String>, Query, description = "`json` (default) or `csv`"),
+    ),
+    responses(
+        (status = 200, description = "Stale golinks by owner", content(
+            (StaleReport = "application/json"),
+            (String = "text/csv"),
+        )),
+        (status = 400, description = "Invalid days or format", body = ErrorResponse),
+        (status = 403, description = "Admin token required", body = ErrorResponse),
+    )
+)]
+pub async fn get_stale_golinks(
+    params: HashMap<String, String>,
+    role: Role,
+    storage: Storage,
+) -> Result<warp::reply::Response, ApiError> {
+    use warp::Reply;
+
+    if role != Role::Admin {
+        return Err(ApiError::new(
+            ErrorCode::AdminRequired,
+            "Admin token required to view stale golinks",
+        ));
+    }
+
+    let days = stale_days(&params)?;
+    let format = report_format(&params)?;
+
+    let cutoff = stale_cutoff(days);
+    let stale = storage.get_stale(&cutoff).await?;
+
+    let report = StaleReport {
+        days,
+        cutoff,
+        total: stale.len(),
+        owners: group_by_owner(stale),
+    };
+
+    match format {
+        ReportFormat::Json => Ok(warp::reply::json(&report).into_response()),
+        ReportFormat::Csv => Ok(warp::reply::with_header(
+            stale_csv(&report),
+            "content-type",
+            "text/csv; charset=utf-8",
+        )
+        .into_response()),
+    }
+}
+
+// Marks every golink in the stale report as deprecated. Admin only.
+#[utoipa::path(
+    post,
+    path = "/admin/golinks/stale/deprecate",
+    tag = "admin",
+    params(("days" = Option<i64>, Query, description = "Days without clicks or edits, defaults to 90")),
+    responses(
+        (status = 200, description = "Golinks marked as deprecated", body = DeprecateResult),
+        (status = 400, description = "Invalid days", body = ErrorResponse),
+        (status = 403, description = "Admin token required", body = ErrorResponse),
+    )
+)]
+pub async fn deprecate_stale_golinks(
+    params: HashMap<String, String>,
+    role: Role,
+    storage: Storage,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, ApiError> {
+    if role != Role::Admin {
+        return Err(ApiError::new(
+            ErrorCode::AdminRequired,
+            "Admin token required to deprecate golinks",
+        ));
+    }
+
+    let days = stale_days(&params)?;
+
+    let cutoff = stale_cutoff(days);
+    let stale = storage.get_stale(&cutoff).await?;
+
+    let short_links: Vec<String> = stale
+        .into_iter()
+        .filter(|golink| !golink.deprecated)
+        .map(|golink| golink.short_link)
+        .collect();
+
+    let deprecated = storage.set_deprecated(&short_links, true).await?;
+
+    let result = DeprecateResult {
+        days,
+        cutoff,
+        deprecated,
+        short_links,
+    };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&result),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::HashMapStorage;
+    use std::sync::Arc;
+    use warp::Reply;
+
+    async fn create_test_storage() -> Storage {
+        let storage: Storage = Arc::new(HashMapStorage::new());
+        let old = "2020-01-01T00:00:00+00:00".to_string();
+        let now = Utc::now().to_rfc3339();
+        let golinks = [
+            ("go/old-docs", Some("alice"), old.clone()),
+            ("go/old-wiki", Some("bob"), old.clone()),
+            ("go/old-misc", None, old.clone()),
+            ("go/fresh", Some("alice"), now),
+        ];
+        for (short_link, owner, created_at) in golinks {
+            storage
+                .create(Golink {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    short_link: short_link.to_string(),
+                    url: "https://example.com/a,b".to_string(),
+                    owner: owner.map(str::to_string),
+                    created_at: created_at.clone(),
+                    updated_at: created_at,
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+        storage
+    }
+
+    #[tokio::test]
+    async fn test_stale_report_grouped_by_owner() {
+        let storage = create_test_storage().await;
+
+        let response = get_stale_golinks(HashMap::new(), Role::Admin, storage)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let report: StaleReport = serde_json::from_slice(&body).unwrap();
+        assert_eq!(report.days, DEFAULT_STALE_DAYS);
+        assert_eq!(report.total, 3);
+        let owners: Vec<&str> = report.owners.iter().map(|o| o.owner.as_str()).collect();
+        assert_eq!(owners, vec![UNOWNED, "alice", "bob"]);
+        assert_eq!(report.owners[1].links[0].short_link, "go/old-docs");
+    }
+
+    #[tokio::test]
+    async fn test_stale_report_csv() {
+        let storage = create_test_storage().await;
+        let params = HashMap::from([("format".to_string(), "csv".to_string())]);
+
+        let response = get_stale_golinks(params, Role::Admin, storage)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+        assert_eq!(
+            response.headers()["content-type"],
+            "text/csv; charset=utf-8"
+        );
+
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let csv = String::from_utf8(body.to_vec()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("owner,short_link,url"));
+        assert!(lines[2].starts_with("alice,go/old-docs,\"https://example.com/a,b\","));
+    }
+
+    #[tokio::test]
+    async fn test_stale_report_rejects_invalid_params() {
+        let storage = create_test_storage().await;
+
+        for (key, value) in [("days", "0"), ("days", "abc"), ("format", "xml")] {
+            let params = HashMap::from([(key.to_string(), value.to_string())]);
+            let error = get_stale_golinks(params, Role::Admin, storage.clone())
+                .await
+                .unwrap_err();
+            assert_eq!(error.code, ErrorCode::InvalidParameter);
+            assert_eq!(error.status(), warp::http::StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stale_report_requires_admin() {
+        let storage = create_test_storage().await;
+
+        let error = get_stale_golinks(HashMap::new(), Role::User, storage.clone())
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::AdminRequired);
+        assert_eq!(error.status(), warp::http::StatusCode::FORBIDDEN);
+
+        let response = deprecate_stale_golinks(HashMap::new(), Role::User, storage.clone())
+            .await
+            .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::FORBIDDEN);
+        assert!(!storage.get("go/old-docs").await.unwrap().deprecated);
+    }
+
+    #[tokio::test]
+    async fn test_deprecate_stale_golinks() {
+        let storage = create_test_storage().await;
+
+        let response = deprecate_stale_golinks(HashMap::new(), Role::Admin, storage.clone())
+            .await
+            .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let result: DeprecateResult = serde_json::from_slice(&body).unwrap();
+        assert_eq!(result.deprecated, 3);
+        assert!(storage.get("go/old-wiki").await.unwrap().deprecated);
+        assert!(!storage.get("go/fresh").await.unwrap().deprecated);
+
+        // Already deprecated golinks aren't counted again
+        let response = deprecate_stale_golinks(HashMap::new(), Role::Admin, storage)
+            .await
+            .into_response();
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let result: DeprecateResult = serde_json::from_slice(&body).unwrap();
+        assert_eq!(result.deprecated, 0);
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
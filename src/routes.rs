@@ -0,0 +1,570 @@
+use crate::analytics::{
+    ClickRecorder, LEADERBOARD_CACHE_TTL, ResponseCache, get_golink_stats, get_top_golinks,
+    get_trending_golinks,
+};
+use crate::batch::apply_golink_batch;
+use crate::bookmarks::{export_bookmarks, import_bookmarks};
+use crate::conditional::{Conditional, with_conditional};
+use crate::collections::{
+    UpdateCollection, create_collection, delete_collection, get_all_collections, get_collection,
+    update_collection,
+};
+use crate::error::{ApiError, ErrorCode, handle_rejection, negotiate};
+use crate::favorites::{favorite_golink, get_my_favorites, unfavorite_golink};
+use crate::openapi::get_openapi;
+use crate::reports::{deprecate_stale_golinks, get_stale_golinks};
+use crate::search::{search_golinks, suggest_golinks};
+use crate::service::{
+    RenameGolink, Role, SetLock, Storage, UpdateGolink, create_golink, delete_golink,
+    get_all_golinks, get_golink, patch_golink, redirect_golink,
+    rename_golink, set_golink_lock, update_golink, with_auth, with_browser_auth, with_clicks,
+    with_identity, with_role, with_storage,
+};
+use crate::transfer::{MAX_IMPORT_BYTES, export_golinks, import_golinks};
+use crate::ui::get_ui;
+use warp::http::HeaderMap;
+use warp::http::header::{ALLOW, HeaderValue, LINK};
+use warp::path::FullPath;
+use warp::{Filter, Reply};
+
+// Builds the full HTTP API on top of the given storage backend. Redirects are
+// counted through `clicks`, which should be flushed to the same storage.
+// Errors are JSON, or problem details when the client asks for them.
+pub fn routes(
+    storage: Storage,
+    clicks: ClickRecorder,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = std::convert::Infallible> + Clone {
+    // Boxed since it's mounted twice, which would otherwise nest the route
+    // types deeper than the compiler allows
+    let (v1, table) = api_v1(storage, clicks);
+    let v1 = v1.map(Reply::into_response).boxed();
+    let table = std::sync::Arc::new(table);
+    // Each version lives under its own prefix. The unversioned paths serve v1
+    // as it was before versioning, so existing scripts keep working. The web UI
+    // is a page on top of the API rather than part of it.
+    let ui = warp::path("ui")
+        .and(warp::path::end())
+        .and(warp::get())
+        .then(get_ui);
+    let api = warp::path!("api" / "v1" / ..)
+        .and(v1.clone())
+        .or(v1)
+        .or(ui)
+        .with(warp::cors().allow_any_origin())
+        .recover(handle_rejection);
+    warp::header::headers_cloned()
+        .and(warp::path::full())
+        .and(api)
+        .map(move |headers: HeaderMap, path: FullPath, reply| match v1_route(path.as_str()) {
+            Some(route) => negotiate(&headers, with_allow(&table, route, reply)),
+            None => {
+                let response = negotiate(&headers, with_allow(&table, path.as_str(), reply));
+                with_deprecation(&table, path.as_str(), response)
+            }
+        })
+}
+
+// Prefix of version 1 of the API
+pub const API_V1: &str = "/api/v1";
+
+// The route below `API_V1` that `path` asks for, or None for unversioned paths
+fn v1_route(path: &str) -> Option<&str> {
+    path.strip_prefix(API_V1)
+        .filter(|route| route.is_empty() || route.starts_with('/'))
+}
+
+// Unversioned API paths are deprecated aliases of their `API_V1` successor.
+// Redirects under /go/ are the short links themselves and stay where they are.
+fn with_deprecation(
+    table: &RouteTable,
+    path: &str,
+    mut response: warp::reply::Response,
+) -> warp::reply::Response {
+    if path.starts_with("/go/") || allowed_methods(table, path).is_empty() {
+        return response;
+    }
+    response
+        .headers_mut()
+        .insert("deprecation", HeaderValue::from_static("true"));
+    let successor = format!("<{}{}>; rel=\"successor-version\"", API_V1, path);
+    if let Ok(link) = HeaderValue::from_str(&successor) {
+        response.headers_mut().insert(LINK, link);
+    }
+    response
+}
+
+// Method and OpenAPI path template of every route in `api_v1`, in the order
+// they were registered with `route!`
+type RouteTable = Vec<(&'static str, String)>;
+
+// Methods that some route accepts for `path`
+fn allowed_methods(table: &RouteTable, path: &str) -> Vec<&'static str> {
+    let mut methods = Vec::new();
+    for (method, template) in table {
+        let matches = template.split('/').count() == path.split('/').count()
+            && template
+                .split('/')
+                .zip(path.split('/'))
+                .all(|(expected, actual)| {
+                    expected == actual || (expected.starts_with('{') && !actual.is_empty())
+                });
+        if matches && !methods.contains(method) {
+            methods.push(*method);
+        }
+    }
+    methods
+}
+
+// Warp only tells that some route refused the method. A 405 lists the methods
+// the path does accept in `Allow`, and becomes a 404 if no route has the path.
+fn with_allow(table: &RouteTable, path: &str, reply: impl warp::Reply) -> warp::reply::Response {
+    let mut response = reply.into_response();
+    let refused = response
+        .extensions()
+        .get::<ApiError>()
+        .is_some_and(|error| error.code == ErrorCode::MethodNotAllowed);
+    if !refused {
+        return response;
+    }
+
+    let methods = allowed_methods(table, path);
+    if methods.is_empty() {
+        return ApiError::new(ErrorCode::RouteNotFound, "No route matches the path")
+            .into_response();
+    }
+    if let Ok(allow) = HeaderValue::from_str(&methods.join(", ")) {
+        response.headers_mut().insert(ALLOW, allow);
+    }
+    response
+}
+
+// Matches `method` on the path made of `segments`, where `{param}` segments
+// extract a `String` and other segments are literals, and records the route in
+// `table` under its OpenAPI path template. Declaring both at once keeps the
+// table from drifting away from what is actually served.
+macro_rules! route {
+    ($table:ident, $method:ident, $($segment:tt)/+) => {{
+        $table.push((stringify!($method), [$(route!(@template $segment)),+].concat()));
+        warp::any()
+            $(.and(route!(@filter $segment)))+
+            .and(warp::path::end())
+            .and(route!(@method $method))
+    }};
+    (@template { $param:ident }) => {
+        concat!("/{", stringify!($param), "}")
+    };
+    (@template $literal:literal) => {
+        concat!("/", $literal)
+    };
+    (@filter { $param:ident }) => {
+        warp::path::param::<String>()
+    };
+    (@filter $literal:literal) => {
+        warp::path($literal)
+    };
+    (@method GET) => { warp::get() };
+    (@method POST) => { warp::post() };
+    (@method PUT) => { warp::put() };
+    (@method PATCH) => { warp::patch() };
+    (@method DELETE) => { warp::delete() };
+}
+
+// Version 1 of the API, without CORS and rejection handling, and the table of
+// its routes. Routes are declared with `route!` and also need a
+// `#[utoipa::path]` on their handler, listed in `openapi::ApiDoc`.
+//
+// A v2 that changes representations gets its own `api_v2` next to this one,
+// reusing the v1 filters for routes that stay the same, and is mounted under
+// `/api/v2` in `routes`.
+fn api_v1(
+    storage: Storage,
+    clicks: ClickRecorder,
+) -> (
+    impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone,
+    RouteTable,
+) {
+    let mut routes = RouteTable::new();
+
+    let batch_route = route!(routes, POST, "golinks" / "batch")
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and(with_role()) // Require authentication, locked golinks also need an admin
+        .and(warp::body::json())
+        .and(with_storage(storage.clone()))
+        .then(apply_golink_batch);
+
+    let import_route = route!(routes, POST, "golinks" / "import")
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and(with_role()) // Only admins may import golinks
+        .and(warp::header::optional::<String>("content-type"))
+        .and(warp::body::content_length_limit(MAX_IMPORT_BYTES))
+        .and(warp::body::bytes())
+        .and(with_storage(storage.clone()))
+        .then(import_golinks);
+
+    let import_bookmarks_route = route!(routes, POST, "golinks" / "import" / "bookmarks")
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and(with_role()) // Only admins may import golinks
+        .and(warp::body::content_length_limit(MAX_IMPORT_BYTES))
+        .and(warp::body::bytes())
+        .and(with_storage(storage.clone()))
+        .then(import_bookmarks);
+
+    let export_bookmarks_route = route!(routes, GET, "golinks" / "export" / "bookmarks")
+        .and(with_auth()) // Require authentication for exporting golinks
+        .and(with_storage(storage.clone()))
+        .then(export_bookmarks);
+
+    let export_route = route!(routes, GET, "golinks" / "export")
+        .and(with_auth()) // Require authentication for exporting golinks
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and(with_storage(storage.clone()))
+        .then(export_golinks);
+
+    let create_route = route!(routes, POST, "golinks")
+        .and(with_auth()) // Require authentication for creating golinks
+        .and(warp::body::json())
+        .and(with_storage(storage.clone()))
+        .then(create_golink);
+
+    let get_all_route = route!(routes, GET, "golinks")
+        .and(with_auth()) // Require authentication for getting all golinks
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and(with_conditional())
+        .and(with_storage(storage.clone()))
+        .then(get_all_golinks);
+
+    let get_route = route!(routes, GET, "golinks" / {prefix} / {name})
+        .and(with_auth()) // Require authentication for getting specific golinks
+        .and(with_conditional())
+        .and(with_storage(storage.clone()))
+        .then(
+            |prefix: String, name: String, conditional: Conditional, storage: Storage| {
+                get_golink(format!("{}/{}", prefix, name), conditional, storage)
+            },
+        );
+
+    let update_route = route!(routes, PUT, "golinks" / {prefix} / {name})
+        .and(with_role()) // Require authentication, locked golinks also need an admin
+        .and(warp::header::optional::<String>("if-match"))
+        .and(warp::body::json())
+        .and(with_storage(storage.clone()))
+        .then(
+            |prefix: String,
+             name: String,
+             role: Role,
+             if_match: Option<String>,
+             update_data: UpdateGolink,
+             storage: Storage| {
+                update_golink(format!("{}/{}", prefix, name), update_data, role, if_match, storage)
+            },
+        );
+
+    let patch_route = route!(routes, PATCH, "golinks" / {prefix} / {name})
+        .and(with_role()) // Require authentication, locked golinks also need an admin
+        .and(warp::header::optional::<String>("if-match"))
+        .and(warp::body::bytes())
+        .and(with_storage(storage.clone()))
+        .then(
+            |prefix: String,
+             name: String,
+             role: Role,
+             if_match: Option<String>,
+             body: warp::hyper::body::Bytes,
+             storage: Storage| {
+                patch_golink(format!("{}/{}", prefix, name), body, role, if_match, storage)
+            },
+        );
+
+    let delete_route = route!(routes, DELETE, "golinks" / {prefix} / {name})
+        .and(with_role()) // Require authentication, locked golinks also need an admin
+        .and(warp::header::optional::<String>("if-match"))
+        .and(with_storage(storage.clone()))
+        .then(
+            |prefix: String, name: String, role: Role, if_match: Option<String>, storage: Storage| {
+                delete_golink(format!("{}/{}", prefix, name), role, if_match, storage)
+            },
+        );
+
+    let rename_route = route!(routes, POST, "golinks" / {prefix} / {name} / "rename")
+        .and(with_role()) // Require authentication, locked golinks also need an admin
+        .and(warp::body::json())
+        .and(with_storage(storage.clone()))
+        .then(
+            |prefix: String, name: String, role: Role, rename: RenameGolink, storage: Storage| {
+                rename_golink(format!("{}/{}", prefix, name), rename, role, storage)
+            },
+        );
+
+    let stats_route = route!(routes, GET, "golinks" / {prefix} / {name} / "stats")
+        .and(with_auth()) // Require authentication for reading golink stats
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and(with_storage(storage.clone()))
+        .then(
+            |prefix: String,
+             name: String,
+             params: std::collections::HashMap<String, String>,
+             storage: Storage| {
+                get_golink_stats(format!("{}/{}", prefix, name), params, storage)
+            },
+        );
+
+    let favorite_route = route!(routes, PUT, "golinks" / {prefix} / {name} / "favorite")
+        .and(with_identity()) // Favorites belong to the caller's personal token
+        .and(with_storage(storage.clone()))
+        .then(|prefix: String, name: String, user: String, storage: Storage| {
+            favorite_golink(format!("{}/{}", prefix, name), user, storage)
+        });
+
+    let unfavorite_route = route!(routes, DELETE, "golinks" / {prefix} / {name} / "favorite")
+        .and(with_identity()) // Favorites belong to the caller's personal token
+        .and(with_storage(storage.clone()))
+        .then(|prefix: String, name: String, user: String, storage: Storage| {
+            unfavorite_golink(format!("{}/{}", prefix, name), user, storage)
+        });
+
+    let my_favorites_route = route!(routes, GET, "me" / "favorites")
+        .and(with_identity()) // Favorites belong to the caller's personal token
+        .and(with_storage(storage.clone()))
+        .then(get_my_favorites);
+
+    let create_collection_route = route!(routes, POST, "collections")
+        .and(with_auth()) // Require authentication for creating collections
+        .and(warp::body::json())
+        .and(with_storage(storage.clone()))
+        .then(create_collection);
+
+    let get_all_collections_route = route!(routes, GET, "collections")
+        .and(with_auth()) // Require authentication for listing collections
+        .and(with_storage(storage.clone()))
+        .then(get_all_collections);
+
+    let get_collection_route = route!(routes, GET, "collections" / {prefix} / {name})
+        .and(with_auth()) // Require authentication for getting specific collections
+        .and(with_storage(storage.clone()))
+        .then(|prefix: String, name: String, storage: Storage| {
+            get_collection(format!("{}/{}", prefix, name), storage)
+        });
+
+    let update_collection_route = route!(routes, PUT, "collections" / {prefix} / {name})
+        .and(with_auth()) // Require authentication for updating collections
+        .and(warp::body::json())
+        .and(with_storage(storage.clone()))
+        .then(
+            |prefix: String, name: String, update: UpdateCollection, storage: Storage| {
+                update_collection(format!("{}/{}", prefix, name), update, storage)
+            },
+        );
+
+    let delete_collection_route = route!(routes, DELETE, "collections" / {prefix} / {name})
+        .and(with_auth()) // Require authentication for deleting collections
+        .and(with_storage(storage.clone()))
+        .then(|prefix: String, name: String, storage: Storage| {
+            delete_collection(format!("{}/{}", prefix, name), storage)
+        });
+
+    let leaderboard_cache = ResponseCache::new(LEADERBOARD_CACHE_TTL);
+    let with_leaderboard_cache = warp::any().map(move || leaderboard_cache.clone());
+
+    let search_route = route!(routes, GET, "golinks" / "search")
+        .and(with_auth()) // Require authentication for searching golinks
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and(with_storage(storage.clone()))
+        .then(search_golinks);
+
+    let suggest_route = route!(routes, GET, "golinks" / "suggest")
+        .and(with_auth()) // Require authentication for suggesting golinks
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and(with_storage(storage.clone()))
+        .then(suggest_golinks);
+
+    let top_route = route!(routes, GET, "golinks" / "top")
+        .and(with_auth()) // Require authentication for reading leaderboards
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and(with_leaderboard_cache.clone())
+        .and(with_storage(storage.clone()))
+        .then(get_top_golinks);
+
+    let trending_route = route!(routes, GET, "golinks" / "trending")
+        .and(with_auth()) // Require authentication for reading leaderboards
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and(with_leaderboard_cache)
+        .and(with_storage(storage.clone()))
+        .then(get_trending_golinks);
+
+    let lock_route = route!(routes, PUT, "admin" / "golinks" / {prefix} / {name} / "lock")
+        .and(with_role()) // Only admins may lock or unlock golinks
+        .and(warp::body::json())
+        .and(with_storage(storage.clone()))
+        .then(
+            |prefix: String, name: String, role: Role, set_lock: SetLock, storage: Storage| {
+                set_golink_lock(format!("{}/{}", prefix, name), set_lock, role, storage)
+            },
+        );
+
+    let stale_route = route!(routes, GET, "admin" / "golinks" / "stale")
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and(with_role()) // Only admins may view the stale report
+        .and(with_storage(storage.clone()))
+        .then(get_stale_golinks);
+
+    let deprecate_route = route!(routes, POST, "admin" / "golinks" / "stale" / "deprecate")
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and(with_role()) // Only admins may deprecate golinks
+        .and(with_storage(storage.clone()))
+        .then(deprecate_stale_golinks);
+
+    let openapi_route = route!(routes, GET, "openapi.json")
+        .and_then(get_openapi);
+
+    let redirect_route = route!(routes, GET, "go" / {name})
+        .and(with_browser_auth()) // Browsers follow golinks with the web UI's cookie
+        .and(warp::header::optional::<String>("referer"))
+        .and(warp::header::optional::<String>("user-agent"))
+        .and(with_clicks(clicks))
+        .and(with_storage(storage.clone()))
+        .then(
+            |name: String,
+             referer: Option<String>,
+             user_agent: Option<String>,
+             clicks: ClickRecorder,
+             storage: Storage| {
+                redirect_golink(format!("go/{}", name), referer, user_agent, clicks, storage)
+            },
+        );
+
+    // IMPORTANT: Route order matters! Specific routes must come before general routes.
+    // get_route (/golinks/{prefix}/{name}) must come before get_all_route (/golinks)
+    // to prevent the general route from matching specific golink requests.
+    let api = batch_route
+        .or(import_route)
+        .or(import_bookmarks_route)
+        .or(export_route)     // Specific: /golinks/export
+        .or(export_bookmarks_route) // Specific: /golinks/export/bookmarks
+        .or(create_route)
+        .or(get_route)        // Specific: /golinks/{prefix}/{name}
+        .or(update_route)     // Specific: /golinks/{prefix}/{name}
+        .or(patch_route)      // Specific: /golinks/{prefix}/{name}
+        .or(delete_route)     // Specific: /golinks/{prefix}/{name}
+        .or(rename_route)     // Specific: /golinks/{prefix}/{name}/rename
+        .or(stats_route)      // Specific: /golinks/{prefix}/{name}/stats
+        .or(favorite_route)   // Specific: /golinks/{prefix}/{name}/favorite
+        .or(unfavorite_route) // Specific: /golinks/{prefix}/{name}/favorite
+        .or(my_favorites_route) // User: /me/favorites
+        .or(search_route)     // Specific: /golinks/search
+        .or(suggest_route)    // Specific: /golinks/suggest
+        .or(top_route)        // Specific: /golinks/top
+        .or(trending_route)   // Specific: /golinks/trending
+        .or(lock_route)       // Admin: /admin/golinks/{prefix}/{name}/lock
+        .or(stale_route)      // Admin: /admin/golinks/stale
+        .or(deprecate_route)  // Admin: /admin/golinks/stale/deprecate
+        .or(create_collection_route)   // Collections: /collections
+        .or(get_all_collections_route) // Collections: /collections
+        .or(get_collection_route)      // Collections: /collections/{prefix}/{name}
+        .or(update_collection_route)   // Collections: /collections/{prefix}/{name}
+        .or(delete_collection_route)   // Collections: /collections/{prefix}/{name}
+        .or(openapi_route)    // Public: /openapi.json
+        .or(redirect_route)   // Redirect: /go/{name} (golinks and collections)
+        .or(get_all_route);   // General: /golinks (must be last)
+    (api, routes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openapi::ApiDoc;
+    use crate::storage::HashMapStorage;
+    use std::sync::Arc;
+    use utoipa::OpenApi;
+
+    fn route_table() -> RouteTable {
+        let storage: Storage = Arc::new(HashMapStorage::new());
+        api_v1(storage.clone(), ClickRecorder::new(storage)).1
+    }
+
+    #[test]
+    fn test_openapi_covers_every_route() {
+        let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        let mut documented = Vec::new();
+        for (path, item) in spec["paths"].as_object().unwrap() {
+            for method in ["get", "put", "post", "delete", "patch"] {
+                if item.get(method).is_some() {
+                    documented.push((method.to_uppercase(), path.clone()));
+                }
+            }
+        }
+        documented.sort();
+
+        let mut registered: Vec<(String, String)> = route_table()
+            .into_iter()
+            .map(|(method, path)| (method.to_string(), path))
+            .collect();
+        registered.sort();
+        assert_eq!(documented, registered);
+    }
+
+    #[test]
+    fn test_allowed_methods() {
+        let table = route_table();
+        assert_eq!(allowed_methods(&table, "/golinks"), ["POST", "GET"]);
+        assert_eq!(allowed_methods(&table, "/golinks/go/docs/favorite"), ["PUT", "DELETE"]);
+        assert_eq!(allowed_methods(&table, "/golinks/search"), ["GET"]);
+        assert_eq!(
+            allowed_methods(&table, "/collections/go/onboarding"),
+            ["GET", "PUT", "DELETE"]
+        );
+        assert!(allowed_methods(&table, "/nothing/here").is_empty());
+        assert!(allowed_methods(&table, "/golinks/go/").is_empty());
+    }
+
+    #[test]
+    fn test_v1_route() {
+        assert_eq!(v1_route("/api/v1/golinks"), Some("/golinks"));
+        assert_eq!(v1_route("/api/v1"), Some(""));
+        assert_eq!(v1_route("/api/v10/golinks"), None);
+        assert_eq!(v1_route("/golinks"), None);
+    }
+
+    #[test]
+    fn test_with_deprecation() {
+        let table = route_table();
+        let response = with_deprecation(&table, "/golinks/go/docs", warp::reply().into_response());
+        assert_eq!(response.headers()["deprecation"], "true");
+        assert_eq!(
+            response.headers()[LINK],
+            "</api/v1/golinks/go/docs>; rel=\"successor-version\""
+        );
+
+        for path in ["/go/docs", "/nothing/here"] {
+            let response = with_deprecation(&table, path, warp::reply().into_response());
+            assert!(response.headers().get("deprecation").is_none(), "{}", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_routes_are_registered() {
+        let storage: Storage = Arc::new(HashMapStorage::new());
+        let (api, table) = api_v1(storage.clone(), ClickRecorder::new(storage));
+
+        // Without a body some routes reject the request, but only after matching
+        // its path and method
+        for (method, path) in table {
+            let uri = path.replace("{prefix}", "go").replace("{name}", "docs");
+            let result = warp::test::request()
+                .method(method)
+                .path(&uri)
+                .filter(&api)
+                .await;
+            if let Err(rejection) = result {
+                assert!(
+                    rejection.find::<warp::body::BodyDeserializeError>().is_some()
+                        || rejection.find::<warp::reject::LengthRequired>().is_some()
+                        || rejection.find::<ApiError>().is_some(),
+                    "{} {} is not routed: {:?}",
+                    method,
+                    path,
+                    rejection
+                );
+            }
+        }
+    }
+}
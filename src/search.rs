@@ -0,0 +1,539 @@
+use crate::collections::escape_html;
+use crate::error::{ApiError, ErrorCode, ErrorResponse};
+use crate::service::{Golink, Storage};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use utoipa::ToSchema;
+
+pub const DEFAULT_SEARCH_LIMIT: usize = 20;
+pub const MAX_SEARCH_LIMIT: usize = 100;
+pub const DEFAULT_SUGGEST_LIMIT: usize = 10;
+pub const MAX_SUGGEST_LIMIT: usize = 50;
+
+// Extra query terms are ignored past this many
+const MAX_QUERY_TOKENS: usize = 10;
+
+// Fragments longer than this are cut down to the text around the first match
+const FRAGMENT_CHARS: usize = 80;
+
+// Searched fields and their weights, most important first. SQLite passes the
+// same weights to bm25().
+pub(crate) const SEARCH_FIELDS: [(&str, f64); 4] = [
+    ("short_link", 10.0),
+    ("description", 4.0),
+    ("tags", 3.0),
+    ("url", 2.0),
+];
+
+// The part of a golink that matched, with matched words wrapped in <mark>
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct Highlight {
+    pub field: String,
+    // HTML, everything but the <mark> tags is escaped
+    pub fragment: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct SearchHit {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub golink: Golink,
+    // Relevance, higher is better. Only comparable within one response.
+    pub score: f64,
+    #[sqlx(skip)]
+    pub highlight: Highlight,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SearchResponse {
+    pub query: String,
+    // Number of results returned, so never more than the limit
+    pub count: usize,
+    pub results: Vec<SearchHit>,
+}
+
+// A golink offered while its name is being typed
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Suggestion {
+    pub short_link: String,
+    pub url: String,
+    pub description: Option<String>,
+    pub clicks: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SuggestResponse {
+    pub prefix: String,
+    pub suggestions: Vec<Suggestion>,
+}
+
+// Lowercased runs of letters and digits, the same split SQLite's unicode61
+// tokenizer makes
+pub fn search_tokens(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+// Query words, deduplicated. Each one matches any word it is a prefix of.
+pub(crate) fn query_tokens(query: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = Vec::new();
+    for token in search_tokens(query) {
+        if !tokens.contains(&token) {
+            tokens.push(token);
+        }
+    }
+    tokens.truncate(MAX_QUERY_TOKENS);
+    tokens
+}
+
+pub(crate) fn field_text(golink: &Golink, field: &str) -> String {
+    match field {
+        "short_link" => golink.short_link.clone(),
+        "url" => golink.url.clone(),
+        "description" => golink.description.clone().unwrap_or_default(),
+        "tags" => golink.tags.join(" "),
+        _ => String::new(),
+    }
+}
+
+fn searchable_tokens(golink: &Golink) -> BTreeSet<String> {
+    SEARCH_FIELDS
+        .iter()
+        .flat_map(|(field, _)| search_tokens(&field_text(golink, field)))
+        .collect()
+}
+
+// Inverted index from words to the golinks containing them, kept by
+// `HashMapStorage` alongside its data
+#[derive(Debug, Default)]
+pub(crate) struct TokenIndex {
+    postings: BTreeMap<String, BTreeSet<String>>,
+    // short_link -> indexed words, to undo a golink's postings. Sorted, so it
+    // also finds short links by prefix.
+    documents: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl TokenIndex {
+    pub(crate) fn insert(&mut self, golink: &Golink) {
+        self.remove(&golink.short_link);
+        let tokens = searchable_tokens(golink);
+        for token in &tokens {
+            self.postings
+                .entry(token.clone())
+                .or_default()
+                .insert(golink.short_link.clone());
+        }
+        self.documents.insert(golink.short_link.clone(), tokens);
+    }
+
+    pub(crate) fn remove(&mut self, short_link: &str) {
+        let Some(tokens) = self.documents.remove(short_link) else {
+            return;
+        };
+        for token in tokens {
+            if let Some(short_links) = self.postings.get_mut(&token) {
+                short_links.remove(short_link);
+                if short_links.is_empty() {
+                    self.postings.remove(&token);
+                }
+            }
+        }
+    }
+
+    // Indexed short links starting with `prefix`, in order
+    pub(crate) fn short_links_with_prefix<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = &'a String> + 'a {
+        self.documents
+            .range(prefix.to_string()..)
+            .map(|(short_link, _)| short_link)
+            .take_while(move |short_link| short_link.starts_with(prefix))
+    }
+
+    // Golinks containing a word starting with every one of the query tokens
+    pub(crate) fn lookup(&self, tokens: &[String]) -> BTreeSet<String> {
+        let mut matches: Option<BTreeSet<String>> = None;
+        for token in tokens {
+            let found: BTreeSet<String> = self
+                .postings
+                .range(token.clone()..)
+                .take_while(|(word, _)| word.starts_with(token.as_str()))
+                .flat_map(|(_, short_links)| short_links.iter().cloned())
+                .collect();
+            matches = Some(match matches {
+                Some(matches) => matches.intersection(&found).cloned().collect(),
+                None => found,
+            });
+        }
+        matches.unwrap_or_default()
+    }
+}
+
+// Weighted count of matching words, exact words count double. Used where
+// there is no bm25 to rank with.
+pub(crate) fn score(golink: &Golink, tokens: &[String]) -> f64 {
+    let mut score = 0.0;
+    for (field, weight) in SEARCH_FIELDS {
+        let words = search_tokens(&field_text(golink, field));
+        let mut field_score = 0.0;
+        for token in tokens {
+            for word in &words {
+                if word == token {
+                    field_score += 2.0 * weight;
+                } else if word.starts_with(token.as_str()) {
+                    field_score += weight;
+                }
+            }
+        }
+        // Shorter fields make each match count for more
+        score += field_score / (1.0 + words.len() as f64 / 10.0);
+    }
+    score
+}
+
+fn is_match(word: &str, tokens: &[String]) -> bool {
+    let word = word.to_lowercase();
+    tokens.iter().any(|token| word.starts_with(token.as_str()))
+}
+
+// Wraps matching words of `text` in <mark>, trimmed to the area around the
+// first match. None if nothing in `text` matches.
+pub(crate) fn highlight_text(text: &str, tokens: &[String]) -> Option<String> {
+    // (start, end) byte ranges of matching words
+    let mut matches = Vec::new();
+    let mut word_start = None;
+    for (index, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        match (c.is_alphanumeric() && index < text.len(), word_start) {
+            (true, None) => word_start = Some(index),
+            (false, Some(start)) => {
+                if is_match(&text[start..index], tokens) {
+                    matches.push((start, index));
+                }
+                word_start = None;
+            }
+            _ => {}
+        }
+    }
+    let (first, _) = *matches.first()?;
+
+    let chars = text.chars().count();
+    let (mut from, mut to) = (0, text.len());
+    if chars > FRAGMENT_CHARS {
+        let lead = FRAGMENT_CHARS / 4;
+        let before = text[..first].chars().count();
+        let skip = before.saturating_sub(lead).min(chars - FRAGMENT_CHARS);
+        from = text.char_indices().nth(skip).map_or(0, |(i, _)| i);
+        to = text
+            .char_indices()
+            .nth(skip + FRAGMENT_CHARS)
+            .map_or(text.len(), |(i, _)| i);
+    }
+
+    let mut fragment = String::new();
+    if from > 0 {
+        fragment.push('…');
+    }
+    let mut position = from;
+    for (start, end) in matches {
+        if end <= from || start >= to {
+            continue;
+        }
+        let (start, end) = (start.max(from), end.min(to));
+        fragment.push_str(&escape_html(&text[position..start]));
+        fragment.push_str("<mark>");
+        fragment.push_str(&escape_html(&text[start..end]));
+        fragment.push_str("</mark>");
+        position = end;
+    }
+    fragment.push_str(&escape_html(&text[position..to]));
+    if to < text.len() {
+        fragment.push('…');
+    }
+    Some(fragment)
+}
+
+// Highlights the most important field with a match
+pub(crate) fn highlight(golink: &Golink, tokens: &[String]) -> Highlight {
+    SEARCH_FIELDS
+        .iter()
+        .find_map(|(field, _)| {
+            highlight_text(&field_text(golink, field), tokens).map(|fragment| Highlight {
+                field: field.to_string(),
+                fragment,
+            })
+        })
+        .unwrap_or_default()
+}
+
+// Ranked full-text search over short links, URLs, descriptions and tags.
+// Every query word has to match the start of a word in the golink.
+#[utoipa::path(
+    get,
+    path = "/golinks/search",
+    tag = "golinks",
+    params(
+        ("q" = String, Query, description = "Words to search for"),
+        ("limit" = Option<usize>, Query, description = "Between 1 and 100, defaults to 20"),
+    ),
+    responses(
+        (status = 200, description = "Matching golinks, best first", body = SearchResponse),
+        (status = 400, description = "Missing query or invalid limit", body = ErrorResponse),
+    )
+)]
+pub async fn search_golinks(
+    params: HashMap<String, String>,
+    storage: Storage,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, ApiError> {
+    let query = params.get("q").map(|q| q.trim()).unwrap_or_default();
+    if query_tokens(query).is_empty() {
+        return Err(ApiError::new(
+            ErrorCode::InvalidParameter,
+            "Missing q. The query needs at least one letter or digit",
+        ));
+    }
+    let limit = limit_param(&params, DEFAULT_SEARCH_LIMIT, MAX_SEARCH_LIMIT)?;
+
+    let results = storage.search(query, limit).await?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&SearchResponse {
+            query: query.to_string(),
+            count: results.len(),
+            results,
+        }),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+// Short links starting with what has been typed so far, for autocompletion.
+// The prefix includes `go/` and is case-sensitive, like the names themselves.
+#[utoipa::path(
+    get,
+    path = "/golinks/suggest",
+    tag = "golinks",
+    params(
+        ("prefix" = String, Query, description = "Start of the short link, such as go/de"),
+        ("limit" = Option<usize>, Query, description = "Between 1 and 50, defaults to 10"),
+    ),
+    responses(
+        (status = 200, description = "Matching golinks, most clicked first", body = SuggestResponse),
+        (status = 400, description = "Missing prefix or invalid limit", body = ErrorResponse),
+    )
+)]
+pub async fn suggest_golinks(
+    params: HashMap<String, String>,
+    storage: Storage,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, ApiError> {
+    let Some(prefix) = params.get("prefix") else {
+        return Err(ApiError::new(ErrorCode::InvalidParameter, "Missing prefix"));
+    };
+    let limit = limit_param(&params, DEFAULT_SUGGEST_LIMIT, MAX_SUGGEST_LIMIT)?;
+
+    let suggestions = storage
+        .suggest(prefix, limit)
+        .await?
+        .into_iter()
+        .map(|golink| Suggestion {
+            short_link: golink.short_link,
+            url: golink.url,
+            description: golink.description,
+            clicks: golink.clicks,
+        })
+        .collect();
+    Ok(warp::reply::with_status(
+        warp::reply::json(&SuggestResponse {
+            prefix: prefix.clone(),
+            suggestions,
+        }),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+// The `limit` query parameter, between 1 and `max`
+fn limit_param(
+    params: &HashMap<String, String>,
+    default: usize,
+    max: usize,
+) -> Result<usize, ApiError> {
+    match params.get("limit") {
+        None => Ok(default),
+        Some(value) => match value.parse::<usize>() {
+            Ok(limit) if (1..=max).contains(&limit) => Ok(limit),
+            _ => Err(ApiError::new(
+                ErrorCode::InvalidParameter,
+                format!("Invalid limit. Must be a number between 1 and {}", max),
+            )),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::HashMapStorage;
+    use std::sync::Arc;
+    use warp::Reply;
+
+    fn golink(short_link: &str, url: &str, description: Option<&str>) -> Golink {
+        Golink {
+            id: uuid::Uuid::new_v4().to_string(),
+            short_link: short_link.to_string(),
+            url: url.to_string(),
+            description: description.map(str::to_string),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_query_tokens() {
+        assert_eq!(
+            query_tokens("Grafana  dashboards, grafana!"),
+            vec!["grafana", "dashboards"]
+        );
+        assert!(query_tokens(" -- ").is_empty());
+    }
+
+    #[test]
+    fn test_token_index() {
+        let mut index = TokenIndex::default();
+        index.insert(&golink(
+            "go/dash",
+            "https://grafana.example.com/d/abc",
+            None,
+        ));
+        index.insert(&golink(
+            "go/wiki",
+            "https://wiki.example.com",
+            Some("Team wiki"),
+        ));
+
+        let tokens = |query: &str| query_tokens(query);
+        assert_eq!(
+            index
+                .lookup(&tokens("graf"))
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec!["go/dash"]
+        );
+        assert_eq!(index.lookup(&tokens("example")).len(), 2);
+        assert!(index.lookup(&tokens("team grafana")).is_empty());
+
+        index.remove("go/wiki");
+        assert_eq!(index.lookup(&tokens("example")).len(), 1);
+        assert!(index.lookup(&tokens("team")).is_empty());
+    }
+
+    #[test]
+    fn test_highlight_text() {
+        let tokens = query_tokens("graf dash");
+        assert_eq!(
+            highlight_text("https://grafana.example.com/d/dash?a=1&b=2", &tokens).unwrap(),
+            "https://<mark>grafana</mark>.example.com/d/<mark>dash</mark>?a=1&amp;b=2"
+        );
+        assert!(highlight_text("https://example.com", &tokens).is_none());
+
+        let long = format!("{} grafana {}", "x ".repeat(60), "y ".repeat(60));
+        let fragment = highlight_text(&long, &tokens).unwrap();
+        assert!(fragment.starts_with('…') && fragment.ends_with('…'));
+        assert!(fragment.contains("<mark>grafana</mark>"));
+    }
+
+    #[tokio::test]
+    async fn test_search_golinks() {
+        let storage: Storage = Arc::new(HashMapStorage::new());
+        for golink in [
+            golink("go/grafana", "https://grafana.example.com", None),
+            golink(
+                "go/oncall",
+                "https://pager.example.com",
+                Some("Grafana alerts for the on-call rotation"),
+            ),
+            golink("go/wiki", "https://wiki.example.com", None),
+        ] {
+            storage.create(golink).await.unwrap();
+        }
+
+        let params = HashMap::from([("q".to_string(), "grafana".to_string())]);
+        let response = search_golinks(params, storage.clone())
+            .await
+            .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let response: SearchResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.count, 2);
+        assert_eq!(response.results[0].golink.short_link, "go/grafana");
+        assert_eq!(response.results[1].highlight.field, "description");
+
+        // The count is of the results returned, not of every match
+        let params = HashMap::from([
+            ("q".to_string(), "grafana".to_string()),
+            ("limit".to_string(), "1".to_string()),
+        ]);
+        let response = search_golinks(params, storage.clone())
+            .await
+            .into_response();
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let response: SearchResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.count, 1);
+        assert_eq!(response.results.len(), 1);
+
+        let params = HashMap::from([("q".to_string(), "?!".to_string())]);
+        let response = search_golinks(params, storage)
+            .await
+            .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_suggest_golinks() {
+        let storage: Storage = Arc::new(HashMapStorage::new());
+        for (name, clicks) in [("go/deploy", 2), ("go/design", 8), ("go/wiki", 20)] {
+            let mut golink = golink(name, "https://example.com", None);
+            golink.clicks = clicks;
+            storage.create(golink).await.unwrap();
+        }
+
+        let params = HashMap::from([("prefix".to_string(), "go/de".to_string())]);
+        let response = suggest_golinks(params, storage.clone())
+            .await
+            .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let response: SuggestResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.prefix, "go/de");
+        let names: Vec<&str> = response
+            .suggestions
+            .iter()
+            .map(|suggestion| suggestion.short_link.as_str())
+            .collect();
+        assert_eq!(names, vec!["go/design", "go/deploy"]);
+        assert_eq!(response.suggestions[0].clicks, 8);
+
+        let error = suggest_golinks(HashMap::new(), storage.clone())
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(error.code, ErrorCode::InvalidParameter);
+
+        for limit in ["0", "51", "ten"] {
+            let params = HashMap::from([
+                ("prefix".to_string(), "go/".to_string()),
+                ("limit".to_string(), limit.to_string()),
+            ]);
+            let error = suggest_golinks(params, storage.clone())
+                .await
+                .err()
+                .unwrap();
+            assert_eq!(error.code, ErrorCode::InvalidParameter, "{}", limit);
+        }
+    }
+}
@@ -1,38 +1,121 @@
-use crate::storage::{GoStorage, StorageError};
+use crate::analytics::ClickRecorder;
+use crate::collections::collection_landing_page;
+use crate::conditional::{Conditional, not_modified, parse_rfc3339, with_validators};
+use crate::error::{ApiError, ErrorCode, ErrorResponse};
+use crate::openapi::{GolinkList, MessageResponse, ShortLinkPath};
+use crate::storage::{
+    ClickSource, Cursor, GoStorage, GolinkChanges, GolinkFilter, GolinkSort, ListQuery,
+    SortDirection, StorageError,
+};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use utoipa::ToSchema;
 use uuid::Uuid;
-use warp::Filter;
+use warp::{Filter, Reply};
 
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
 pub struct Golink {
     pub id: String,
     pub short_link: String,
     pub url: String,
     pub created_at: String,
+    // Locked golinks can only be updated or deleted by an admin
+    #[serde(default)]
+    pub locked: bool,
+    // Usage is counted by the redirect route and flushed in batches
+    #[serde(default)]
+    pub clicks: i64,
+    #[serde(default)]
+    pub last_used_at: Option<String>,
+    // Person or team responsible for the golink
+    #[serde(default)]
+    pub owner: Option<String>,
+    // Last time the golink itself was changed, starts out as `created_at`
+    #[serde(default)]
+    pub updated_at: String,
+    // Marked by admins when pruning unused golinks, deprecated golinks still redirect
+    #[serde(default)]
+    pub deprecated: bool,
+    // Free-form labels, normalized with `normalize_tags`
+    #[serde(default)]
+    #[sqlx(json)]
+    pub tags: Vec<String>,
+    // What the link is for, searched along with the name and URL
+    #[serde(default)]
+    pub description: Option<String>,
+    // Bumped by every edit, clicks aside. Exposed to clients through the ETag.
+    #[serde(default)]
+    pub version: i64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl Golink {
+    // Strong entity tag, changing whenever the golink is edited. The id keeps a
+    // recreated golink from matching tags handed out for the one it replaced.
+    pub fn etag(&self) -> String {
+        format!("\"{}-{}\"", self.id, self.version)
+    }
+
+    // Weak tag for reads. Clicks and `last_used_at` are in the body but don't
+    // bump the version, so two reads with the same tag are only equivalent.
+    pub fn weak_etag(&self) -> String {
+        format!("W/{}", self.etag())
+    }
+}
+
+// A golink starred by a user
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct Favorite {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub golink: Golink,
+    pub favorited_at: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
 pub struct CreateGolink {
     pub short_link: String,
     pub url: String,
+    #[serde(default)]
+    pub owner: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub description: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct UpdateGolink {
     pub url: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct RenameGolink {
+    pub new_short_link: String,
+    // Leave the old name behind as an alias redirecting to the new one
+    #[serde(default)]
+    pub keep_alias: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SetLock {
+    pub locked: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct PaginationInfo {
-    pub page: usize,
+    // Absent when paging with a cursor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<usize>,
     pub page_size: usize,
     pub total_items: usize,
     pub total_pages: usize,
+    // Pass as `cursor` to get the next page, absent on the last page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct PaginatedResponse<T> {
     pub data: Vec<T>,
     pub pagination: PaginationInfo,
@@ -46,6 +129,19 @@ pub fn with_storage(
     warp::any().map(move || storage.clone())
 }
 
+// Role of the caller, derived from the bearer token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    User,
+    Admin,
+}
+
+pub fn with_clicks(
+    clicks: ClickRecorder,
+) -> impl Filter<Extract = (ClickRecorder,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || clicks.clone())
+}
+
 // Authentication middleware
 pub fn with_auth() -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
     warp::header::optional::<String>("authorization")
@@ -53,170 +149,459 @@ pub fn with_auth() -> impl Filter<Extract = (), Error = warp::Rejection> + Clone
         .untuple_one()
 }
 
-async fn validate_token_optional(auth_header: Option<String>) -> Result<(), warp::Rejection> {
-    // Check if authentication is enabled via environment variable
-    let auth_token = match std::env::var("AUTH_TOKEN") {
-        Ok(token) if !token.is_empty() => token,
-        _ => {
-            // If no auth token is set, allow all requests (for backward compatibility)
-            return Ok(());
-        }
-    };
+// Cookie the web UI keeps the token in, percent-encoded, for requests the
+// browser makes on its own
+pub const TOKEN_COOKIE: &str = "golink_token";
 
-    // Check if authorization header is present
-    let auth_header = match auth_header {
-        Some(header) => header,
-        None => {
-            return Err(warp::reject::custom(AuthError::MissingBearer));
-        }
-    };
+// `with_auth` for pages browsers open directly, like redirects, which can't
+// carry an Authorization header. The token may also come from `TOKEN_COOKIE`.
+pub fn with_browser_auth() -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("authorization")
+        .and(warp::cookie::optional::<String>(TOKEN_COOKIE))
+        .and_then(|auth_header: Option<String>, cookie: Option<String>| {
+            let auth_header = auth_header
+                .or_else(|| cookie.map(|token| format!("Bearer {}", percent_decode(&token))));
+            validate_token_optional(auth_header)
+        })
+        .untuple_one()
+}
 
-    // Extract token from "Bearer <token>" format
-    if let Some(token) = auth_header.strip_prefix("Bearer ") {
-        if token == auth_token {
-            Ok(())
-        } else {
-            Err(warp::reject::custom(AuthError::InvalidToken))
+// Undoes `encodeURIComponent`, leaving malformed escapes as they are
+fn percent_decode(value: &str) -> String {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut rest = value.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = (byte == b'%')
+            .then(|| tail.get(..2))
+            .flatten()
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
         }
-    } else {
-        Err(warp::reject::custom(AuthError::MissingBearer))
     }
+    String::from_utf8_lossy(&bytes).into_owned()
 }
 
+// Authentication middleware that also extracts the caller's role
+pub fn with_role() -> impl Filter<Extract = (Role,), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("authorization").and_then(resolve_role)
+}
 
-// Custom error types for authentication
-#[derive(Debug)]
-pub enum AuthError {
-    MissingBearer,
-    InvalidToken,
+// Authentication middleware for per-user endpoints, extracts the name bound
+// to the caller's personal token
+pub fn with_identity() -> impl Filter<Extract = (String,), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("authorization").and_then(resolve_identity)
 }
 
-impl warp::reject::Reject for AuthError {}
+async fn validate_token_optional(auth_header: Option<String>) -> Result<(), warp::Rejection> {
+    resolve_role(auth_header).await.map(|_| ())
+}
 
-// Error handling for authentication
-pub async fn handle_auth_rejection(
-    err: warp::Rejection,
-) -> Result<impl warp::Reply, std::convert::Infallible> {
-    if let Some(auth_error) = err.find::<AuthError>() {
-        let (code, message) = match auth_error {
-            AuthError::MissingBearer => (
-                warp::http::StatusCode::UNAUTHORIZED,
-                "Missing or invalid Authorization header. Expected: Bearer <token>",
-            ),
-            AuthError::InvalidToken => (
-                warp::http::StatusCode::UNAUTHORIZED,
-                "Invalid authentication token",
-            ),
-        };
+async fn resolve_role(auth_header: Option<String>) -> Result<Role, warp::Rejection> {
+    resolve_caller(auth_header).map(|caller| caller.role)
+}
 
-        let json = warp::reply::json(&serde_json::json!({
-            "error": message
-        }));
+async fn resolve_identity(auth_header: Option<String>) -> Result<String, warp::Rejection> {
+    resolve_caller(auth_header)?
+        .identity
+        .ok_or_else(|| {
+            warp::reject::custom(ApiError::new(
+                ErrorCode::IdentityRequired,
+                "A personal token from USER_TOKENS is required for this endpoint",
+            ))
+        })
+}
 
-        Ok(warp::reply::with_status(json, code))
-    } else {
-        // For other rejections, return a generic error
-        let json = warp::reply::json(&serde_json::json!({
-            "error": "Internal server error"
-        }));
-        Ok(warp::reply::with_status(
-            json,
-            warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-        ))
+fn env_token(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|token| !token.is_empty())
+}
+
+// Personal tokens from USER_TOKENS, formatted as "alice=token1,bob=token2"
+fn user_tokens() -> Vec<(String, String)> {
+    env_token("USER_TOKENS")
+        .map(|value| {
+            value
+                .split(',')
+                .filter_map(|entry| {
+                    let (name, token) = entry.split_once('=')?;
+                    let (name, token) = (name.trim(), token.trim());
+                    (!name.is_empty() && !token.is_empty())
+                        .then(|| (name.to_string(), token.to_string()))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Who is calling: the role decides what they may do, the identity is only
+// known when they used a personal token
+struct Caller {
+    role: Role,
+    identity: Option<String>,
+}
+
+impl Caller {
+    fn anonymous(role: Role) -> Self {
+        Self {
+            role,
+            identity: None,
+        }
+    }
+}
+
+fn resolve_caller(auth_header: Option<String>) -> Result<Caller, warp::Rejection> {
+    // Check if authentication is enabled via environment variables
+    let auth_token = env_token("AUTH_TOKEN");
+    let admin_token = env_token("ADMIN_TOKEN");
+    let user_tokens = user_tokens();
+    // Personal tokens replace the shared AUTH_TOKEN, so they also require authentication
+    let auth_required = auth_token.is_some() || !user_tokens.is_empty();
+
+    // Extract token from "Bearer <token>" format
+    let token = auth_header
+        .as_deref()
+        .and_then(|header| header.strip_prefix("Bearer "));
+
+    if let Some(token) = token
+        && let Some((name, _)) = user_tokens.iter().find(|(_, user_token)| user_token == token)
+    {
+        return Ok(Caller {
+            role: Role::User,
+            identity: Some(name.clone()),
+        });
+    }
+
+    if !auth_required && admin_token.is_none() {
+        // If no token is set, allow all requests (for backward compatibility)
+        return Ok(Caller::anonymous(Role::Admin));
+    }
+
+    match token {
+        Some(token) if admin_token.as_deref() == Some(token) => Ok(Caller::anonymous(Role::Admin)),
+        Some(token) if auth_token.as_deref() == Some(token) => Ok(Caller::anonymous(Role::User)),
+        Some(_) => Err(warp::reject::custom(ApiError::new(
+            ErrorCode::InvalidToken,
+            "Invalid authentication token",
+        ))),
+        // Without any user tokens regular operations stay open, only admin ones are guarded
+        None if !auth_required => Ok(Caller::anonymous(Role::User)),
+        None => Err(warp::reject::custom(ApiError::new(
+            ErrorCode::MissingToken,
+            "Missing or invalid Authorization header. Expected: Bearer <token>",
+        ))),
     }
 }
 
-fn validate_golink_pattern(short_link: &str) -> Result<(), &'static str> {
-    let re = Regex::new(r"^go/[a-zA-Z0-9_-]+$").unwrap();
+fn locked_error() -> ApiError {
+    ApiError::new(
+        ErrorCode::GolinkLocked,
+        "Golink is locked and can only be changed by an admin",
+    )
+}
+
+// The version a write has to find the golink at, or None to write whatever
+// it is at. Non-admins are refused locked golinks and always write over the
+// version they checked, so a lock taken in between fails their write.
+async fn write_version(
+    short_link: &str,
+    role: Role,
+    if_match: Option<&str>,
+    storage: &Storage,
+) -> Result<Option<i64>, ApiError> {
+    if role == Role::Admin {
+        return current_version(short_link, if_match, storage).await;
+    }
+    let golink = match storage.get(short_link).await {
+        Ok(golink) => golink,
+        Err(StorageError::NotFound) if if_match.is_some() => {
+            return Err(StorageError::VersionMismatch.into());
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if golink.locked {
+        return Err(locked_error());
+    }
+    if_match_version(if_match, &golink)?;
+    Ok(Some(golink.version))
+}
+
+// Reports a write that found the golink at another version than it expected.
+// Without `If-Match` that version came from `write_version`, so the golink may
+// have been locked since.
+async fn write_error(
+    error: StorageError,
+    short_link: &str,
+    if_match: Option<&str>,
+    storage: &Storage,
+) -> ApiError {
+    if matches!(error, StorageError::VersionMismatch)
+        && if_match.is_none()
+        && let Ok(golink) = storage.get(short_link).await
+        && golink.locked
+    {
+        return locked_error();
+    }
+    error.into()
+}
+
+// Shared with the web UI, which validates names as they are typed
+pub(crate) const GOLINK_PATTERN: &str = r"^go/[a-zA-Z0-9_-]+$";
+pub(crate) const INVALID_PATTERN: &str = "Invalid golink pattern. Must match 'go/[a-zA-Z0-9_-]+'";
+
+pub(crate) fn validate_golink_pattern(short_link: &str) -> Result<(), &'static str> {
+    let re = Regex::new(GOLINK_PATTERN).unwrap();
     if re.is_match(short_link) {
         Ok(())
     } else {
-        Err("Invalid golink pattern. Must match 'go/[a-zA-Z0-9_-]+'")
+        Err(INVALID_PATTERN)
+    }
+}
+
+// Lowercases a tag and collapses anything outside `[a-z0-9_-]` into single dashes,
+// so "Engineering Tools" and "engineering-tools" are the same tag
+pub(crate) fn normalize_tag(tag: &str) -> String {
+    let mut normalized = String::new();
+    for c in tag.trim().chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+            normalized.push(c);
+        } else if !normalized.ends_with('-') {
+            normalized.push('-');
+        }
+    }
+    normalized.trim_matches('-').to_string()
+}
+
+// Normalizes tags, dropping empty ones and duplicates while keeping their order
+pub(crate) fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags.iter().map(|tag| normalize_tag(tag)) {
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
     }
+    normalized
 }
 
+#[utoipa::path(
+    post,
+    path = "/golinks",
+    tag = "golinks",
+    request_body = CreateGolink,
+    responses(
+        (status = 201, description = "Golink created", body = Golink, headers(("ETag" = String))),
+        (status = 400, description = "Invalid short link", body = ErrorResponse),
+        (status = 409, description = "Golink already exists", body = ErrorResponse),
+    )
+)]
 pub async fn create_golink(
     create_golink: CreateGolink,
     storage: Storage,
-) -> Result<warp::reply::WithStatus<warp::reply::Json>, warp::Rejection> {
-    if let Err(e) = validate_golink_pattern(&create_golink.short_link) {
-        let error_response = serde_json::json!({"error": e});
-        return Ok(warp::reply::with_status(
-            warp::reply::json(&error_response),
-            warp::http::StatusCode::BAD_REQUEST,
-        ));
-    }
+) -> Result<warp::reply::Response, ApiError> {
+    validate_golink_pattern(&create_golink.short_link)
+        .map_err(|e| ApiError::new(ErrorCode::InvalidPattern, e))?;
 
+    let now = chrono::Utc::now().to_rfc3339();
     let golink = Golink {
         id: Uuid::new_v4().to_string(),
         short_link: create_golink.short_link.clone(),
         url: create_golink.url,
-        created_at: chrono::Utc::now().to_rfc3339(),
+        owner: create_golink.owner,
+        tags: normalize_tags(&create_golink.tags),
+        description: create_golink.description,
+        created_at: now.clone(),
+        updated_at: now,
+        version: 1,
+        ..Default::default()
     };
 
-    match storage.create(golink.clone()).await {
-        Ok(_) => Ok(warp::reply::with_status(
-            warp::reply::json(&golink),
-            warp::http::StatusCode::CREATED,
-        )),
-        Err(StorageError::AlreadyExists) => {
-            let error_response = serde_json::json!({"error": "Golink already exists"});
-            Ok(warp::reply::with_status(
-                warp::reply::json(&error_response),
-                warp::http::StatusCode::CONFLICT,
-            ))
-        }
-        Err(StorageError::DatabaseError(e)) => {
-            let error_response = serde_json::json!({"error": format!("Database error: {}", e)});
-            Ok(warp::reply::with_status(
-                warp::reply::json(&error_response),
-                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-            ))
-        }
-        Err(StorageError::NotFound) => {
-            let error_response = serde_json::json!({"error": "Unexpected error"});
-            Ok(warp::reply::with_status(
-                warp::reply::json(&error_response),
-                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-            ))
-        }
-    }
+    storage.create(golink.clone()).await?;
+    Ok(golink_response(&golink, warp::http::StatusCode::CREATED))
 }
 
+// Answers with 304 when the client's copy is still current. The ETag and
+// `Last-Modified` only track edits, so such a copy may have older click counts,
+// which is why the ETag is weak here.
+#[utoipa::path(
+    get,
+    path = "/golinks/{prefix}/{name}",
+    tag = "golinks",
+    params(
+        ShortLinkPath,
+        ("If-None-Match" = Option<String>, Header, description = "ETag of the client's copy"),
+        ("If-Modified-Since" = Option<String>, Header, description = "HTTP date"),
+    ),
+    responses(
+        (status = 200, description = "The golink", body = Golink, headers(("ETag" = String, description = "Weak tag of the golink's version"), ("Last-Modified" = String))),
+        (status = 304, description = "The golink wasn't edited since the client's copy"),
+        (status = 404, description = "Golink not found", body = ErrorResponse),
+    )
+)]
 pub async fn get_golink(
     short_link: String,
+    conditional: Conditional,
     storage: Storage,
-) -> Result<warp::reply::WithStatus<warp::reply::Json>, warp::Rejection> {
-    match storage.get(&short_link).await {
-        Ok(golink) => Ok(warp::reply::with_status(
-            warp::reply::json(&golink),
-            warp::http::StatusCode::OK,
-        )),
-        Err(StorageError::NotFound) => Ok(warp::reply::with_status(
-            warp::reply::json(&serde_json::json!({"error": "Golink not found"})),
-            warp::http::StatusCode::NOT_FOUND,
-        )),
-        Err(StorageError::DatabaseError(e)) => {
-            let error_response = serde_json::json!({"error": format!("Database error: {}", e)});
-            Ok(warp::reply::with_status(
-                warp::reply::json(&error_response),
-                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-            ))
-        }
-        Err(_) => {
-            let error_response = serde_json::json!({"error": "Unexpected error"});
-            Ok(warp::reply::with_status(
-                warp::reply::json(&error_response),
-                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-            ))
+) -> Result<warp::reply::Response, ApiError> {
+    let golink = storage.get(&short_link).await?;
+    let etag = golink.weak_etag();
+    if conditional.is_fresh(&etag, parse_rfc3339(&golink.updated_at)) {
+        return Ok(not_modified(&etag, parse_rfc3339(&golink.updated_at)));
+    }
+    let response = warp::reply::json(&golink).into_response();
+    Ok(with_validators(response, &etag, parse_rfc3339(&golink.updated_at)))
+}
+
+// The golink as written, with its strong ETag for `If-Match`
+fn golink_response(golink: &Golink, status: warp::http::StatusCode) -> warp::reply::Response {
+    let response = warp::reply::with_status(warp::reply::json(golink), status).into_response();
+    with_validators(response, &golink.etag(), parse_rfc3339(&golink.updated_at))
+}
+
+// Checks an `If-Match` header against the golink's current ETag. `Ok(None)`
+// lets the write through unconditionally, `Ok(Some(version))` only over that
+// version, and `Err` is the 412 to send instead. The weak tag from a read names
+// the same version, so clients can send back whatever `GET` gave them.
+fn if_match_version(if_match: Option<&str>, golink: &Golink) -> Result<Option<i64>, ApiError> {
+    let Some(if_match) = if_match else {
+        return Ok(None);
+    };
+    if if_match.trim() == "*" {
+        return Ok(None);
+    }
+    let (etag, weak_etag) = (golink.etag(), golink.weak_etag());
+    if if_match.split(',').any(|tag| tag.trim() == etag || tag.trim() == weak_etag) {
+        Ok(Some(golink.version))
+    } else {
+        Err(StorageError::VersionMismatch.into())
+    }
+}
+
+// `if_match_version` for handlers that haven't loaded the golink yet. A
+// missing golink never matches.
+async fn current_version(
+    short_link: &str,
+    if_match: Option<&str>,
+    storage: &Storage,
+) -> Result<Option<i64>, ApiError> {
+    if if_match.is_none() {
+        return Ok(None);
+    }
+    match storage.get(short_link).await {
+        Ok(golink) => if_match_version(if_match, &golink),
+        Err(StorageError::NotFound) => Err(StorageError::VersionMismatch.into()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Builds the ordering and filters for a listing from `sort`, `direction`,
+// `url_contains`, `host`, `created_after`, `created_before`, `owner`, `tag` and
+// `cursor`
+pub(crate) fn list_query(
+    params: &std::collections::HashMap<String, String>,
+) -> Result<ListQuery, String> {
+    let sort = match params.get("sort") {
+        Some(value) => GolinkSort::parse(value).ok_or(
+            "Invalid sort. Must be one of 'short_link', 'created_at', 'updated_at', 'clicks', 'last_used_at'",
+        )?,
+        None => GolinkSort::default(),
+    };
+    let direction = match params.get("direction") {
+        Some(value) => Some(
+            SortDirection::parse(value).ok_or("Invalid direction. Must be 'asc' or 'desc'")?,
+        ),
+        None => None,
+    };
+    let text = |name: &str| {
+        params
+            .get(name)
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    let timestamp = |name: &str| -> Result<Option<String>, String> {
+        match text(name) {
+            Some(value) => parse_timestamp(&value).map(Some).ok_or(format!(
+                "Invalid {}. Must be an RFC 3339 timestamp or a YYYY-MM-DD date",
+                name
+            )),
+            None => Ok(None),
         }
+    };
+    let filter = GolinkFilter {
+        url_contains: text("url_contains"),
+        host: text("host"),
+        created_after: timestamp("created_after")?,
+        created_before: timestamp("created_before")?,
+        owner: text("owner"),
+        tag: text("tag").map(|tag| normalize_tag(&tag)),
+    };
+    let after = match params.get("cursor") {
+        Some(token) => Some(Cursor::decode(token).ok_or("Invalid cursor")?),
+        None => None,
+    };
+    // A cursor only makes sense in the ordering it was taken from
+    if let Some(cursor) = &after
+        && (cursor.sort, cursor.direction) != (sort, direction.unwrap_or(sort.default_direction()))
+    {
+        return Err("Invalid cursor. It was issued for another sort or direction".into());
     }
+    Ok(ListQuery {
+        sort,
+        direction,
+        filter,
+        after,
+    })
 }
 
+// Normalizes to the UTC RFC 3339 form golinks are stored with, so timestamps
+// compare correctly as strings. A bare date means midnight UTC.
+fn parse_timestamp(value: &str) -> Option<String> {
+    if let Ok(timestamp) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Some(timestamp.with_timezone(&chrono::Utc).to_rfc3339());
+    }
+    let date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc().to_rfc3339())
+}
+
+// The list's ETag and `Last-Modified` come from the storage revision, so a
+// client polling with `If-None-Match` gets a 304 until any golink changes
+#[utoipa::path(
+    get,
+    path = "/golinks",
+    tag = "golinks",
+    params(
+        ("page" = Option<usize>, Query, description = "Page number, starting at 1"),
+        ("page_size" = Option<usize>, Query, description = "Between 1 and 100, defaults to 10"),
+        ("cursor" = Option<String>, Query, description = "`next_cursor` of the previous page, used instead of `page`"),
+        ("sort" = Option<String>, Query, description = "`short_link`, `created_at`, `updated_at`, `clicks` or `last_used_at`"),
+        ("direction" = Option<String>, Query, description = "`asc` or `desc`"),
+        ("url_contains" = Option<String>, Query, description = "Case-insensitive substring of the URL"),
+        ("host" = Option<String>, Query, description = "Host of the URL, ignoring case"),
+        ("created_after" = Option<String>, Query, description = "RFC 3339 timestamp or YYYY-MM-DD"),
+        ("created_before" = Option<String>, Query, description = "RFC 3339 timestamp or YYYY-MM-DD"),
+        ("owner" = Option<String>, Query),
+        ("tag" = Option<String>, Query),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a list the client already has"),
+        ("If-Modified-Since" = Option<String>, Header, description = "HTTP date"),
+    ),
+    responses(
+        (status = 200, description = "All golinks, or one page of them when paginating", body = GolinkList,
+            headers(("ETag" = String), ("Last-Modified" = String))),
+        (status = 304, description = "No golink changed since the client's copy"),
+        (status = 400, description = "Invalid sort, filter or cursor", body = ErrorResponse),
+    )
+)]
 pub async fn get_all_golinks(
     params: std::collections::HashMap<String, String>,
+    conditional: Conditional,
     storage: Storage,
-) -> Result<warp::reply::WithStatus<warp::reply::Json>, warp::Rejection> {
+) -> Result<warp::reply::Response, ApiError> {
     // Parse pagination parameters
     let page = params
         .get("page")
@@ -228,141 +613,433 @@ pub async fn get_all_golinks(
         .get("page_size")
         .and_then(|p| p.parse::<usize>().ok())
         .unwrap_or(10)
-        .min(100)
-        .max(1);
+        .clamp(1, 100);
+
+    let query =
+        list_query(&params).map_err(|e| ApiError::new(ErrorCode::InvalidParameter, e))?;
+
+    // Read before the golinks, so a write in between only makes the next
+    // request download the list again
+    let revision = storage.revision().await?;
+    let etag = revision.etag();
+    let last_modified = parse_rfc3339(&revision.changed_at);
+    if conditional.is_fresh(&etag, last_modified) {
+        return Ok(not_modified(&etag, last_modified));
+    }
 
     // Check if pagination is requested
-    let use_pagination = params.contains_key("page") || params.contains_key("page_size");
+    let use_pagination = params.contains_key("page")
+        || params.contains_key("page_size")
+        || query.after.is_some();
 
     if use_pagination {
-        match storage.get_paginated(page, page_size).await {
-            Ok((golinks, total_items)) => {
-                let total_pages = (total_items + page_size - 1) / page_size;
-                let pagination_info = PaginationInfo {
-                    page,
-                    page_size,
-                    total_items,
-                    total_pages,
-                };
-                let response = PaginatedResponse {
-                    data: golinks,
-                    pagination: pagination_info,
-                };
-                Ok(warp::reply::with_status(
-                    warp::reply::json(&response),
-                    warp::http::StatusCode::OK,
-                ))
-            }
-            Err(StorageError::DatabaseError(e)) => {
-                let error_response = serde_json::json!({"error": format!("Database error: {}", e)});
-                Ok(warp::reply::with_status(
-                    warp::reply::json(&error_response),
-                    warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-                ))
-            }
-            Err(_) => {
-                let error_response = serde_json::json!({"error": "Unexpected error"});
-                Ok(warp::reply::with_status(
-                    warp::reply::json(&error_response),
-                    warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-                ))
-            }
-        }
+        // A cursor replaces the page number, resuming right after the last
+        // golink of the previous page
+        let offset = match query.after {
+            Some(_) => 0,
+            None => (page - 1) * page_size,
+        };
+        // One extra golink tells whether there is a next page
+        let (mut golinks, total_items) =
+            storage.list(&query, offset, Some(page_size + 1)).await?;
+        let has_more = golinks.len() > page_size;
+        golinks.truncate(page_size);
+        let next_cursor = golinks
+            .last()
+            .filter(|_| has_more)
+            .map(|last| Cursor::after(&query, last).encode());
+        let total_pages = total_items.div_ceil(page_size);
+        let pagination_info = PaginationInfo {
+            page: query.after.is_none().then_some(page),
+            page_size,
+            total_items,
+            total_pages,
+            next_cursor,
+        };
+        let response = PaginatedResponse {
+            data: golinks,
+            pagination: pagination_info,
+        };
+        let response = warp::reply::json(&response).into_response();
+        Ok(with_validators(response, &etag, last_modified))
     } else {
         // Return all items without pagination for backward compatibility
-        match storage.get_all().await {
-            Ok(golinks) => Ok(warp::reply::with_status(
-                warp::reply::json(&golinks),
-                warp::http::StatusCode::OK,
-            )),
-            Err(StorageError::DatabaseError(e)) => {
-                let error_response = serde_json::json!({"error": format!("Database error: {}", e)});
-                Ok(warp::reply::with_status(
-                    warp::reply::json(&error_response),
-                    warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-                ))
-            }
-            Err(_) => {
-                let error_response = serde_json::json!({"error": "Unexpected error"});
-                Ok(warp::reply::with_status(
-                    warp::reply::json(&error_response),
-                    warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-                ))
+        let (golinks, _) = storage.list(&query, 0, None).await?;
+        let response = warp::reply::json(&golinks).into_response();
+        Ok(with_validators(response, &etag, last_modified))
+    }
+}
+
+// Redirects to the golink's URL and counts the click along with its
+// referrer host and client class
+#[utoipa::path(
+    get,
+    path = "/go/{name}",
+    tag = "golinks",
+    params(("name" = String, Path, example = "docs")),
+    responses(
+        (status = 302, description = "Redirect to the golink's URL", headers(("Location" = String))),
+        (status = 200, description = "Landing page of a collection", body = String, content_type = "text/html"),
+        (status = 404, description = "Golink not found", body = ErrorResponse),
+        (status = 502, description = "Golink URL is not a valid redirect target", body = ErrorResponse),
+    )
+)]
+pub async fn redirect_golink(
+    short_link: String,
+    referer: Option<String>,
+    user_agent: Option<String>,
+    clicks: ClickRecorder,
+    storage: Storage,
+) -> Result<warp::reply::Response, ApiError> {
+    // Old names of renamed golinks keep working
+    let golink = match storage.get(&short_link).await {
+        Err(StorageError::NotFound) => match storage.resolve_alias(&short_link).await {
+            Ok(target) => storage.get(&target).await,
+            Err(e) => Err(e),
+        },
+        result => result,
+    };
+
+    let golink = match golink {
+        Ok(golink) => golink,
+        Err(StorageError::NotFound) => {
+            // Collections share the golink namespace and resolve to a landing page
+            if let Some(response) = collection_landing_page(&short_link, &storage).await {
+                return Ok(response);
             }
+            return Err(StorageError::NotFound.into());
         }
-    }
+        Err(e) => return Err(e.into()),
+    };
+
+    let location = warp::http::HeaderValue::from_str(&golink.url).map_err(|_| {
+        ApiError::new(
+            ErrorCode::InvalidRedirect,
+            "Golink URL is not a valid redirect target",
+        )
+    })?;
+
+    let source = ClickSource::from_headers(referer.as_deref(), user_agent.as_deref());
+    clicks.record(&golink.short_link, &source);
+
+    let mut response = warp::reply::Response::default();
+    *response.status_mut() = warp::http::StatusCode::FOUND;
+    response
+        .headers_mut()
+        .insert(warp::http::header::LOCATION, location);
+    Ok(response)
 }
 
+// Replaces the golink's URL. With `If-Match` the write only happens if the
+// golink still has one of the listed ETags, otherwise it fails with 412.
+#[utoipa::path(
+    put,
+    path = "/golinks/{prefix}/{name}",
+    tag = "golinks",
+    params(ShortLinkPath, ("If-Match" = Option<String>, Header, description = "Only write if the golink still has one of these ETags")),
+    request_body = UpdateGolink,
+    responses(
+        (status = 200, description = "Golink updated", body = Golink, headers(("ETag" = String))),
+        (status = 403, description = "Golink is locked", body = ErrorResponse),
+        (status = 404, description = "Golink not found", body = ErrorResponse),
+        (status = 412, description = "Golink was changed by someone else", body = ErrorResponse),
+    )
+)]
 pub async fn update_golink(
     short_link: String,
     update_golink: UpdateGolink,
+    role: Role,
+    if_match: Option<String>,
     storage: Storage,
-) -> Result<warp::reply::WithStatus<warp::reply::Json>, warp::Rejection> {
-    match storage.update(&short_link, update_golink.url).await {
-        Ok(golink) => Ok(warp::reply::with_status(
-            warp::reply::json(&golink),
-            warp::http::StatusCode::OK,
-        )),
-        Err(StorageError::NotFound) => Ok(warp::reply::with_status(
-            warp::reply::json(&serde_json::json!({"error": "Golink not found"})),
-            warp::http::StatusCode::NOT_FOUND,
-        )),
-        Err(StorageError::DatabaseError(e)) => {
-            let error_response = serde_json::json!({"error": format!("Database error: {}", e)});
-            Ok(warp::reply::with_status(
-                warp::reply::json(&error_response),
-                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-            ))
+) -> Result<warp::reply::Response, ApiError> {
+    let version = write_version(&short_link, role, if_match.as_deref(), &storage).await?;
+
+    let result = match version {
+        Some(version) => {
+            let changes = GolinkChanges {
+                url: Some(update_golink.url),
+                ..Default::default()
+            };
+            storage.apply_changes_if(&short_link, version, changes).await
         }
-        Err(_) => {
-            let error_response = serde_json::json!({"error": "Unexpected error"});
-            Ok(warp::reply::with_status(
-                warp::reply::json(&error_response),
-                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-            ))
+        None => storage.update(&short_link, update_golink.url).await,
+    };
+    match result {
+        Ok(golink) => Ok(golink_response(&golink, warp::http::StatusCode::OK)),
+        Err(e) => Err(write_error(e, &short_link, if_match.as_deref(), &storage).await),
+    }
+}
+
+// Golink fields that can never change once the golink exists
+const IMMUTABLE_FIELDS: [&str; 3] = ["id", "short_link", "created_at"];
+
+// Golink fields maintained by the service itself
+const READ_ONLY_FIELDS: [&str; 4] = ["clicks", "last_used_at", "updated_at", "version"];
+
+// Applies an RFC 7396 JSON merge patch to `target`
+pub(crate) fn merge_patch(target: &mut serde_json::Value, patch: &serde_json::Value) {
+    let serde_json::Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = serde_json::Value::Object(serde_json::Map::new());
+    }
+    let target = target.as_object_mut().unwrap();
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge_patch(target.entry(key.clone()).or_insert(serde_json::Value::Null), value);
         }
     }
 }
 
-pub async fn delete_golink(
+fn patch_error(message: &str) -> ApiError {
+    ApiError::new(ErrorCode::InvalidBody, message)
+}
+
+// Works out which fields a merge patch changes, rejecting patches that touch
+// fields clients don't control
+fn golink_changes(golink: &Golink, patch: &serde_json::Value) -> Result<GolinkChanges, ApiError> {
+    let Some(fields) = patch.as_object() else {
+        return Err(patch_error("Patch must be a JSON object"));
+    };
+
+    let mut patched = serde_json::to_value(golink).unwrap();
+    for key in fields.keys() {
+        if !patched.as_object().unwrap().contains_key(key) {
+            return Err(patch_error(&format!("Unknown field '{}'", key)));
+        }
+    }
+    merge_patch(&mut patched, patch);
+
+    let patched: Golink = serde_json::from_value(patched)
+        .map_err(|e| patch_error(&format!("Invalid patch: {}", e)))?;
+
+    let original = serde_json::to_value(golink).unwrap();
+    let updated = serde_json::to_value(&patched).unwrap();
+    for field in IMMUTABLE_FIELDS {
+        if original[field] != updated[field] {
+            return Err(patch_error(&format!("Field '{}' is immutable", field)));
+        }
+    }
+    for field in READ_ONLY_FIELDS {
+        if original[field] != updated[field] {
+            return Err(patch_error(&format!("Field '{}' is read-only", field)));
+        }
+    }
+
+    Ok(GolinkChanges {
+        url: (patched.url != golink.url).then_some(patched.url),
+        owner: (patched.owner != golink.owner).then_some(patched.owner),
+        locked: (patched.locked != golink.locked).then_some(patched.locked),
+        deprecated: (patched.deprecated != golink.deprecated).then_some(patched.deprecated),
+        tags: Some(normalize_tags(&patched.tags)).filter(|tags| *tags != golink.tags),
+        description: (patched.description != golink.description).then_some(patched.description),
+    })
+}
+
+// Partial update with an RFC 7396 merge patch against the golink's JSON representation.
+// The body is parsed here since `warp::body::json` refuses `application/merge-patch+json`.
+#[utoipa::path(
+    patch,
+    path = "/golinks/{prefix}/{name}",
+    tag = "golinks",
+    params(ShortLinkPath, ("If-Match" = Option<String>, Header, description = "Only write if the golink still has one of these ETags")),
+    request_body(content = Object, description = "JSON merge patch of the golink", content_type = "application/merge-patch+json"),
+    responses(
+        (status = 200, description = "Golink patched", body = Golink, headers(("ETag" = String))),
+        (status = 400, description = "Invalid patch or read-only field", body = ErrorResponse),
+        (status = 403, description = "Golink is locked, or `locked` or `deprecated` changed without the admin token", body = ErrorResponse),
+        (status = 404, description = "Golink not found", body = ErrorResponse),
+        (status = 412, description = "Golink was changed by someone else", body = ErrorResponse),
+    )
+)]
+pub async fn patch_golink(
     short_link: String,
+    body: warp::hyper::body::Bytes,
+    role: Role,
+    if_match: Option<String>,
     storage: Storage,
-) -> Result<warp::reply::WithStatus<warp::reply::Json>, warp::Rejection> {
-    match storage.delete(&short_link).await {
-        Ok(_) => Ok(warp::reply::with_status(
-            warp::reply::json(&serde_json::json!({"message": "Golink deleted successfully"})),
-            warp::http::StatusCode::OK,
-        )),
-        Err(StorageError::NotFound) => Ok(warp::reply::with_status(
-            warp::reply::json(&serde_json::json!({"error": "Golink not found"})),
-            warp::http::StatusCode::NOT_FOUND,
-        )),
-        Err(StorageError::DatabaseError(e)) => {
-            let error_response = serde_json::json!({"error": format!("Database error: {}", e)});
-            Ok(warp::reply::with_status(
-                warp::reply::json(&error_response),
-                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-            ))
+) -> Result<warp::reply::Response, ApiError> {
+    let patch: serde_json::Value = serde_json::from_slice(&body)
+        .map_err(|e| patch_error(&format!("Invalid JSON: {}", e)))?;
+
+    let golink = match storage.get(&short_link).await {
+        Ok(golink) => golink,
+        Err(StorageError::NotFound) if if_match.is_some() => {
+            return Err(StorageError::VersionMismatch.into());
         }
-        Err(_) => {
-            let error_response = serde_json::json!({"error": "Unexpected error"});
-            Ok(warp::reply::with_status(
-                warp::reply::json(&error_response),
-                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-            ))
+        Err(e) => return Err(e.into()),
+    };
+    let mut version = if_match_version(if_match.as_deref(), &golink)?;
+    let changes = golink_changes(&golink, &patch)?;
+
+    if role != Role::Admin {
+        if golink.locked {
+            return Err(locked_error());
+        }
+        // Over the version checked here, so a lock taken since fails the write
+        version = Some(golink.version);
+        if changes.locked.is_some() {
+            return Err(ApiError::new(
+                ErrorCode::AdminRequired,
+                "Admin role required to lock or unlock golinks",
+            ));
+        }
+        // Deprecation is an admin decision, see `deprecate_stale_golinks`
+        if changes.deprecated.is_some() {
+            return Err(ApiError::new(
+                ErrorCode::AdminRequired,
+                "Admin role required to deprecate or undeprecate golinks",
+            ));
+        }
+    }
+
+    if changes.is_empty() {
+        return Ok(golink_response(&golink, warp::http::StatusCode::OK));
+    }
+
+    let result = match version {
+        Some(version) => storage.apply_changes_if(&short_link, version, changes).await,
+        None => storage.apply_changes(&short_link, changes).await,
+    };
+    match result {
+        Ok(golink) => Ok(golink_response(&golink, warp::http::StatusCode::OK)),
+        Err(e) => Err(write_error(e, &short_link, if_match.as_deref(), &storage).await),
+    }
+}
+
+// Moves a golink to a new name without losing its id, creation time or clicks
+#[utoipa::path(
+    post,
+    path = "/golinks/{prefix}/{name}/rename",
+    tag = "golinks",
+    params(ShortLinkPath),
+    request_body = RenameGolink,
+    responses(
+        (status = 200, description = "Golink renamed", body = Golink, headers(("ETag" = String))),
+        (status = 400, description = "Invalid new short link", body = ErrorResponse),
+        (status = 403, description = "Golink is locked", body = ErrorResponse),
+        (status = 404, description = "Golink not found", body = ErrorResponse),
+        (status = 409, description = "New short link is taken", body = ErrorResponse),
+        (status = 412, description = "Golink was changed while renaming it", body = ErrorResponse),
+    )
+)]
+pub async fn rename_golink(
+    short_link: String,
+    rename: RenameGolink,
+    role: Role,
+    storage: Storage,
+) -> Result<warp::reply::Response, ApiError> {
+    validate_golink_pattern(&rename.new_short_link)
+        .map_err(|e| ApiError::new(ErrorCode::InvalidPattern, e))?;
+    if rename.new_short_link == short_link {
+        return Err(ApiError::new(
+            ErrorCode::InvalidBody,
+            "New short link must differ from the current one",
+        ));
+    }
+
+    let version = write_version(&short_link, role, None, &storage).await?;
+
+    let result = match version {
+        Some(version) => {
+            storage
+                .rename_if(&short_link, &rename.new_short_link, rename.keep_alias, version)
+                .await
+        }
+        None => {
+            storage
+                .rename(&short_link, &rename.new_short_link, rename.keep_alias)
+                .await
         }
+    };
+    match result {
+        Ok(golink) => Ok(golink_response(&golink, warp::http::StatusCode::OK)),
+        Err(StorageError::AlreadyExists) => Err(ApiError::new(
+            ErrorCode::GolinkConflict,
+            "A golink, alias or collection with this name already exists",
+        )),
+        Err(e) => Err(write_error(e, &short_link, None, &storage).await),
     }
 }
 
+// Deletes the golink. With `If-Match` it only goes if the golink still has one
+// of the listed ETags, otherwise it fails with 412.
+#[utoipa::path(
+    delete,
+    path = "/golinks/{prefix}/{name}",
+    tag = "golinks",
+    params(ShortLinkPath, ("If-Match" = Option<String>, Header, description = "Only write if the golink still has one of these ETags")),
+    responses(
+        (status = 200, description = "Golink deleted", body = MessageResponse),
+        (status = 403, description = "Golink is locked", body = ErrorResponse),
+        (status = 404, description = "Golink not found", body = ErrorResponse),
+        (status = 412, description = "Golink was changed by someone else", body = ErrorResponse),
+    )
+)]
+pub async fn delete_golink(
+    short_link: String,
+    role: Role,
+    if_match: Option<String>,
+    storage: Storage,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, ApiError> {
+    let version = write_version(&short_link, role, if_match.as_deref(), &storage).await?;
+
+    let result = match version {
+        Some(version) => storage.delete_if(&short_link, version).await,
+        None => storage.delete(&short_link).await,
+    };
+    if let Err(e) = result {
+        return Err(write_error(e, &short_link, if_match.as_deref(), &storage).await);
+    }
+    Ok(warp::reply::with_status(
+        warp::reply::json(&serde_json::json!({"message": "Golink deleted successfully"})),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/golinks/{prefix}/{name}/lock",
+    tag = "admin",
+    params(ShortLinkPath),
+    request_body = SetLock,
+    responses(
+        (status = 200, description = "Lock changed", body = Golink),
+        (status = 403, description = "Admin token required", body = ErrorResponse),
+        (status = 404, description = "Golink not found", body = ErrorResponse),
+    )
+)]
+pub async fn set_golink_lock(
+    short_link: String,
+    set_lock: SetLock,
+    role: Role,
+    storage: Storage,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, ApiError> {
+    if role != Role::Admin {
+        return Err(ApiError::new(ErrorCode::AdminRequired, "Admin role required"));
+    }
+
+    let golink = storage.set_locked(&short_link, set_lock.locked).await?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&golink),
+        warp::http::StatusCode::OK,
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::storage::HashMapStorage;
-    use std::sync::{Arc, Mutex};
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
     use warp::Reply;
 
     // Mutex to ensure authentication tests run sequentially to avoid environment variable conflicts
-    static AUTH_TEST_LOCK: Mutex<()> = Mutex::new(());
+    static AUTH_TEST_LOCK: Mutex<()> = Mutex::const_new(());
 
     async fn create_test_storage() -> Storage {
         Arc::new(HashMapStorage::new())
@@ -374,6 +1051,7 @@ mod tests {
             short_link: short_link.to_string(),
             url: url.to_string(),
             created_at: chrono::Utc::now().to_rfc3339(),
+            ..Default::default()
         }
     }
 
@@ -403,6 +1081,7 @@ mod tests {
         let create_req = CreateGolink {
             short_link: "go/test".to_string(),
             url: "https://example.com".to_string(),
+            ..Default::default()
         };
 
         let response = create_golink(create_req, storage).await;
@@ -419,14 +1098,13 @@ mod tests {
         let create_req = CreateGolink {
             short_link: "invalid".to_string(),
             url: "https://example.com".to_string(),
+            ..Default::default()
         };
 
         let response = create_golink(create_req, storage).await;
-        assert!(response.is_ok());
-        
-        let reply = response.unwrap();
-        let status = reply.into_response().status();
-        assert_eq!(status, warp::http::StatusCode::BAD_REQUEST);
+        let error = response.unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidPattern);
+        assert_eq!(error.status(), warp::http::StatusCode::BAD_REQUEST);
     }
 
     #[tokio::test]
@@ -440,14 +1118,13 @@ mod tests {
         let create_req = CreateGolink {
             short_link: "go/test".to_string(),
             url: "https://example.com".to_string(),
+            ..Default::default()
         };
 
         let response = create_golink(create_req, storage).await;
-        assert!(response.is_ok());
-        
-        let reply = response.unwrap();
-        let status = reply.into_response().status();
-        assert_eq!(status, warp::http::StatusCode::CONFLICT);
+        let error = response.unwrap_err();
+        assert_eq!(error.code, ErrorCode::GolinkConflict);
+        assert_eq!(error.status(), warp::http::StatusCode::CONFLICT);
     }
 
     #[tokio::test]
@@ -458,7 +1135,7 @@ mod tests {
         // Pre-populate storage
         storage.create(golink.clone()).await.unwrap();
 
-        let response = get_golink("go/test".to_string(), storage).await;
+        let response = get_golink("go/test".to_string(), Conditional::default(), storage).await;
         assert!(response.is_ok());
         
         let reply = response.unwrap();
@@ -470,12 +1147,10 @@ mod tests {
     async fn test_get_golink_not_found() {
         let storage = create_test_storage().await;
 
-        let response = get_golink("go/nonexistent".to_string(), storage).await;
-        assert!(response.is_ok());
-
-        let reply = response.unwrap();
-        let status = reply.into_response().status();
-        assert_eq!(status, warp::http::StatusCode::NOT_FOUND);
+        let response = get_golink("go/nonexistent".to_string(), Conditional::default(), storage).await;
+        let error = response.unwrap_err();
+        assert_eq!(error.code, ErrorCode::GolinkNotFound);
+        assert_eq!(error.status(), warp::http::StatusCode::NOT_FOUND);
     }
 
     #[tokio::test]
@@ -489,7 +1164,7 @@ mod tests {
         storage.create(golink2).await.unwrap();
 
         let params = std::collections::HashMap::new();
-        let response = get_all_golinks(params, storage).await;
+        let response = get_all_golinks(params, Conditional::default(), storage).await;
         assert!(response.is_ok());
         
         let reply = response.unwrap();
@@ -513,7 +1188,7 @@ mod tests {
         params.insert("page".to_string(), "1".to_string());
         params.insert("page_size".to_string(), "2".to_string());
 
-        let response = get_all_golinks(params, storage).await;
+        let response = get_all_golinks(params, Conditional::default(), storage).await;
         assert!(response.is_ok());
         
         let reply = response.unwrap();
@@ -521,6 +1196,50 @@ mod tests {
         assert_eq!(status, warp::http::StatusCode::OK);
     }
 
+    #[tokio::test]
+    async fn test_get_all_golinks_invalid_sort() {
+        let storage = create_test_storage().await;
+
+        let mut params = std::collections::HashMap::new();
+        params.insert("sort".to_string(), "popularity".to_string());
+
+        let response = get_all_golinks(params, Conditional::default(), storage).await;
+        let error = response.unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidParameter);
+        assert_eq!(error.status(), warp::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_redirect_golink_records_click() {
+        let storage = create_test_storage().await;
+        storage
+            .create(create_test_golink("go/test", "https://example.com"))
+            .await
+            .unwrap();
+        let clicks = ClickRecorder::new(storage.clone());
+
+        let response = redirect_golink("go/test".to_string(), None, None, clicks.clone(), storage.clone())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), warp::http::StatusCode::FOUND);
+        assert_eq!(response.headers()["location"], "https://example.com");
+
+        clicks.flush().await.unwrap();
+        assert_eq!(storage.get("go/test").await.unwrap().clicks, 1);
+    }
+
+    #[tokio::test]
+    async fn test_redirect_golink_not_found() {
+        let storage = create_test_storage().await;
+        let clicks = ClickRecorder::new(storage.clone());
+
+        let error = redirect_golink("go/nonexistent".to_string(), None, None, clicks, storage)
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::GolinkNotFound);
+        assert_eq!(error.status(), warp::http::StatusCode::NOT_FOUND);
+    }
+
     #[tokio::test]
     async fn test_update_golink_success() {
         let storage = create_test_storage().await;
@@ -533,7 +1252,7 @@ mod tests {
             url: "https://updated.com".to_string(),
         };
 
-        let response = update_golink("go/test".to_string(), update_req, storage).await;
+        let response = update_golink("go/test".to_string(), update_req, Role::User, None, storage).await;
         assert!(response.is_ok());
         
         let reply = response.unwrap();
@@ -549,12 +1268,10 @@ mod tests {
             url: "https://updated.com".to_string(),
         };
 
-        let response = update_golink("go/nonexistent".to_string(), update_req, storage).await;
-        assert!(response.is_ok());
-        
-        let reply = response.unwrap();
-        let status = reply.into_response().status();
-        assert_eq!(status, warp::http::StatusCode::NOT_FOUND);
+        let response = update_golink("go/nonexistent".to_string(), update_req, Role::User, None, storage).await;
+        let error = response.unwrap_err();
+        assert_eq!(error.code, ErrorCode::GolinkNotFound);
+        assert_eq!(error.status(), warp::http::StatusCode::NOT_FOUND);
     }
 
     #[tokio::test]
@@ -565,7 +1282,7 @@ mod tests {
         // Pre-populate storage
         storage.create(golink.clone()).await.unwrap();
 
-        let response = delete_golink("go/test".to_string(), storage).await;
+        let response = delete_golink("go/test".to_string(), Role::User, None, storage).await;
         assert!(response.is_ok());
         
         let reply = response.unwrap();
@@ -577,17 +1294,423 @@ mod tests {
     async fn test_delete_golink_not_found() {
         let storage = create_test_storage().await;
 
-        let response = delete_golink("go/nonexistent".to_string(), storage).await;
-        assert!(response.is_ok());
-        
-        let reply = response.unwrap();
-        let status = reply.into_response().status();
-        assert_eq!(status, warp::http::StatusCode::NOT_FOUND);
+        let response = delete_golink("go/nonexistent".to_string(), Role::User, None, storage).await;
+        let error = response.err().unwrap();
+        assert_eq!(error.code, ErrorCode::GolinkNotFound);
+        assert_eq!(error.status(), warp::http::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_update_locked_golink_forbidden_for_user() {
+        let storage = create_test_storage().await;
+        let mut golink = create_test_golink("go/test", "https://example.com");
+        golink.locked = true;
+        storage.create(golink).await.unwrap();
+
+        let update_req = UpdateGolink {
+            url: "https://updated.com".to_string(),
+        };
+
+        let response = update_golink("go/test".to_string(), update_req, Role::User, None, storage.clone()).await;
+        let error = response.unwrap_err();
+        assert_eq!(error.code, ErrorCode::GolinkLocked);
+        assert_eq!(error.status(), warp::http::StatusCode::FORBIDDEN);
+        assert_eq!(storage.get("go/test").await.unwrap().url, "https://example.com");
+    }
+
+    #[tokio::test]
+    async fn test_update_locked_golink_allowed_for_admin() {
+        let storage = create_test_storage().await;
+        let mut golink = create_test_golink("go/test", "https://example.com");
+        golink.locked = true;
+        storage.create(golink).await.unwrap();
+
+        let update_req = UpdateGolink {
+            url: "https://updated.com".to_string(),
+        };
+
+        let response = update_golink("go/test".to_string(), update_req, Role::Admin, None, storage).await;
+        let status = response.unwrap().into_response().status();
+        assert_eq!(status, warp::http::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_delete_locked_golink_forbidden_for_user() {
+        let storage = create_test_storage().await;
+        let mut golink = create_test_golink("go/test", "https://example.com");
+        golink.locked = true;
+        storage.create(golink).await.unwrap();
+
+        let response = delete_golink("go/test".to_string(), Role::User, None, storage.clone()).await;
+        let error = response.err().unwrap();
+        assert_eq!(error.code, ErrorCode::GolinkLocked);
+        assert_eq!(error.status(), warp::http::StatusCode::FORBIDDEN);
+        assert!(storage.exists("go/test").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_write_after_lock_is_refused() {
+        let storage = create_test_storage().await;
+        storage
+            .create(create_test_golink("go/test", "https://example.com"))
+            .await
+            .unwrap();
+
+        // An admin locks the golink between a user's check and write
+        let version = write_version("go/test", Role::User, None, &storage)
+            .await
+            .unwrap()
+            .unwrap();
+        storage.set_locked("go/test", true).await.unwrap();
+        let changes = GolinkChanges {
+            url: Some("https://updated.com".to_string()),
+            ..Default::default()
+        };
+        let error = storage
+            .apply_changes_if("go/test", version, changes)
+            .await
+            .unwrap_err();
+        let error = write_error(error, "go/test", None, &storage).await;
+        assert_eq!(error.code, ErrorCode::GolinkLocked);
+        assert_eq!(storage.get("go/test").await.unwrap().url, "https://example.com");
+
+        // Other changes in between are plain conflicts
+        storage.set_locked("go/test", false).await.unwrap();
+        let error = write_error(StorageError::VersionMismatch, "go/test", None, &storage).await;
+        assert_eq!(error.code, ErrorCode::GolinkChanged);
+        assert_eq!(error.status(), warp::http::StatusCode::PRECONDITION_FAILED);
+    }
+
+    #[tokio::test]
+    async fn test_set_golink_lock_requires_admin() {
+        let storage = create_test_storage().await;
+        storage
+            .create(create_test_golink("go/test", "https://example.com"))
+            .await
+            .unwrap();
+
+        let response =
+            set_golink_lock("go/test".to_string(), SetLock { locked: true }, Role::User, storage.clone()).await;
+        let error = response.err().unwrap();
+        assert_eq!(error.code, ErrorCode::AdminRequired);
+        assert_eq!(error.status(), warp::http::StatusCode::FORBIDDEN);
+        assert!(!storage.get("go/test").await.unwrap().locked);
+
+        let response =
+            set_golink_lock("go/test".to_string(), SetLock { locked: true }, Role::Admin, storage.clone()).await;
+        let status = response.unwrap().into_response().status();
+        assert_eq!(status, warp::http::StatusCode::OK);
+        assert!(storage.get("go/test").await.unwrap().locked);
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("plain-token_1.2~"), "plain-token_1.2~");
+        assert_eq!(percent_decode("a%3Bb%20c%25"), "a;b c%");
+        assert_eq!(percent_decode("%E2%98%85"), "★");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+    }
+
+    #[tokio::test]
+    async fn test_resolve_role() {
+        let _guard = AUTH_TEST_LOCK.lock().await;
+
+        // Set both tokens
+        unsafe {
+            std::env::set_var("AUTH_TOKEN", "test-token-123");
+            std::env::set_var("ADMIN_TOKEN", "admin-token-456");
+        }
+
+        let role = resolve_role(Some("Bearer admin-token-456".to_string())).await;
+        assert_eq!(role.unwrap(), Role::Admin);
+        let role = resolve_role(Some("Bearer test-token-123".to_string())).await;
+        assert_eq!(role.unwrap(), Role::User);
+        assert!(resolve_role(None).await.is_err());
+
+        // Without AUTH_TOKEN only admin access is guarded
+        unsafe { std::env::remove_var("AUTH_TOKEN"); }
+        assert_eq!(resolve_role(None).await.unwrap(), Role::User);
+
+        // Clean up
+        unsafe { std::env::remove_var("ADMIN_TOKEN"); }
+    }
+
+    #[tokio::test]
+    async fn test_rename_golink() {
+        let storage = create_test_storage().await;
+        storage
+            .create(create_test_golink("go/oldteam-wiki", "https://wiki.example.com"))
+            .await
+            .unwrap();
+        let mut locked = create_test_golink("go/benefits", "https://benefits.example.com");
+        locked.locked = true;
+        storage.create(locked).await.unwrap();
+
+        let cases = [
+            ("go/oldteam-wiki", "invalid", warp::http::StatusCode::BAD_REQUEST),
+            ("go/oldteam-wiki", "go/oldteam-wiki", warp::http::StatusCode::BAD_REQUEST),
+            ("go/oldteam-wiki", "go/benefits", warp::http::StatusCode::CONFLICT),
+            ("go/missing", "go/new", warp::http::StatusCode::NOT_FOUND),
+            ("go/benefits", "go/perks", warp::http::StatusCode::FORBIDDEN),
+        ];
+        for (short_link, new_short_link, status) in cases {
+            let rename = RenameGolink {
+                new_short_link: new_short_link.to_string(),
+                ..Default::default()
+            };
+            let response =
+                rename_golink(short_link.to_string(), rename, Role::User, storage.clone())
+                    .await
+                    .into_response();
+            assert_eq!(response.status(), status);
+        }
+
+        let rename = RenameGolink {
+            new_short_link: "go/platform-wiki".to_string(),
+            keep_alias: true,
+        };
+        let short_link = "go/oldteam-wiki".to_string();
+        let response = rename_golink(short_link.clone(), rename, Role::User, storage.clone())
+            .await
+            .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+        let renamed = storage.get("go/platform-wiki").await.unwrap();
+        assert_eq!(response.headers()["etag"], renamed.etag().as_str());
+        assert!(response.headers().contains_key("last-modified"));
+
+        // The old name still redirects
+        let clicks = ClickRecorder::new(storage.clone());
+        let response = redirect_golink(short_link, None, None, clicks.clone(), storage.clone())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), warp::http::StatusCode::FOUND);
+        assert_eq!(response.headers()["location"], "https://wiki.example.com");
+
+        // Clicks through the alias count towards the renamed golink
+        clicks.flush().await.unwrap();
+        assert_eq!(storage.get("go/platform-wiki").await.unwrap().clicks, 1);
+    }
+
+    #[test]
+    fn test_normalize_tags() {
+        let tags = ["Engineering Tools", "engineering-tools", " On  Call ", "!!", "ops_team"]
+            .map(String::from);
+        assert_eq!(
+            normalize_tags(&tags),
+            vec!["engineering-tools", "on-call", "ops_team"]
+        );
+    }
+
+    #[test]
+    fn test_list_query() {
+        let params = |pairs: &[(&str, &str)]| -> std::collections::HashMap<String, String> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+
+        let query = list_query(&params(&[
+            ("sort", "short_link"),
+            ("host", " docs.example.com "),
+            ("created_after", "2024-01-01"),
+            ("created_before", "2024-02-01T02:00:00+02:00"),
+            ("tag", "On Call"),
+        ]))
+        .unwrap();
+        assert_eq!(query.sort, GolinkSort::ShortLink);
+        assert_eq!(query.direction(), SortDirection::Asc);
+        assert_eq!(query.filter.host.as_deref(), Some("docs.example.com"));
+        assert_eq!(
+            query.filter.created_after.as_deref(),
+            Some("2024-01-01T00:00:00+00:00")
+        );
+        assert_eq!(
+            query.filter.created_before.as_deref(),
+            Some("2024-02-01T00:00:00+00:00")
+        );
+        assert_eq!(query.filter.tag.as_deref(), Some("on-call"));
+
+        let query = list_query(&params(&[("sort", "clicks"), ("direction", "asc")])).unwrap();
+        assert_eq!(query.direction(), SortDirection::Asc);
+        assert_eq!(query.filter, GolinkFilter::default());
+
+        assert!(list_query(&params(&[("sort", "url")])).is_err());
+        assert!(list_query(&params(&[("direction", "up")])).is_err());
+        assert!(list_query(&params(&[("created_after", "yesterday")])).is_err());
+        assert!(list_query(&params(&[("cursor", "not-a-cursor")])).is_err());
+
+        let golink = Golink {
+            short_link: "go/docs".to_string(),
+            clicks: 3,
+            ..Default::default()
+        };
+        let cursor = Cursor::after(&ListQuery::default(), &golink);
+        let query = list_query(&params(&[("cursor", &cursor.encode())])).unwrap();
+        assert_eq!(query.after, Some(cursor.clone()));
+
+        // The cursor is bound to the sort and direction it came from
+        let token = cursor.encode();
+        assert!(list_query(&params(&[("cursor", &token), ("direction", "desc")])).is_ok());
+        assert!(list_query(&params(&[("cursor", &token), ("direction", "asc")])).is_err());
+        assert!(list_query(&params(&[("cursor", &token), ("sort", "clicks")])).is_err());
+        let query = list_query(&params(&[("sort", "clicks"), ("direction", "asc")])).unwrap();
+        let token = Cursor::after(&query, &golink).encode();
+        let params = params(&[("cursor", &token), ("sort", "clicks"), ("direction", "asc")]);
+        assert_eq!(list_query(&params).unwrap().after.unwrap().clicks, 3);
+    }
+
+    #[test]
+    fn test_merge_patch() {
+        // Examples from RFC 7396 appendix A
+        let cases = [
+            (r#"{"a":"b"}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+            (r#"{"a":"b"}"#, r#"{"b":"c"}"#, r#"{"a":"b","b":"c"}"#),
+            (r#"{"a":"b"}"#, r#"{"a":null}"#, r#"{}"#),
+            (r#"{"a":{"b":"c"}}"#, r#"{"a":{"b":"d","c":null}}"#, r#"{"a":{"b":"d"}}"#),
+            (r#"{"a":["b"]}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+            (r#"["a","b"]"#, r#"["c","d"]"#, r#"["c","d"]"#),
+            (r#"{"a":"foo"}"#, r#"null"#, r#"null"#),
+            (r#"{"e":null}"#, r#"{"a":1}"#, r#"{"e":null,"a":1}"#),
+            (r#"[1,2]"#, r#"{"a":"b","c":null}"#, r#"{"a":"b"}"#),
+        ];
+        for (target, patch, expected) in cases {
+            let mut target: serde_json::Value = serde_json::from_str(target).unwrap();
+            merge_patch(&mut target, &serde_json::from_str(patch).unwrap());
+            assert_eq!(target, serde_json::from_str::<serde_json::Value>(expected).unwrap());
+        }
+    }
+
+    fn patch_body(patch: &serde_json::Value) -> warp::hyper::body::Bytes {
+        serde_json::to_vec(patch).unwrap().into()
+    }
+
+    #[tokio::test]
+    async fn test_patch_golink() {
+        let storage = create_test_storage().await;
+        let mut golink = create_test_golink("go/test", "https://example.com");
+        golink.owner = Some("alice".to_string());
+        storage.create(golink).await.unwrap();
+
+        let patch = serde_json::json!({"owner": null, "deprecated": true});
+        let body = patch_body(&patch);
+        let response = patch_golink("go/test".to_string(), body, Role::Admin, None, storage.clone())
+            .await
+            .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+
+        let golink = storage.get("go/test").await.unwrap();
+        assert_eq!(golink.owner, None);
+        assert!(golink.deprecated);
+        assert_eq!(golink.url, "https://example.com");
+    }
+
+    #[tokio::test]
+    async fn test_patch_golink_rejects_protected_fields() {
+        let storage = create_test_storage().await;
+        let golink = create_test_golink("go/test", "https://example.com");
+        let id = golink.id.clone();
+        storage.create(golink).await.unwrap();
+
+        let cases = [
+            serde_json::json!({"id": "other"}),
+            serde_json::json!({"created_at": null}),
+            serde_json::json!({"clicks": 100}),
+            serde_json::json!({"url": null}),
+            serde_json::json!({"url": 42}),
+            serde_json::json!({"unknown": true}),
+            serde_json::json!(["url"]),
+        ];
+        for patch in cases {
+            let body = patch_body(&patch);
+            let response = patch_golink("go/test".to_string(), body, Role::Admin, None, storage.clone())
+                .await
+                .into_response();
+            assert_eq!(response.status(), warp::http::StatusCode::BAD_REQUEST);
+        }
+
+        // Repeating an immutable field's current value is allowed
+        let patch = serde_json::json!({"id": id, "url": "https://new.example.com"});
+        let body = patch_body(&patch);
+        let response = patch_golink("go/test".to_string(), body, Role::User, None, storage.clone())
+            .await
+            .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+
+        // Only admins may change the lock
+        let patch = serde_json::json!({"locked": true});
+        let body = patch_body(&patch);
+        let response = patch_golink("go/test".to_string(), body, Role::User, None, storage.clone())
+            .await
+            .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::FORBIDDEN);
+        assert!(!storage.get("go/test").await.unwrap().locked);
+
+        // And deprecate or undeprecate it, in both directions
+        let patch = serde_json::json!({"deprecated": true});
+        let body = patch_body(&patch);
+        let error = patch_golink("go/test".to_string(), body, Role::User, None, storage.clone())
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(error.code, ErrorCode::AdminRequired);
+        storage
+            .set_deprecated(&["go/test".to_string()], true)
+            .await
+            .unwrap();
+        let patch = serde_json::json!({"deprecated": false});
+        let body = patch_body(&patch);
+        let error = patch_golink("go/test".to_string(), body, Role::User, None, storage.clone())
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(error.code, ErrorCode::AdminRequired);
+        assert!(storage.get("go/test").await.unwrap().deprecated);
+
+        // Repeating the current value isn't a change
+        let patch = serde_json::json!({"deprecated": true, "description": "Old"});
+        let body = patch_body(&patch);
+        let response = patch_golink("go/test".to_string(), body, Role::User, None, storage.clone())
+            .await
+            .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_identity() {
+        let _guard = AUTH_TEST_LOCK.lock().await;
+
+        unsafe {
+            std::env::set_var("USER_TOKENS", "alice=alice-token, bob=bob-token,broken");
+            std::env::set_var("ADMIN_TOKEN", "admin-token-456");
+        }
+
+        let user = resolve_identity(Some("Bearer alice-token".to_string())).await;
+        assert_eq!(user.unwrap(), "alice");
+        let user = resolve_identity(Some("Bearer bob-token".to_string())).await;
+        assert_eq!(user.unwrap(), "bob");
+        let role = resolve_role(Some("Bearer alice-token".to_string())).await;
+        assert_eq!(role.unwrap(), Role::User);
+
+        // Admins and unknown callers have no identity
+        assert!(resolve_identity(Some("Bearer admin-token-456".to_string())).await.is_err());
+        assert!(resolve_identity(Some("Bearer wrong".to_string())).await.is_err());
+        // Personal tokens turn authentication on for regular operations
+        assert!(resolve_role(None).await.is_err());
+
+        // Clean up
+        unsafe {
+            std::env::remove_var("USER_TOKENS");
+            std::env::remove_var("ADMIN_TOKEN");
+        }
+        assert!(resolve_identity(None).await.is_err());
+        assert_eq!(resolve_role(None).await.unwrap(), Role::Admin);
     }
 
     #[tokio::test]
     async fn test_validate_token_success() {
-        let _guard = AUTH_TEST_LOCK.lock().unwrap();
+        let _guard = AUTH_TEST_LOCK.lock().await;
         
         // Set a test token
         unsafe { std::env::set_var("AUTH_TOKEN", "test-token-123"); }
@@ -601,7 +1724,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_validate_token_invalid() {
-        let _guard = AUTH_TEST_LOCK.lock().unwrap();
+        let _guard = AUTH_TEST_LOCK.lock().await;
         
         // Set a test token
         unsafe { std::env::set_var("AUTH_TOKEN", "test-token-123"); }
@@ -615,7 +1738,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_validate_token_missing_bearer() {
-        let _guard = AUTH_TEST_LOCK.lock().unwrap();
+        let _guard = AUTH_TEST_LOCK.lock().await;
         
         // Set a test token
         unsafe { std::env::set_var("AUTH_TOKEN", "test-token-123"); }
@@ -629,7 +1752,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_validate_token_no_auth_required() {
-        let _guard = AUTH_TEST_LOCK.lock().unwrap();
+        let _guard = AUTH_TEST_LOCK.lock().await;
         
         // Don't set AUTH_TOKEN - should allow all requests
         unsafe { std::env::remove_var("AUTH_TOKEN"); }
@@ -640,7 +1763,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_validate_token_missing_header() {
-        let _guard = AUTH_TEST_LOCK.lock().unwrap();
+        let _guard = AUTH_TEST_LOCK.lock().await;
         
         // Set a test token
         unsafe { std::env::set_var("AUTH_TOKEN", "test-token-123"); }
@@ -1,6 +1,8 @@
-use crate::service::Golink;
+use crate::collections::Collection;
+use crate::search::{self, SearchHit, TokenIndex};
+use crate::service::{Favorite, Golink};
 use async_trait::async_trait;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -8,32 +10,954 @@ use tokio::sync::RwLock;
 pub enum StorageError {
     NotFound,
     AlreadyExists,
+    // A compare-and-swap found the golink at a different version
+    VersionMismatch,
+    // A write meant for unlocked golinks found the golink locked
+    Locked,
     DatabaseError(String),
 }
 
 pub type StorageResult<T> = Result<T, StorageError>;
 
+// Coarse classification of the client following a golink
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ClientClass {
+    Browser,
+    Cli,
+    Bot,
+}
+
+impl ClientClass {
+    pub fn classify(user_agent: Option<&str>) -> Self {
+        let Some(user_agent) = user_agent else {
+            return Self::Cli;
+        };
+        let user_agent = user_agent.to_ascii_lowercase();
+        const BOT_MARKERS: [&str; 7] = [
+            "bot", "crawler", "spider", "slurp", "preview", "facebookexternalhit", "headless",
+        ];
+        if BOT_MARKERS.iter().any(|marker| user_agent.contains(marker)) {
+            Self::Bot
+        } else if user_agent.starts_with("mozilla/") || user_agent.starts_with("opera/") {
+            Self::Browser
+        } else {
+            Self::Cli
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "browser" => Some(Self::Browser),
+            "cli" => Some(Self::Cli),
+            "bot" => Some(Self::Bot),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Browser => "browser",
+            Self::Cli => "cli",
+            Self::Bot => "bot",
+        }
+    }
+}
+
+// Where a click came from: the `Referer` host, if any, and the client class
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClickSource {
+    pub referrer: Option<String>,
+    pub client: ClientClass,
+}
+
+impl ClickSource {
+    pub fn from_headers(referer: Option<&str>, user_agent: Option<&str>) -> Self {
+        Self {
+            referrer: referer.and_then(referrer_host),
+            client: ClientClass::classify(user_agent),
+        }
+    }
+}
+
+// Lowercased host of a `Referer` header value, without port or credentials
+fn referrer_host(referer: &str) -> Option<String> {
+    let rest = referer.split_once("://").map_or(referer, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    let host = if host.starts_with('[') {
+        // IPv6 literal, keep the brackets
+        host.split_inclusive(']').next()?
+    } else {
+        host.split(':').next()?
+    };
+    (!host.is_empty()).then(|| host.to_ascii_lowercase())
+}
+
+// Clicks from one source on one day
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceCount {
+    pub referrer: Option<String>,
+    pub client: ClientClass,
+    pub clicks: i64,
+}
+
+// Clicks on a single golink accumulated since the last flush. Bot clicks
+// only show up in `sources`.
+#[derive(Debug, Clone, Default)]
+pub struct ClickCount {
+    pub short_link: String,
+    pub count: i64,
+    pub last_used_at: Option<String>,
+    // Clicks per hour, keyed by the start of the hour in unix seconds
+    pub hourly: BTreeMap<i64, i64>,
+    // Clicks per day, referrer host and client class
+    pub sources: BTreeMap<(i64, Option<String>, ClientClass), i64>,
+}
+
+impl ClickCount {
+    pub fn new(short_link: &str) -> Self {
+        Self {
+            short_link: short_link.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn add(&mut self, at: chrono::DateTime<chrono::Utc>, source: &ClickSource) {
+        let day = Granularity::Day.bucket_start(at.timestamp());
+        *self
+            .sources
+            .entry((day, source.referrer.clone(), source.client))
+            .or_insert(0) += 1;
+        if source.client == ClientClass::Bot {
+            return;
+        }
+
+        self.count += 1;
+        *self
+            .hourly
+            .entry(Granularity::Hour.bucket_start(at.timestamp()))
+            .or_insert(0) += 1;
+        let at = at.to_rfc3339();
+        if self.last_used_at.as_ref().is_none_or(|last| *last < at) {
+            self.last_used_at = Some(at);
+        }
+    }
+
+    pub fn merge(&mut self, other: ClickCount) {
+        self.count += other.count;
+        for (start, clicks) in other.hourly {
+            *self.hourly.entry(start).or_insert(0) += clicks;
+        }
+        for (key, clicks) in other.sources {
+            *self.sources.entry(key).or_insert(0) += clicks;
+        }
+        self.last_used_at = self.last_used_at.take().max(other.last_used_at);
+    }
+
+    // Hourly counts rolled up into daily buckets
+    pub fn daily(&self) -> BTreeMap<i64, i64> {
+        let mut daily = BTreeMap::new();
+        for (start, clicks) in &self.hourly {
+            *daily.entry(Granularity::Day.bucket_start(*start)).or_insert(0) += clicks;
+        }
+        daily
+    }
+}
+
+// Bucket sizes for click time series
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Granularity {
+    Hour,
+    Day,
+}
+
+impl Granularity {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "hour" => Some(Self::Hour),
+            "day" => Some(Self::Day),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Hour => "hour",
+            Self::Day => "day",
+        }
+    }
+
+    pub fn seconds(&self) -> i64 {
+        match self {
+            Self::Hour => 3600,
+            Self::Day => 86400,
+        }
+    }
+
+    // Start of the bucket containing the given unix timestamp
+    pub fn bucket_start(&self, timestamp: i64) -> i64 {
+        timestamp - timestamp.rem_euclid(self.seconds())
+    }
+}
+
+// Number of clicks in the bucket starting at `start` (unix seconds)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClickBucket {
+    pub start: i64,
+    pub clicks: i64,
+}
+
+// Field changes applied to a golink in one step, `None` leaves a field as is
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GolinkChanges {
+    pub url: Option<String>,
+    pub owner: Option<Option<String>>,
+    pub locked: Option<bool>,
+    pub deprecated: Option<bool>,
+    pub tags: Option<Vec<String>>,
+    pub description: Option<Option<String>>,
+}
+
+impl GolinkChanges {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn apply(self, golink: &mut Golink) {
+        if let Some(url) = self.url {
+            golink.url = url;
+        }
+        if let Some(owner) = self.owner {
+            golink.owner = owner;
+        }
+        if let Some(locked) = self.locked {
+            golink.locked = locked;
+        }
+        if let Some(deprecated) = self.deprecated {
+            golink.deprecated = deprecated;
+        }
+        if let Some(tags) = self.tags {
+            golink.tags = tags;
+        }
+        if let Some(description) = self.description {
+            golink.description = description;
+        }
+    }
+}
+
+// One write in a batch
+#[derive(Debug, Clone)]
+pub enum BatchOperation {
+    Create(Golink),
+    // Creates the golink or overwrites every field of an existing one
+    Replace(Golink),
+    // With `unlocked_only` these fail with `Locked` if the golink is locked by
+    // the time they are applied, for writes that only admins may make to
+    // locked golinks
+    Update { short_link: String, url: String, unlocked_only: bool },
+    Delete { short_link: String, unlocked_only: bool },
+}
+
+// The created or updated golink, `None` for deletes
+pub type BatchResult = StorageResult<Option<Golink>>;
+
+// Orderings supported when listing golinks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GolinkSort {
+    ShortLink,
+    #[default]
+    CreatedAt,
+    UpdatedAt,
+    Clicks,
+    LastUsedAt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+impl SortDirection {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "asc" => Some(Self::Asc),
+            "desc" => Some(Self::Desc),
+            _ => None,
+        }
+    }
+
+    fn sql(&self) -> &'static str {
+        match self {
+            Self::Asc => "ASC",
+            Self::Desc => "DESC",
+        }
+    }
+}
+
+impl GolinkSort {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "short_link" => Some(Self::ShortLink),
+            "created_at" => Some(Self::CreatedAt),
+            "updated_at" => Some(Self::UpdatedAt),
+            "clicks" => Some(Self::Clicks),
+            "last_used_at" => Some(Self::LastUsedAt),
+            _ => None,
+        }
+    }
+
+    // Names sort alphabetically, everything else newest or biggest first
+    pub fn default_direction(&self) -> SortDirection {
+        match self {
+            Self::ShortLink => SortDirection::Asc,
+            _ => SortDirection::Desc,
+        }
+    }
+
+    // Ties are broken by creation time and then by name, in the same direction,
+    // so both backends return golinks in exactly the same order. Never-used
+    // golinks come last whichever way `last_used_at` is sorted.
+    pub fn compare(&self, direction: SortDirection, a: &Golink, b: &Golink) -> std::cmp::Ordering {
+        if *self == Self::LastUsedAt {
+            match (&a.last_used_at, &b.last_used_at) {
+                (None, Some(_)) => return std::cmp::Ordering::Greater,
+                (Some(_), None) => return std::cmp::Ordering::Less,
+                _ => {}
+            }
+        }
+        let ordering = match self {
+            Self::ShortLink => a.short_link.cmp(&b.short_link),
+            Self::CreatedAt => std::cmp::Ordering::Equal,
+            Self::UpdatedAt => a.updated_at.cmp(&b.updated_at),
+            Self::Clicks => a.clicks.cmp(&b.clicks),
+            Self::LastUsedAt => a.last_used_at.cmp(&b.last_used_at),
+        }
+        .then_with(|| a.created_at.cmp(&b.created_at))
+        .then_with(|| a.short_link.cmp(&b.short_link));
+        match direction {
+            SortDirection::Asc => ordering,
+            SortDirection::Desc => ordering.reverse(),
+        }
+    }
+
+    fn order_by(&self, direction: SortDirection) -> String {
+        let dir = direction.sql();
+        let column = match self {
+            Self::ShortLink => Some("short_link"),
+            Self::CreatedAt => None,
+            Self::UpdatedAt => Some("updated_at"),
+            Self::Clicks => Some("clicks"),
+            Self::LastUsedAt => Some("last_used_at"),
+        };
+        let mut order_by = String::new();
+        if *self == Self::LastUsedAt {
+            order_by.push_str("last_used_at IS NULL, ");
+        }
+        if let Some(column) = column {
+            order_by.push_str(&format!("{} {}, ", column, dir));
+        }
+        order_by.push_str(&format!("created_at {}, short_link {}", dir, dir));
+        order_by
+    }
+}
+
+// Restricts which golinks are listed. Text matches ignore ASCII case, the same
+// way SQLite's LIKE does, and timestamps are normalized RFC 3339 strings.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GolinkFilter {
+    pub url_contains: Option<String>,
+    pub host: Option<String>,
+    pub created_after: Option<String>,
+    pub created_before: Option<String>,
+    pub owner: Option<String>,
+    pub tag: Option<String>,
+}
+
+impl GolinkFilter {
+    pub fn matches(&self, golink: &Golink) -> bool {
+        if let Some(needle) = &self.url_contains
+            && !golink
+                .url
+                .to_ascii_lowercase()
+                .contains(&needle.to_ascii_lowercase())
+        {
+            return false;
+        }
+        if let Some(host) = &self.host
+            && !url_has_host(&golink.url, host)
+        {
+            return false;
+        }
+        if let Some(after) = &self.created_after
+            && golink.created_at.as_str() <= after.as_str()
+        {
+            return false;
+        }
+        if let Some(before) = &self.created_before
+            && golink.created_at.as_str() >= before.as_str()
+        {
+            return false;
+        }
+        if let Some(owner) = &self.owner
+            && golink.owner.as_ref() != Some(owner)
+        {
+            return false;
+        }
+        if let Some(tag) = &self.tag
+            && !golink.tags.contains(tag)
+        {
+            return false;
+        }
+        true
+    }
+
+    fn push_where(&self, builder: &mut sqlx::QueryBuilder<'_, sqlx::Sqlite>) {
+        builder.push(" WHERE 1 = 1");
+        if let Some(needle) = &self.url_contains {
+            builder
+                .push(" AND url LIKE ")
+                .push_bind(format!("%{}%", escape_like(needle)))
+                .push(" ESCAPE '\\'");
+        }
+        if let Some(host) = &self.host {
+            // Everything after the scheme must be the host itself or the host
+            // followed by a port, path, query or fragment
+            let host = escape_like(host);
+            builder
+                .push(" AND instr(url, '://') > 0 AND (substr(url, instr(url, '://') + 3) LIKE ")
+                .push_bind(host.clone())
+                .push(" ESCAPE '\\'");
+            for separator in ['/', ':', '?', '#'] {
+                builder
+                    .push(" OR substr(url, instr(url, '://') + 3) LIKE ")
+                    .push_bind(format!("{}{}%", host, separator))
+                    .push(" ESCAPE '\\'");
+            }
+            builder.push(")");
+        }
+        if let Some(after) = &self.created_after {
+            builder.push(" AND created_at > ").push_bind(after.clone());
+        }
+        if let Some(before) = &self.created_before {
+            builder.push(" AND created_at < ").push_bind(before.clone());
+        }
+        if let Some(owner) = &self.owner {
+            builder.push(" AND owner = ").push_bind(owner.clone());
+        }
+        if let Some(tag) = &self.tag {
+            builder
+                .push(" AND EXISTS (SELECT 1 FROM json_each(golinks.tags) WHERE value = ")
+                .push_bind(tag.clone())
+                .push(")");
+        }
+    }
+}
+
+// Whether the URL's authority starts with `host` followed by nothing, a port,
+// a path, a query or a fragment
+fn url_has_host(url: &str, host: &str) -> bool {
+    let Some((_, rest)) = url.split_once("://") else {
+        return false;
+    };
+    let Some(prefix) = rest.get(..host.len()) else {
+        return false;
+    };
+    prefix.eq_ignore_ascii_case(host)
+        && rest[host.len()..]
+            .chars()
+            .next()
+            .is_none_or(|c| matches!(c, '/' | ':' | '?' | '#'))
+}
+
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+// Position after the last golink of a page. It carries every sort key so a
+// listing resumes right after that golink, even if rows were inserted or
+// deleted in the meantime, and the ordering it was taken in, since the keys
+// mean nothing in another one.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Cursor {
+    pub sort: GolinkSort,
+    pub direction: SortDirection,
+    pub short_link: String,
+    pub created_at: String,
+    pub updated_at: String,
+    pub clicks: i64,
+    pub last_used_at: Option<String>,
+}
+
+impl Cursor {
+    pub fn after(query: &ListQuery, golink: &Golink) -> Self {
+        Self {
+            sort: query.sort,
+            direction: query.direction(),
+            short_link: golink.short_link.clone(),
+            created_at: golink.created_at.clone(),
+            updated_at: golink.updated_at.clone(),
+            clicks: golink.clicks,
+            last_used_at: golink.last_used_at.clone(),
+        }
+    }
+
+    // Opaque token handed to clients: hex encoded JSON
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        json.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    pub fn decode(token: &str) -> Option<Self> {
+        if !token.len().is_multiple_of(2) || !token.is_ascii() {
+            return None;
+        }
+        let bytes = (0..token.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&token[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    fn golink(&self) -> Golink {
+        Golink {
+            short_link: self.short_link.clone(),
+            created_at: self.created_at.clone(),
+            updated_at: self.updated_at.clone(),
+            clicks: self.clicks,
+            last_used_at: self.last_used_at.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+// Storage-wide change marker for conditional requests. `counter` goes up with
+// every write to golinks, clicks included, and `changed_at` is when the last
+// one happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Revision {
+    // Tells storages apart, so a counter starting over never repeats an ETag
+    pub instance: String,
+    pub counter: i64,
+    pub changed_at: String,
+}
+
+impl Default for Revision {
+    fn default() -> Self {
+        Self {
+            instance: uuid::Uuid::new_v4().to_string(),
+            counter: 0,
+            changed_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+impl Revision {
+    pub fn etag(&self) -> String {
+        format!("\"{}-{}\"", self.instance, self.counter)
+    }
+
+    fn bump(&mut self) {
+        self.counter += 1;
+        self.changed_at = chrono::Utc::now().to_rfc3339();
+    }
+}
+
+// How to order and filter a listing of golinks
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListQuery {
+    pub sort: GolinkSort,
+    // Falls back to the sort's default direction
+    pub direction: Option<SortDirection>,
+    pub filter: GolinkFilter,
+    // Only golinks ordered after this position are listed
+    pub after: Option<Cursor>,
+}
+
+impl ListQuery {
+    pub fn direction(&self) -> SortDirection {
+        self.direction.unwrap_or(self.sort.default_direction())
+    }
+
+    pub fn compare(&self, a: &Golink, b: &Golink) -> std::cmp::Ordering {
+        self.sort.compare(self.direction(), a, b)
+    }
+
+    pub fn is_after_cursor(&self, golink: &Golink) -> bool {
+        self.after.as_ref().is_none_or(|cursor| {
+            self.compare(golink, &cursor.golink()) == std::cmp::Ordering::Greater
+        })
+    }
+
+    // Keyset condition matching `is_after_cursor` and the ORDER BY of `order_by`
+    fn push_after(&self, builder: &mut sqlx::QueryBuilder<'_, sqlx::Sqlite>) {
+        let Some(cursor) = &self.after else {
+            return;
+        };
+        let op = match self.direction() {
+            SortDirection::Asc => ">",
+            SortDirection::Desc => "<",
+        };
+        builder.push(" AND ");
+        match self.sort {
+            GolinkSort::CreatedAt => {
+                builder.push(format!("(created_at, short_link) {} (", op));
+            }
+            GolinkSort::ShortLink => {
+                builder
+                    .push(format!("(short_link, created_at, short_link) {} (", op))
+                    .push_bind(cursor.short_link.clone())
+                    .push(", ");
+            }
+            GolinkSort::UpdatedAt => {
+                builder
+                    .push(format!("(updated_at, created_at, short_link) {} (", op))
+                    .push_bind(cursor.updated_at.clone())
+                    .push(", ");
+            }
+            GolinkSort::Clicks => {
+                builder
+                    .push(format!("(clicks, created_at, short_link) {} (", op))
+                    .push_bind(cursor.clicks)
+                    .push(", ");
+            }
+            // Never-used golinks follow every used one
+            GolinkSort::LastUsedAt => match &cursor.last_used_at {
+                Some(last_used_at) => {
+                    builder
+                        .push(format!(
+                            "(last_used_at IS NULL OR (last_used_at, created_at, short_link) {} (",
+                            op
+                        ))
+                        .push_bind(last_used_at.clone())
+                        .push(", ");
+                }
+                None => {
+                    builder.push(format!(
+                        "(last_used_at IS NULL AND (created_at, short_link) {} (",
+                        op
+                    ));
+                }
+            },
+        }
+        builder
+            .push_bind(cursor.created_at.clone())
+            .push(", ")
+            .push_bind(cursor.short_link.clone())
+            .push(")");
+        if self.sort == GolinkSort::LastUsedAt {
+            builder.push(")");
+        }
+    }
+}
+
 #[async_trait]
 pub trait GoStorage: Send + Sync {
     async fn create(&self, golink: Golink) -> StorageResult<()>;
     async fn get(&self, short_link: &str) -> StorageResult<Golink>;
     async fn get_all(&self) -> StorageResult<Vec<Golink>>;
-    async fn get_paginated(&self, page: usize, page_size: usize) -> StorageResult<(Vec<Golink>, usize)>;
+    // Golinks matching the query's filter in the query's order, skipping
+    // `offset` and returning at most `limit`, along with the number that match
+    async fn list(
+        &self,
+        query: &ListQuery,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> StorageResult<(Vec<Golink>, usize)>;
+    async fn get_paginated(
+        &self,
+        page: usize,
+        page_size: usize,
+        sort: GolinkSort,
+    ) -> StorageResult<(Vec<Golink>, usize)> {
+        let query = ListQuery {
+            sort,
+            ..Default::default()
+        };
+        self.list(&query, page.saturating_sub(1) * page_size, Some(page_size))
+            .await
+    }
     async fn update(&self, short_link: &str, url: String) -> StorageResult<Golink>;
     async fn delete(&self, short_link: &str) -> StorageResult<()>;
     async fn exists(&self, short_link: &str) -> StorageResult<bool>;
+    // Whether a golink, alias or collection already goes by this name
+    async fn name_taken(&self, name: &str) -> StorageResult<bool>;
+    async fn set_locked(&self, short_link: &str, locked: bool) -> StorageResult<Golink>;
+    // Applies operations in order and reports the result of each. In atomic mode
+    // nothing is written unless every operation succeeds.
+    async fn apply_batch(
+        &self,
+        operations: Vec<BatchOperation>,
+        atomic: bool,
+    ) -> StorageResult<Vec<BatchResult>>;
+    // Applies all changes at once and bumps `updated_at`
+    async fn apply_changes(&self, short_link: &str, changes: GolinkChanges) -> StorageResult<Golink>;
+    // Compare-and-swap forms of `apply_changes` and `delete`: they fail with
+    // `VersionMismatch` and leave the golink alone unless it is still at `version`
+    async fn apply_changes_if(
+        &self,
+        short_link: &str,
+        version: i64,
+        changes: GolinkChanges,
+    ) -> StorageResult<Golink>;
+    async fn delete_if(&self, short_link: &str, version: i64) -> StorageResult<()>;
+    // Clicks for golinks that no longer exist are dropped
+    async fn record_clicks(&self, clicks: Vec<ClickCount>) -> StorageResult<()>;
+    // Non-empty buckets starting within [from, to), ordered by start
+    async fn get_click_buckets(
+        &self,
+        short_link: &str,
+        granularity: Granularity,
+        from: i64,
+        to: i64,
+    ) -> StorageResult<Vec<ClickBucket>>;
+    // Total clicks per golink over buckets starting within [from, to)
+    async fn get_click_totals(
+        &self,
+        granularity: Granularity,
+        from: i64,
+        to: i64,
+    ) -> StorageResult<HashMap<String, i64>>;
+    // Clicks per referrer and client class on days within [from, to)
+    async fn get_click_sources(
+        &self,
+        short_link: &str,
+        from: i64,
+        to: i64,
+    ) -> StorageResult<Vec<SourceCount>>;
+    // Golinks neither clicked nor modified since `cutoff` (an RFC 3339 timestamp)
+    async fn get_stale(&self, cutoff: &str) -> StorageResult<Vec<Golink>>;
+    // Returns how many of the given golinks were found
+    async fn set_deprecated(&self, short_links: &[String], deprecated: bool) -> StorageResult<usize>;
+    // Drops buckets of the given granularity starting before `before`. Daily
+    // pruning also drops the per-source counts.
+    async fn prune_click_buckets(&self, granularity: Granularity, before: i64) -> StorageResult<()>;
+    // Starring a golink twice keeps the original `favorited_at`
+    async fn add_favorite(&self, user: &str, short_link: &str) -> StorageResult<Favorite>;
+    async fn remove_favorite(&self, user: &str, short_link: &str) -> StorageResult<()>;
+    // Most recently starred first
+    async fn get_favorites(&self, user: &str) -> StorageResult<Vec<Favorite>>;
+    // Moves a golink to a new name, keeping its id and history. With `keep_alias`
+    // the old name stays behind as an alias of the new one.
+    async fn rename(
+        &self,
+        short_link: &str,
+        new_short_link: &str,
+        keep_alias: bool,
+    ) -> StorageResult<Golink>;
+    // Compare-and-swap form of `rename`, like `apply_changes_if`
+    async fn rename_if(
+        &self,
+        short_link: &str,
+        new_short_link: &str,
+        keep_alias: bool,
+        version: i64,
+    ) -> StorageResult<Golink>;
+    // Short link an alias points at
+    async fn resolve_alias(&self, alias: &str) -> StorageResult<String>;
+    // Golinks, aliases and collections share one namespace, so creating any of
+    // them fails with `AlreadyExists` when the name is taken by another
+    async fn create_collection(&self, collection: Collection) -> StorageResult<()>;
+    async fn get_collection(&self, name: &str) -> StorageResult<Collection>;
+    // Ordered by name
+    async fn get_collections(&self) -> StorageResult<Vec<Collection>>;
+    async fn update_collection(
+        &self,
+        name: &str,
+        title: String,
+        description: Option<String>,
+        links: Vec<String>,
+    ) -> StorageResult<Collection>;
+    async fn delete_collection(&self, name: &str) -> StorageResult<()>;
+
+    // Golinks matching every word of `query` as a prefix of a word in their name,
+    // URL, description or tags, best match first
+    async fn search(&self, query: &str, limit: usize) -> StorageResult<Vec<SearchHit>>;
+    // Golinks whose short link starts with `prefix`, most clicked first
+    async fn suggest(&self, prefix: &str, limit: usize) -> StorageResult<Vec<Golink>>;
+    async fn revision(&self) -> StorageResult<Revision>;
+}
+
+fn is_stale(golink: &Golink, cutoff: &str) -> bool {
+    golink.last_used_at.as_deref().is_none_or(|used| used < cutoff)
+        && golink.created_at.as_str() < cutoff
+        && golink.updated_at.as_str() < cutoff
 }
 
+type BucketKey = (String, Granularity, i64);
+type SourceKey = (String, i64, Option<String>, ClientClass);
+
 // In-memory HashMap storage implementation
+#[derive(Default)]
 pub struct HashMapStorage {
     data: Arc<RwLock<HashMap<String, Golink>>>,
+    click_buckets: Arc<RwLock<BTreeMap<BucketKey, i64>>>,
+    click_sources: Arc<RwLock<BTreeMap<SourceKey, i64>>>,
+    // (user, short_link) -> favorited_at
+    favorites: Arc<RwLock<BTreeMap<(String, String), String>>>,
+    // Always locked after `data` when both are needed
+    collections: Arc<RwLock<BTreeMap<String, Collection>>>,
+    // alias -> short_link, locked after `collections`
+    aliases: Arc<RwLock<BTreeMap<String, String>>>,
+    // Search index over `data`, locked after `aliases`
+    search_index: Arc<RwLock<TokenIndex>>,
+    // Moved on by every write to `data`, locked last
+    revision: Arc<RwLock<Revision>>,
 }
 
 impl HashMapStorage {
     pub fn new() -> Self {
         Self {
             data: Arc::new(RwLock::new(HashMap::new())),
+            click_buckets: Arc::new(RwLock::new(BTreeMap::new())),
+            click_sources: Arc::new(RwLock::new(BTreeMap::new())),
+            favorites: Arc::new(RwLock::new(BTreeMap::new())),
+            collections: Arc::new(RwLock::new(BTreeMap::new())),
+            aliases: Arc::new(RwLock::new(BTreeMap::new())),
+            search_index: Arc::new(RwLock::new(TokenIndex::default())),
+            revision: Arc::new(RwLock::new(Revision::default())),
+        }
+    }
+
+    // Deletes the golink and everything attached to it, provided it is still
+    // at `version` when one is given
+    async fn delete_at(&self, short_link: &str, version: Option<i64>) -> StorageResult<()> {
+        let mut store = self.data.write().await;
+        let golink = store.get(short_link).ok_or(StorageError::NotFound)?;
+        if version.is_some_and(|version| version != golink.version) {
+            return Err(StorageError::VersionMismatch);
+        }
+        store.remove(short_link);
+        self.click_buckets
+            .write()
+            .await
+            .retain(|(name, _, _), _| name != short_link);
+        self.click_sources
+            .write()
+            .await
+            .retain(|(name, _, _, _), _| name != short_link);
+        self.favorites
+            .write()
+            .await
+            .retain(|(_, name), _| name != short_link);
+        for collection in self.collections.write().await.values_mut() {
+            collection.links.retain(|name| name != short_link);
+        }
+        self.aliases
+            .write()
+            .await
+            .retain(|_, target| target != short_link);
+        self.search_index.write().await.remove(short_link);
+        self.revision.write().await.bump();
+        Ok(())
+    }
+
+    // Applies the changes, provided the golink is still at `version` when one
+    // is given
+    async fn apply_changes_at(
+        &self,
+        short_link: &str,
+        version: Option<i64>,
+        changes: GolinkChanges,
+    ) -> StorageResult<Golink> {
+        let mut store = self.data.write().await;
+        let golink = store.get_mut(short_link).ok_or(StorageError::NotFound)?;
+        if version.is_some_and(|version| version != golink.version) {
+            return Err(StorageError::VersionMismatch);
+        }
+        changes.apply(golink);
+        golink.updated_at = chrono::Utc::now().to_rfc3339();
+        golink.version += 1;
+        self.search_index.write().await.insert(golink);
+        self.revision.write().await.bump();
+        Ok(golink.clone())
+    }
+
+    // Renames the golink, provided it is still at `version` when one is given
+    async fn rename_at(
+        &self,
+        short_link: &str,
+        new_short_link: &str,
+        keep_alias: bool,
+        version: Option<i64>,
+    ) -> StorageResult<Golink> {
+        let mut store = self.data.write().await;
+        let mut buckets = self.click_buckets.write().await;
+        let mut sources = self.click_sources.write().await;
+        let mut favorites = self.favorites.write().await;
+        let mut collections = self.collections.write().await;
+        let mut aliases = self.aliases.write().await;
+
+        let golink = store.get(short_link).ok_or(StorageError::NotFound)?;
+        if version.is_some_and(|version| version != golink.version) {
+            return Err(StorageError::VersionMismatch);
+        }
+        // Renaming back to one of its own aliases frees that alias
+        let own_alias = aliases.get(new_short_link).is_some_and(|target| target == short_link);
+        if store.contains_key(new_short_link)
+            || collections.contains_key(new_short_link)
+            || (aliases.contains_key(new_short_link) && !own_alias)
+        {
+            return Err(StorageError::AlreadyExists);
+        }
+        aliases.remove(new_short_link);
+
+        let mut golink = store.remove(short_link).unwrap();
+        golink.short_link = new_short_link.to_string();
+        golink.updated_at = chrono::Utc::now().to_rfc3339();
+        golink.version += 1;
+        store.insert(new_short_link.to_string(), golink.clone());
+
+        let moved: Vec<BucketKey> = buckets
+            .keys()
+            .filter(|(name, _, _)| name == short_link)
+            .cloned()
+            .collect();
+        for key in moved {
+            let clicks = buckets.remove(&key).unwrap();
+            buckets.insert((new_short_link.to_string(), key.1, key.2), clicks);
+        }
+        let moved: Vec<SourceKey> = sources
+            .keys()
+            .filter(|(name, _, _, _)| name == short_link)
+            .cloned()
+            .collect();
+        for key in moved {
+            let clicks = sources.remove(&key).unwrap();
+            sources.insert((new_short_link.to_string(), key.1, key.2, key.3), clicks);
+        }
+        let moved: Vec<(String, String)> = favorites
+            .keys()
+            .filter(|(_, name)| name == short_link)
+            .cloned()
+            .collect();
+        for key in moved {
+            let favorited_at = favorites.remove(&key).unwrap();
+            favorites.insert((key.0, new_short_link.to_string()), favorited_at);
+        }
+        for collection in collections.values_mut() {
+            for link in collection.links.iter_mut().filter(|link| *link == short_link) {
+                *link = new_short_link.to_string();
+            }
+        }
+        for target in aliases.values_mut().filter(|target| *target == short_link) {
+            *target = new_short_link.to_string();
+        }
+        if keep_alias {
+            aliases.insert(short_link.to_string(), new_short_link.to_string());
         }
+        let mut search_index = self.search_index.write().await;
+        search_index.remove(short_link);
+        search_index.insert(&golink);
+        self.revision.write().await.bump();
+
+        Ok(golink)
     }
 }
 
@@ -41,10 +965,15 @@ impl HashMapStorage {
 impl GoStorage for HashMapStorage {
     async fn create(&self, golink: Golink) -> StorageResult<()> {
         let mut store = self.data.write().await;
-        if store.contains_key(&golink.short_link) {
+        if store.contains_key(&golink.short_link)
+            || self.collections.read().await.contains_key(&golink.short_link)
+            || self.aliases.read().await.contains_key(&golink.short_link)
+        {
             return Err(StorageError::AlreadyExists);
         }
+        self.search_index.write().await.insert(&golink);
         store.insert(golink.short_link.clone(), golink);
+        self.revision.write().await.bump();
         Ok(())
     }
 
@@ -55,24 +984,33 @@ impl GoStorage for HashMapStorage {
 
     async fn get_all(&self) -> StorageResult<Vec<Golink>> {
         let store = self.data.read().await;
-        Ok(store.values().cloned().collect())
+        let mut golinks: Vec<Golink> = store.values().cloned().collect();
+        golinks.sort_by(|a, b| ListQuery::default().compare(a, b));
+        Ok(golinks)
     }
 
-    async fn get_paginated(&self, page: usize, page_size: usize) -> StorageResult<(Vec<Golink>, usize)> {
+    async fn list(
+        &self,
+        query: &ListQuery,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> StorageResult<(Vec<Golink>, usize)> {
         let store = self.data.read().await;
-        let mut all_golinks: Vec<Golink> = store.values().cloned().collect();
-        all_golinks.sort_by(|a, b| b.created_at.cmp(&a.created_at));
-
-        let total_items = all_golinks.len();
-        let offset = (page.saturating_sub(1)) * page_size;
-        
-        let paginated_items = if offset < total_items {
-            all_golinks.into_iter().skip(offset).take(page_size).collect()
-        } else {
-            Vec::new()
-        };
+        let mut golinks: Vec<Golink> = store
+            .values()
+            .filter(|golink| query.filter.matches(golink))
+            .cloned()
+            .collect();
+        let total_items = golinks.len();
+        golinks.retain(|golink| query.is_after_cursor(golink));
+        golinks.sort_by(|a, b| query.compare(a, b));
 
-        Ok((paginated_items, total_items))
+        let page = golinks
+            .into_iter()
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .collect();
+        Ok((page, total_items))
     }
 
     async fn update(&self, short_link: &str, url: String) -> StorageResult<Golink> {
@@ -80,6 +1018,10 @@ impl GoStorage for HashMapStorage {
         match store.get_mut(short_link) {
             Some(golink) => {
                 golink.url = url;
+                golink.updated_at = chrono::Utc::now().to_rfc3339();
+                golink.version += 1;
+                self.search_index.write().await.insert(golink);
+                self.revision.write().await.bump();
                 Ok(golink.clone())
             }
             None => Err(StorageError::NotFound),
@@ -87,49 +1029,512 @@ impl GoStorage for HashMapStorage {
     }
 
     async fn delete(&self, short_link: &str) -> StorageResult<()> {
-        let mut store = self.data.write().await;
-        store.remove(short_link).ok_or(StorageError::NotFound)?;
-        Ok(())
+        self.delete_at(short_link, None).await
     }
 
     async fn exists(&self, short_link: &str) -> StorageResult<bool> {
         let store = self.data.read().await;
         Ok(store.contains_key(short_link))
     }
-}
 
-// SQLite storage implementation
-pub struct SqliteStorage {
-    pool: sqlx::SqlitePool,
-}
+    async fn name_taken(&self, name: &str) -> StorageResult<bool> {
+        let store = self.data.read().await;
+        let collections = self.collections.read().await;
+        let aliases = self.aliases.read().await;
+        Ok(
+            store.contains_key(name)
+                || collections.contains_key(name)
+                || aliases.contains_key(name),
+        )
+    }
 
-impl SqliteStorage {
-    pub async fn new(database_url: &str) -> Result<Self, sqlx::Error> {
-        // Ensure the database URL has the proper format and create directories if needed
-        let formatted_url = if database_url.starts_with("sqlite://") {
-            database_url.to_string()
-        } else {
-            // Handle relative and absolute file paths
-            let path = std::path::Path::new(database_url);
-            
-            // Create parent directories if they don't exist
-            if let Some(parent) = path.parent() {
-                if !parent.exists() {
-                    std::fs::create_dir_all(parent)
-                        .map_err(|e| sqlx::Error::Io(e))?;
-                }
+    async fn set_locked(&self, short_link: &str, locked: bool) -> StorageResult<Golink> {
+        let mut store = self.data.write().await;
+        match store.get_mut(short_link) {
+            Some(golink) => {
+                golink.locked = locked;
+                golink.updated_at = chrono::Utc::now().to_rfc3339();
+                golink.version += 1;
+                self.revision.write().await.bump();
+                Ok(golink.clone())
             }
-            
-            // Convert to proper SQLite URL format
-            let absolute_path = path.canonicalize()
-                .or_else(|_| {
-                    // If canonicalize fails (file doesn't exist yet), use absolute path
-                    if path.is_absolute() {
-                        Ok(path.to_path_buf())
-                    } else {
-                        std::env::current_dir()
-                            .map(|cwd| cwd.join(path))
-                            .map_err(|e| sqlx::Error::Io(e))
+            None => Err(StorageError::NotFound),
+        }
+    }
+
+    async fn apply_batch(
+        &self,
+        operations: Vec<BatchOperation>,
+        atomic: bool,
+    ) -> StorageResult<Vec<BatchResult>> {
+        let mut store = self.data.write().await;
+        let mut buckets = self.click_buckets.write().await;
+        let mut sources = self.click_sources.write().await;
+        let mut favorites = self.favorites.write().await;
+        let mut collections = self.collections.write().await;
+        let mut aliases = self.aliases.write().await;
+        let mut search_index = self.search_index.write().await;
+
+        // Operations run against copies of the golinks they touch, which only
+        // replace the stored ones once the batch is known to go through. `None`
+        // stands for a golink that doesn't exist (anymore).
+        fn stage<'a>(
+            staged: &'a mut HashMap<String, Option<Golink>>,
+            store: &HashMap<String, Golink>,
+            short_link: &str,
+        ) -> &'a mut Option<Golink> {
+            staged
+                .entry(short_link.to_string())
+                .or_insert_with(|| store.get(short_link).cloned())
+        }
+        let mut staged = HashMap::new();
+        let mut deleted = std::collections::HashSet::new();
+        let mut results = Vec::with_capacity(operations.len());
+        for operation in operations {
+            results.push(match operation {
+                BatchOperation::Create(golink) => {
+                    let slot = stage(&mut staged, &store, &golink.short_link);
+                    if slot.is_some()
+                        || collections.contains_key(&golink.short_link)
+                        || aliases.contains_key(&golink.short_link)
+                    {
+                        Err(StorageError::AlreadyExists)
+                    } else {
+                        *slot = Some(golink.clone());
+                        Ok(Some(golink))
+                    }
+                }
+                BatchOperation::Replace(mut golink) => {
+                    if collections.contains_key(&golink.short_link)
+                        || aliases.contains_key(&golink.short_link)
+                    {
+                        Err(StorageError::AlreadyExists)
+                    } else {
+                        let slot = stage(&mut staged, &store, &golink.short_link);
+                        if let Some(existing) = slot {
+                            golink.version = existing.version + 1;
+                        }
+                        *slot = Some(golink.clone());
+                        Ok(Some(golink))
+                    }
+                }
+                BatchOperation::Update {
+                    short_link,
+                    url,
+                    unlocked_only,
+                } => match stage(&mut staged, &store, &short_link) {
+                    Some(golink) if unlocked_only && golink.locked => Err(StorageError::Locked),
+                    Some(golink) => {
+                        golink.url = url;
+                        golink.updated_at = chrono::Utc::now().to_rfc3339();
+                        golink.version += 1;
+                        Ok(Some(golink.clone()))
+                    }
+                    None => Err(StorageError::NotFound),
+                },
+                BatchOperation::Delete {
+                    short_link,
+                    unlocked_only,
+                } => match stage(&mut staged, &store, &short_link) {
+                    Some(golink) if unlocked_only && golink.locked => Err(StorageError::Locked),
+                    slot => match slot.take() {
+                        Some(_) => {
+                            deleted.insert(short_link);
+                            Ok(None)
+                        }
+                        None => Err(StorageError::NotFound),
+                    },
+                },
+            });
+        }
+
+        if atomic && results.iter().any(Result::is_err) {
+            return Ok(results);
+        }
+
+        for (short_link, golink) in staged {
+            match golink {
+                Some(golink) => {
+                    search_index.insert(&golink);
+                    store.insert(short_link, golink);
+                }
+                None => {
+                    search_index.remove(&short_link);
+                    store.remove(&short_link);
+                }
+            }
+        }
+        // History of deleted golinks goes with them, even if the name was reused
+        buckets.retain(|(name, _, _), _| !deleted.contains(name));
+        sources.retain(|(name, _, _, _), _| !deleted.contains(name));
+        favorites.retain(|(_, name), _| !deleted.contains(name));
+        for collection in collections.values_mut() {
+            collection.links.retain(|name| !deleted.contains(name));
+        }
+        aliases.retain(|_, target| !deleted.contains(target));
+        if results.iter().any(Result::is_ok) {
+            self.revision.write().await.bump();
+        }
+
+        Ok(results)
+    }
+
+    async fn apply_changes(&self, short_link: &str, changes: GolinkChanges) -> StorageResult<Golink> {
+        self.apply_changes_at(short_link, None, changes).await
+    }
+
+    async fn apply_changes_if(
+        &self,
+        short_link: &str,
+        version: i64,
+        changes: GolinkChanges,
+    ) -> StorageResult<Golink> {
+        self.apply_changes_at(short_link, Some(version), changes).await
+    }
+
+    async fn delete_if(&self, short_link: &str, version: i64) -> StorageResult<()> {
+        self.delete_at(short_link, Some(version)).await
+    }
+
+    async fn record_clicks(&self, clicks: Vec<ClickCount>) -> StorageResult<()> {
+        let mut store = self.data.write().await;
+        let mut buckets = self.click_buckets.write().await;
+        let mut sources = self.click_sources.write().await;
+        let mut counted = false;
+        for click in clicks {
+            let Some(golink) = store.get_mut(&click.short_link) else {
+                continue;
+            };
+            counted = true;
+            for ((day, referrer, client), count) in &click.sources {
+                *sources
+                    .entry((click.short_link.clone(), *day, referrer.clone(), *client))
+                    .or_insert(0) += count;
+            }
+            golink.clicks += click.count;
+            if click.last_used_at.is_some() {
+                golink.last_used_at = click.last_used_at.clone();
+            }
+
+            let daily = click.daily();
+            let series = [(Granularity::Hour, click.hourly), (Granularity::Day, daily)];
+            for (granularity, counts) in series {
+                for (start, count) in counts {
+                    *buckets
+                        .entry((click.short_link.clone(), granularity, start))
+                        .or_insert(0) += count;
+                }
+            }
+        }
+        if counted {
+            self.revision.write().await.bump();
+        }
+        Ok(())
+    }
+
+    async fn get_click_buckets(
+        &self,
+        short_link: &str,
+        granularity: Granularity,
+        from: i64,
+        to: i64,
+    ) -> StorageResult<Vec<ClickBucket>> {
+        if from >= to {
+            return Ok(Vec::new());
+        }
+        let buckets = self.click_buckets.read().await;
+        let range = (short_link.to_string(), granularity, from)..(short_link.to_string(), granularity, to);
+        Ok(buckets
+            .range(range)
+            .map(|((_, _, start), clicks)| ClickBucket {
+                start: *start,
+                clicks: *clicks,
+            })
+            .collect())
+    }
+
+    async fn add_favorite(&self, user: &str, short_link: &str) -> StorageResult<Favorite> {
+        let store = self.data.read().await;
+        let golink = store.get(short_link).ok_or(StorageError::NotFound)?;
+        let mut favorites = self.favorites.write().await;
+        let favorited_at = favorites
+            .entry((user.to_string(), short_link.to_string()))
+            .or_insert_with(|| chrono::Utc::now().to_rfc3339());
+        Ok(Favorite {
+            golink: golink.clone(),
+            favorited_at: favorited_at.clone(),
+        })
+    }
+
+    async fn remove_favorite(&self, user: &str, short_link: &str) -> StorageResult<()> {
+        let mut favorites = self.favorites.write().await;
+        favorites
+            .remove(&(user.to_string(), short_link.to_string()))
+            .map(|_| ())
+            .ok_or(StorageError::NotFound)
+    }
+
+    async fn get_favorites(&self, user: &str) -> StorageResult<Vec<Favorite>> {
+        let store = self.data.read().await;
+        let favorites = self.favorites.read().await;
+        let mut result: Vec<Favorite> = favorites
+            .iter()
+            .filter(|((name, _), _)| name == user)
+            .filter_map(|((_, short_link), favorited_at)| {
+                store.get(short_link).map(|golink| Favorite {
+                    golink: golink.clone(),
+                    favorited_at: favorited_at.clone(),
+                })
+            })
+            .collect();
+        result.sort_by(|a, b| {
+            b.favorited_at
+                .cmp(&a.favorited_at)
+                .then_with(|| a.golink.short_link.cmp(&b.golink.short_link))
+        });
+        Ok(result)
+    }
+
+    async fn rename(
+        &self,
+        short_link: &str,
+        new_short_link: &str,
+        keep_alias: bool,
+    ) -> StorageResult<Golink> {
+        self.rename_at(short_link, new_short_link, keep_alias, None).await
+    }
+
+    async fn rename_if(
+        &self,
+        short_link: &str,
+        new_short_link: &str,
+        keep_alias: bool,
+        version: i64,
+    ) -> StorageResult<Golink> {
+        self.rename_at(short_link, new_short_link, keep_alias, Some(version))
+            .await
+    }
+
+    async fn resolve_alias(&self, alias: &str) -> StorageResult<String> {
+        let aliases = self.aliases.read().await;
+        aliases.get(alias).cloned().ok_or(StorageError::NotFound)
+    }
+
+    async fn create_collection(&self, collection: Collection) -> StorageResult<()> {
+        let store = self.data.read().await;
+        let mut collections = self.collections.write().await;
+        if store.contains_key(&collection.name)
+            || collections.contains_key(&collection.name)
+            || self.aliases.read().await.contains_key(&collection.name)
+        {
+            return Err(StorageError::AlreadyExists);
+        }
+        collections.insert(collection.name.clone(), collection);
+        Ok(())
+    }
+
+    async fn get_collection(&self, name: &str) -> StorageResult<Collection> {
+        let collections = self.collections.read().await;
+        collections.get(name).cloned().ok_or(StorageError::NotFound)
+    }
+
+    async fn get_collections(&self) -> StorageResult<Vec<Collection>> {
+        let collections = self.collections.read().await;
+        Ok(collections.values().cloned().collect())
+    }
+
+    async fn update_collection(
+        &self,
+        name: &str,
+        title: String,
+        description: Option<String>,
+        links: Vec<String>,
+    ) -> StorageResult<Collection> {
+        let mut collections = self.collections.write().await;
+        let collection = collections.get_mut(name).ok_or(StorageError::NotFound)?;
+        collection.title = title;
+        collection.description = description;
+        collection.links = links;
+        collection.updated_at = chrono::Utc::now().to_rfc3339();
+        Ok(collection.clone())
+    }
+
+    async fn delete_collection(&self, name: &str) -> StorageResult<()> {
+        let mut collections = self.collections.write().await;
+        collections
+            .remove(name)
+            .map(|_| ())
+            .ok_or(StorageError::NotFound)
+    }
+
+    async fn search(&self, query: &str, limit: usize) -> StorageResult<Vec<SearchHit>> {
+        let tokens = search::query_tokens(query);
+        if tokens.is_empty() {
+            return Ok(Vec::new());
+        }
+        let store = self.data.read().await;
+        let matches = self.search_index.read().await.lookup(&tokens);
+
+        let mut hits: Vec<SearchHit> = matches
+            .iter()
+            .filter_map(|short_link| store.get(short_link))
+            .map(|golink| SearchHit {
+                score: search::score(golink, &tokens),
+                highlight: search::highlight(golink, &tokens),
+                golink: golink.clone(),
+            })
+            .collect();
+        hits.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.golink.short_link.cmp(&b.golink.short_link))
+        });
+        hits.truncate(limit);
+        Ok(hits)
+    }
+
+    async fn suggest(&self, prefix: &str, limit: usize) -> StorageResult<Vec<Golink>> {
+        let store = self.data.read().await;
+        let search_index = self.search_index.read().await;
+        let mut matches: Vec<&Golink> = search_index
+            .short_links_with_prefix(prefix)
+            .filter_map(|short_link| store.get(short_link))
+            .collect();
+        matches.sort_by(|a, b| {
+            b.clicks
+                .cmp(&a.clicks)
+                .then_with(|| a.short_link.cmp(&b.short_link))
+        });
+        Ok(matches.into_iter().take(limit).cloned().collect())
+    }
+
+    async fn get_stale(&self, cutoff: &str) -> StorageResult<Vec<Golink>> {
+        let store = self.data.read().await;
+        let mut stale: Vec<Golink> = store
+            .values()
+            .filter(|golink| is_stale(golink, cutoff))
+            .cloned()
+            .collect();
+        stale.sort_by(|a, b| a.short_link.cmp(&b.short_link));
+        Ok(stale)
+    }
+
+    async fn set_deprecated(&self, short_links: &[String], deprecated: bool) -> StorageResult<usize> {
+        let mut store = self.data.write().await;
+        let mut found = 0;
+        for short_link in short_links {
+            if let Some(golink) = store.get_mut(short_link) {
+                golink.deprecated = deprecated;
+                golink.version += 1;
+                found += 1;
+            }
+        }
+        if found > 0 {
+            self.revision.write().await.bump();
+        }
+        Ok(found)
+    }
+
+    async fn get_click_totals(
+        &self,
+        granularity: Granularity,
+        from: i64,
+        to: i64,
+    ) -> StorageResult<HashMap<String, i64>> {
+        let buckets = self.click_buckets.read().await;
+        let mut totals = HashMap::new();
+        for ((name, bucket_granularity, start), clicks) in buckets.iter() {
+            if *bucket_granularity == granularity && *start >= from && *start < to {
+                *totals.entry(name.clone()).or_insert(0) += clicks;
+            }
+        }
+        Ok(totals)
+    }
+
+    async fn get_click_sources(
+        &self,
+        short_link: &str,
+        from: i64,
+        to: i64,
+    ) -> StorageResult<Vec<SourceCount>> {
+        let sources = self.click_sources.read().await;
+        let mut totals: BTreeMap<(Option<String>, ClientClass), i64> = BTreeMap::new();
+        for ((name, day, referrer, client), clicks) in sources.iter() {
+            if name == short_link && *day >= from && *day < to {
+                *totals.entry((referrer.clone(), *client)).or_insert(0) += clicks;
+            }
+        }
+        Ok(totals
+            .into_iter()
+            .map(|((referrer, client), clicks)| SourceCount {
+                referrer,
+                client,
+                clicks,
+            })
+            .collect())
+    }
+
+    async fn prune_click_buckets(&self, granularity: Granularity, before: i64) -> StorageResult<()> {
+        self.click_buckets
+            .write()
+            .await
+            .retain(|(_, bucket_granularity, start), _| {
+                *bucket_granularity != granularity || *start >= before
+            });
+        if granularity == Granularity::Day {
+            self.click_sources
+                .write()
+                .await
+                .retain(|(_, day, _, _), _| *day >= before);
+        }
+        Ok(())
+    }
+
+    async fn revision(&self) -> StorageResult<Revision> {
+        Ok(self.revision.read().await.clone())
+    }
+}
+
+// Columns selected for every `Collection` read. Its `links` come from
+// `collection_links` in a separate query.
+const COLLECTION_COLUMNS: &str = "id, name, title, description, created_at, updated_at";
+
+// Columns selected for every `Golink` read, in `sqlx::FromRow` field order
+const GOLINK_COLUMNS: &str =
+    "id, short_link, url, created_at, locked, clicks, last_used_at, owner, updated_at, deprecated, \
+     tags, description, version";
+
+// SQLite storage implementation
+pub struct SqliteStorage {
+    pool: sqlx::SqlitePool,
+}
+
+impl SqliteStorage {
+    pub async fn new(database_url: &str) -> Result<Self, sqlx::Error> {
+        // Ensure the database URL has the proper format and create directories if needed
+        let formatted_url = if database_url.starts_with("sqlite://") {
+            database_url.to_string()
+        } else {
+            // Handle relative and absolute file paths
+            let path = std::path::Path::new(database_url);
+            
+            // Create parent directories if they don't exist
+            if let Some(parent) = path.parent()
+                && !parent.exists()
+            {
+                std::fs::create_dir_all(parent).map_err(sqlx::Error::Io)?;
+            }
+            
+            // Convert to proper SQLite URL format
+            let absolute_path = path.canonicalize()
+                .or_else(|_| {
+                    // If canonicalize fails (file doesn't exist yet), use absolute path
+                    if path.is_absolute() {
+                        Ok(path.to_path_buf())
+                    } else {
+                        std::env::current_dir()
+                            .map(|cwd| cwd.join(path))
+                            .map_err(sqlx::Error::Io)
                     }
                 })?;
             
@@ -153,146 +1558,2488 @@ impl SqliteStorage {
                 id TEXT PRIMARY KEY,
                 short_link TEXT UNIQUE NOT NULL,
                 url TEXT NOT NULL,
-                created_at TEXT NOT NULL
+                created_at TEXT NOT NULL,
+                locked INTEGER NOT NULL DEFAULT 0,
+                clicks INTEGER NOT NULL DEFAULT 0,
+                last_used_at TEXT,
+                owner TEXT,
+                updated_at TEXT NOT NULL DEFAULT '',
+                deprecated INTEGER NOT NULL DEFAULT 0,
+                tags TEXT NOT NULL DEFAULT '[]',
+                description TEXT,
+                version INTEGER NOT NULL DEFAULT 1
             )
             "#,
         )
         .execute(&pool)
         .await?;
 
-        Ok(Self { pool })
-    }
-}
+        // Databases created by older versions are missing the newer columns
+        add_column_if_missing(&pool, "golinks", "locked", "INTEGER NOT NULL DEFAULT 0").await?;
+        add_column_if_missing(&pool, "golinks", "clicks", "INTEGER NOT NULL DEFAULT 0").await?;
+        add_column_if_missing(&pool, "golinks", "last_used_at", "TEXT").await?;
+        add_column_if_missing(&pool, "golinks", "owner", "TEXT").await?;
+        if add_column_if_missing(&pool, "golinks", "updated_at", "TEXT NOT NULL DEFAULT ''").await? {
+            sqlx::query("UPDATE golinks SET updated_at = created_at")
+                .execute(&pool)
+                .await?;
+        }
+        add_column_if_missing(&pool, "golinks", "deprecated", "INTEGER NOT NULL DEFAULT 0").await?;
+        add_column_if_missing(&pool, "golinks", "tags", "TEXT NOT NULL DEFAULT '[]'").await?;
+        add_column_if_missing(&pool, "golinks", "description", "TEXT").await?;
+        add_column_if_missing(&pool, "golinks", "version", "INTEGER NOT NULL DEFAULT 1").await?;
 
-#[async_trait]
-impl GoStorage for SqliteStorage {
-    async fn create(&self, golink: Golink) -> StorageResult<()> {
-        let result = sqlx::query(
-            "INSERT INTO golinks (id, short_link, url, created_at) VALUES (?, ?, ?, ?)",
+        // Full-text index over the searchable columns, kept in sync by triggers.
+        // The columns are in the order of `search::SEARCH_FIELDS`.
+        let fts_exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = 'golinks_fts')",
         )
-        .bind(&golink.id)
-        .bind(&golink.short_link)
-        .bind(&golink.url)
-        .bind(&golink.created_at)
-        .execute(&self.pool)
-        .await;
-
-        match result {
-            Ok(_) => Ok(()),
-            Err(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => {
-                Err(StorageError::AlreadyExists)
-            }
-            Err(e) => Err(StorageError::DatabaseError(e.to_string())),
+        .fetch_one(&pool)
+        .await?;
+        sqlx::query(
+            r#"
+            CREATE VIRTUAL TABLE IF NOT EXISTS golinks_fts USING fts5(
+                short_link, description, tags, url,
+                content = 'golinks', content_rowid = 'rowid'
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query(
+            r#"
+            CREATE TRIGGER IF NOT EXISTS golinks_fts_insert AFTER INSERT ON golinks BEGIN
+                INSERT INTO golinks_fts (rowid, short_link, description, tags, url)
+                VALUES (new.rowid, new.short_link, new.description, new.tags, new.url);
+            END
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query(
+            r#"
+            CREATE TRIGGER IF NOT EXISTS golinks_fts_delete AFTER DELETE ON golinks BEGIN
+                INSERT INTO golinks_fts (golinks_fts, rowid, short_link, description, tags, url)
+                VALUES ('delete', old.rowid, old.short_link, old.description, old.tags, old.url);
+            END
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query(
+            r#"
+            CREATE TRIGGER IF NOT EXISTS golinks_fts_update
+            AFTER UPDATE OF short_link, description, tags, url ON golinks BEGIN
+                INSERT INTO golinks_fts (golinks_fts, rowid, short_link, description, tags, url)
+                VALUES ('delete', old.rowid, old.short_link, old.description, old.tags, old.url);
+                INSERT INTO golinks_fts (rowid, short_link, description, tags, url)
+                VALUES (new.rowid, new.short_link, new.description, new.tags, new.url);
+            END
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+        if !fts_exists {
+            // Index golinks created before search existed
+            sqlx::query("INSERT INTO golinks_fts (golinks_fts) VALUES ('rebuild')")
+                .execute(&pool)
+                .await?;
         }
-    }
 
-    async fn get(&self, short_link: &str) -> StorageResult<Golink> {
-        let row = sqlx::query_as::<_, Golink>(
-            "SELECT id, short_link, url, created_at FROM golinks WHERE short_link = ?",
+        // Single row change marker for conditional requests, moved on by
+        // triggers so every write to golinks counts
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS revision (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                instance TEXT NOT NULL,
+                counter INTEGER NOT NULL,
+                changed_at TEXT NOT NULL
+            )
+            "#,
         )
-        .bind(short_link)
-        .fetch_optional(&self.pool)
-        .await
-        .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-
-        row.ok_or(StorageError::NotFound)
-    }
+        .execute(&pool)
+        .await?;
+        sqlx::query(
+            "INSERT OR IGNORE INTO revision (id, instance, counter, changed_at) VALUES (1, ?, 0, ?)",
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(chrono::Utc::now().to_rfc3339())
+        .execute(&pool)
+        .await?;
+        for (name, event) in [
+            ("revision_insert", "INSERT"),
+            ("revision_update", "UPDATE"),
+            ("revision_delete", "DELETE"),
+        ] {
+            sqlx::query(&format!(
+                r#"
+                CREATE TRIGGER IF NOT EXISTS {} AFTER {} ON golinks BEGIN
+                    UPDATE revision
+                    SET counter = counter + 1,
+                        changed_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now');
+                END
+                "#,
+                name, event
+            ))
+            .execute(&pool)
+            .await?;
+        }
 
-    async fn get_all(&self) -> StorageResult<Vec<Golink>> {
-        let rows = sqlx::query_as::<_, Golink>(
-            "SELECT id, short_link, url, created_at FROM golinks ORDER BY created_at DESC",
+        // Click time series, one row per golink and bucket
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS click_buckets (
+                short_link TEXT NOT NULL,
+                granularity TEXT NOT NULL,
+                bucket_start INTEGER NOT NULL,
+                clicks INTEGER NOT NULL,
+                PRIMARY KEY (short_link, granularity, bucket_start)
+            )
+            "#,
         )
-        .fetch_all(&self.pool)
-        .await
-        .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        .execute(&pool)
+        .await?;
 
-        Ok(rows)
-    }
+        // Leaderboards and pruning scan buckets by time across all golinks
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_click_buckets_time ON click_buckets (granularity, bucket_start)",
+        )
+        .execute(&pool)
+        .await?;
 
-    async fn get_paginated(&self, page: usize, page_size: usize) -> StorageResult<(Vec<Golink>, usize)> {
-        let offset = (page.saturating_sub(1)) * page_size;
+        // Daily clicks per referrer host ('' when there was none) and client class
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS click_sources (
+                short_link TEXT NOT NULL,
+                day_start INTEGER NOT NULL,
+                referrer TEXT NOT NULL,
+                client TEXT NOT NULL,
+                clicks INTEGER NOT NULL,
+                PRIMARY KEY (short_link, day_start, referrer, client)
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
 
-        // Get total count
-        let total_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM golinks")
-            .fetch_one(&self.pool)
-            .await
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        // Collections and their ordered members
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS collections (
+                id TEXT NOT NULL,
+                name TEXT PRIMARY KEY,
+                title TEXT NOT NULL,
+                description TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
 
-        // Get paginated results
-        let rows = sqlx::query_as::<_, Golink>(
-            "SELECT id, short_link, url, created_at FROM golinks ORDER BY created_at DESC LIMIT ? OFFSET ?",
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS collection_links (
+                collection TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                short_link TEXT NOT NULL,
+                PRIMARY KEY (collection, position)
+            )
+            "#,
         )
-        .bind(page_size as i64)
-        .bind(offset as i64)
-        .fetch_all(&self.pool)
-        .await
-        .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        .execute(&pool)
+        .await?;
 
-        Ok((rows, total_count as usize))
-    }
+        // Old names of renamed golinks that still resolve
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS aliases (
+                alias TEXT PRIMARY KEY,
+                short_link TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
 
-    async fn update(&self, short_link: &str, url: String) -> StorageResult<Golink> {
-        let result = sqlx::query("UPDATE golinks SET url = ? WHERE short_link = ?")
-            .bind(&url)
+        // Golinks starred per user identity
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS favorites (
+                user TEXT NOT NULL,
+                short_link TEXT NOT NULL,
+                favorited_at TEXT NOT NULL,
+                PRIMARY KEY (user, short_link)
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(Self { pool })
+    }
+
+    // Applies the changes, provided the golink is still at `version` when one
+    // is given
+    async fn apply_changes_at(
+        &self,
+        short_link: &str,
+        version: Option<i64>,
+        changes: GolinkChanges,
+    ) -> StorageResult<Golink> {
+        // A single UPDATE so either every change lands or none does
+        let mut query = sqlx::QueryBuilder::<sqlx::Sqlite>::new("UPDATE golinks SET updated_at = ");
+        query.push_bind(chrono::Utc::now().to_rfc3339());
+        query.push(", version = version + 1");
+        if let Some(url) = changes.url {
+            query.push(", url = ").push_bind(url);
+        }
+        if let Some(owner) = changes.owner {
+            query.push(", owner = ").push_bind(owner);
+        }
+        if let Some(locked) = changes.locked {
+            query.push(", locked = ").push_bind(locked);
+        }
+        if let Some(deprecated) = changes.deprecated {
+            query.push(", deprecated = ").push_bind(deprecated);
+        }
+        if let Some(tags) = changes.tags {
+            query.push(", tags = ").push_bind(sqlx::types::Json(tags));
+        }
+        if let Some(description) = changes.description {
+            query.push(", description = ").push_bind(description);
+        }
+        query.push(" WHERE short_link = ").push_bind(short_link);
+        if let Some(version) = version {
+            query.push(" AND version = ").push_bind(version);
+        }
+
+        let result = query
+            .build()
+            .execute(&self.pool)
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            let mut conn = self
+                .pool
+                .acquire()
+                .await
+                .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+            return Err(missing_or_changed(&mut conn, short_link).await);
+        }
+
+        self.get(short_link).await
+    }
+
+    // Renames the golink, provided it is still at `version` when one is given
+    async fn rename_at(
+        &self,
+        short_link: &str,
+        new_short_link: &str,
+        keep_alias: bool,
+        version: Option<i64>,
+    ) -> StorageResult<Golink> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        let exists: bool =
+            sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM golinks WHERE short_link = ?)")
+                .bind(short_link)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        if !exists {
+            return Err(StorageError::NotFound);
+        }
+
+        // Renaming back to one of its own aliases frees that alias
+        sqlx::query("DELETE FROM aliases WHERE alias = ? AND short_link = ?")
+            .bind(new_short_link)
+            .bind(short_link)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        let taken: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM golinks WHERE short_link = ?) \
+             OR EXISTS (SELECT 1 FROM collections WHERE name = ?) \
+             OR EXISTS (SELECT 1 FROM aliases WHERE alias = ?)",
+        )
+        .bind(new_short_link)
+        .bind(new_short_link)
+        .bind(new_short_link)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        if taken {
+            return Err(StorageError::AlreadyExists);
+        }
+
+        let renamed = sqlx::query(
+            "UPDATE golinks SET short_link = ?, updated_at = ?, version = version + 1 \
+             WHERE short_link = ? AND version = COALESCE(?, version)",
+        )
+        .bind(new_short_link)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(short_link)
+        .bind(version)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        if renamed.rows_affected() == 0 {
+            return Err(missing_or_changed(&mut tx, short_link).await);
+        }
+
+        for table in [
+            "click_buckets",
+            "click_sources",
+            "favorites",
+            "collection_links",
+            "aliases",
+        ] {
+            sqlx::query(&format!("UPDATE {} SET short_link = ? WHERE short_link = ?", table))
+                .bind(new_short_link)
+                .bind(short_link)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        }
+
+        if keep_alias {
+            sqlx::query("INSERT INTO aliases (alias, short_link, created_at) VALUES (?, ?, ?)")
+                .bind(short_link)
+                .bind(new_short_link)
+                .bind(chrono::Utc::now().to_rfc3339())
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        self.get(new_short_link).await
+    }
+}
+
+// Why a write that matched no rows did nothing
+async fn missing_or_changed(conn: &mut sqlx::SqliteConnection, short_link: &str) -> StorageError {
+    let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM golinks WHERE short_link = ?)")
+        .bind(short_link)
+        .fetch_one(conn)
+        .await;
+    match exists {
+        Ok(true) => StorageError::VersionMismatch,
+        Ok(false) => StorageError::NotFound,
+        Err(e) => StorageError::DatabaseError(e.to_string()),
+    }
+}
+
+async fn insert_golink(conn: &mut sqlx::SqliteConnection, golink: &Golink) -> StorageResult<()> {
+    // The name must not be taken by a collection or alias either
+    let result = sqlx::query(
+        "INSERT INTO golinks (id, short_link, url, created_at, locked, clicks, last_used_at, \
+         owner, updated_at, deprecated, tags, description, version) \
+         SELECT ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ? \
+         WHERE NOT EXISTS (SELECT 1 FROM collections WHERE name = ?) \
+         AND NOT EXISTS (SELECT 1 FROM aliases WHERE alias = ?)",
+    )
+    .bind(&golink.id)
+    .bind(&golink.short_link)
+    .bind(&golink.url)
+    .bind(&golink.created_at)
+    .bind(golink.locked)
+    .bind(golink.clicks)
+    .bind(&golink.last_used_at)
+    .bind(&golink.owner)
+    .bind(&golink.updated_at)
+    .bind(golink.deprecated)
+    .bind(sqlx::types::Json(&golink.tags))
+    .bind(&golink.description)
+    .bind(golink.version)
+    .bind(&golink.short_link)
+    .bind(&golink.short_link)
+    .execute(conn)
+    .await;
+
+    match result {
+        Ok(result) if result.rows_affected() == 0 => Err(StorageError::AlreadyExists),
+        Ok(_) => Ok(()),
+        Err(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => {
+            Err(StorageError::AlreadyExists)
+        }
+        Err(e) => Err(StorageError::DatabaseError(e.to_string())),
+    }
+}
+
+async fn replace_golink(conn: &mut sqlx::SqliteConnection, golink: &Golink) -> StorageResult<()> {
+    let result = sqlx::query(
+        "INSERT INTO golinks (id, short_link, url, created_at, locked, clicks, last_used_at, \
+         owner, updated_at, deprecated, tags, description, version) \
+         SELECT ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ? \
+         WHERE NOT EXISTS (SELECT 1 FROM collections WHERE name = ?) \
+         AND NOT EXISTS (SELECT 1 FROM aliases WHERE alias = ?) \
+         ON CONFLICT (short_link) DO UPDATE SET id = excluded.id, url = excluded.url, \
+         created_at = excluded.created_at, locked = excluded.locked, clicks = excluded.clicks, \
+         last_used_at = excluded.last_used_at, owner = excluded.owner, \
+         updated_at = excluded.updated_at, deprecated = excluded.deprecated, \
+         tags = excluded.tags, description = excluded.description, \
+         version = golinks.version + 1",
+    )
+    .bind(&golink.id)
+    .bind(&golink.short_link)
+    .bind(&golink.url)
+    .bind(&golink.created_at)
+    .bind(golink.locked)
+    .bind(golink.clicks)
+    .bind(&golink.last_used_at)
+    .bind(&golink.owner)
+    .bind(&golink.updated_at)
+    .bind(golink.deprecated)
+    .bind(sqlx::types::Json(&golink.tags))
+    .bind(&golink.description)
+    .bind(golink.version)
+    .bind(&golink.short_link)
+    .bind(&golink.short_link)
+    .execute(conn)
+    .await;
+
+    match result {
+        Ok(result) if result.rows_affected() == 0 => Err(StorageError::AlreadyExists),
+        Ok(_) => Ok(()),
+        Err(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => {
+            Err(StorageError::AlreadyExists)
+        }
+        Err(e) => Err(StorageError::DatabaseError(e.to_string())),
+    }
+}
+
+// Fails with `Locked` if `unlocked_only` is set and the golink is locked. A
+// missing golink is left for the write itself to report.
+async fn refuse_locked(
+    conn: &mut sqlx::SqliteConnection,
+    short_link: &str,
+    unlocked_only: bool,
+) -> StorageResult<()> {
+    if !unlocked_only {
+        return Ok(());
+    }
+    let locked: Option<bool> = sqlx::query_scalar("SELECT locked FROM golinks WHERE short_link = ?")
+        .bind(short_link)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+    match locked {
+        Some(true) => Err(StorageError::Locked),
+        _ => Ok(()),
+    }
+}
+
+async fn update_golink_url(
+    conn: &mut sqlx::SqliteConnection,
+    short_link: &str,
+    url: &str,
+) -> StorageResult<Golink> {
+    let result = sqlx::query(
+        "UPDATE golinks SET url = ?, updated_at = ?, version = version + 1 WHERE short_link = ?",
+    )
+        .bind(url)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(short_link)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+    if result.rows_affected() == 0 {
+        return Err(StorageError::NotFound);
+    }
+
+    // Fetch the updated record
+    sqlx::query_as::<_, Golink>(&format!(
+        "SELECT {} FROM golinks WHERE short_link = ?",
+        GOLINK_COLUMNS
+    ))
+    .bind(short_link)
+    .fetch_one(conn)
+    .await
+    .map_err(|e| StorageError::DatabaseError(e.to_string()))
+}
+
+// Deletes a golink along with its clicks, favorites, memberships and aliases,
+// when it is still at `version`, if one is given
+async fn delete_golink(
+    conn: &mut sqlx::SqliteConnection,
+    short_link: &str,
+    version: Option<i64>,
+) -> StorageResult<()> {
+    let result = sqlx::query("DELETE FROM golinks WHERE short_link = ? AND (? IS NULL OR version = ?)")
+        .bind(short_link)
+        .bind(version)
+        .bind(version)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+    if result.rows_affected() == 0 {
+        return Err(missing_or_changed(conn, short_link).await);
+    }
+
+    for table in [
+        "click_buckets",
+        "click_sources",
+        "favorites",
+        "collection_links",
+        "aliases",
+    ] {
+        sqlx::query(&format!("DELETE FROM {} WHERE short_link = ?", table))
+            .bind(short_link)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+    }
+    Ok(())
+}
+
+async fn insert_collection_links(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    collection: &str,
+    links: &[String],
+) -> StorageResult<()> {
+    for (position, short_link) in links.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO collection_links (collection, position, short_link) VALUES (?, ?, ?)",
+        )
+        .bind(collection)
+        .bind(position as i64)
+        .bind(short_link)
+        .execute(&mut **tx)
+        .await
+        .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+    }
+    Ok(())
+}
+
+// Returns whether the column had to be added
+async fn add_column_if_missing(
+    pool: &sqlx::SqlitePool,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<bool, sqlx::Error> {
+    let columns: Vec<String> =
+        sqlx::query_scalar(&format!("SELECT name FROM pragma_table_info('{}')", table))
+            .fetch_all(pool)
+            .await?;
+
+    if !columns.iter().any(|name| name == column) {
+        sqlx::query(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {}",
+            table, column, definition
+        ))
+        .execute(pool)
+        .await?;
+        return Ok(true);
+    }
+
+    Ok(false)
+}
+
+#[async_trait]
+impl GoStorage for SqliteStorage {
+    async fn create(&self, golink: Golink) -> StorageResult<()> {
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        insert_golink(&mut conn, &golink).await
+    }
+
+    async fn get(&self, short_link: &str) -> StorageResult<Golink> {
+        let row = sqlx::query_as::<_, Golink>(
+            &format!("SELECT {} FROM golinks WHERE short_link = ?", GOLINK_COLUMNS),
+        )
+        .bind(short_link)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        row.ok_or(StorageError::NotFound)
+    }
+
+    async fn get_all(&self) -> StorageResult<Vec<Golink>> {
+        let rows = sqlx::query_as::<_, Golink>(
+            &format!("SELECT {} FROM golinks ORDER BY created_at DESC, short_link DESC", GOLINK_COLUMNS),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        Ok(rows)
+    }
+
+    async fn list(
+        &self,
+        query: &ListQuery,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> StorageResult<(Vec<Golink>, usize)> {
+        let mut count = sqlx::QueryBuilder::new("SELECT COUNT(*) FROM golinks");
+        query.filter.push_where(&mut count);
+        let total_count: i64 = count
+            .build_query_scalar()
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        let mut select = sqlx::QueryBuilder::new(format!("SELECT {} FROM golinks", GOLINK_COLUMNS));
+        query.filter.push_where(&mut select);
+        query.push_after(&mut select);
+        select.push(format!(" ORDER BY {}", query.sort.order_by(query.direction())));
+        // SQLite treats a negative limit as no limit
+        select
+            .push(" LIMIT ")
+            .push_bind(limit.map_or(-1, |limit| limit as i64))
+            .push(" OFFSET ")
+            .push_bind(offset as i64);
+        let rows = select
+            .build_query_as::<Golink>()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        Ok((rows, total_count as usize))
+    }
+
+    async fn update(&self, short_link: &str, url: String) -> StorageResult<Golink> {
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        update_golink_url(&mut conn, short_link, &url).await
+    }
+
+    async fn delete(&self, short_link: &str) -> StorageResult<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        delete_golink(&mut tx, short_link, None).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))
+    }
+
+    async fn exists(&self, short_link: &str) -> StorageResult<bool> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM golinks WHERE short_link = ?")
+            .bind(short_link)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        Ok(count > 0)
+    }
+
+    async fn name_taken(&self, name: &str) -> StorageResult<bool> {
+        sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM golinks WHERE short_link = ?) \
+             OR EXISTS (SELECT 1 FROM collections WHERE name = ?) \
+             OR EXISTS (SELECT 1 FROM aliases WHERE alias = ?)",
+        )
+        .bind(name)
+        .bind(name)
+        .bind(name)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| StorageError::DatabaseError(e.to_string()))
+    }
+
+    async fn set_locked(&self, short_link: &str, locked: bool) -> StorageResult<Golink> {
+        let result = sqlx::query(
+            "UPDATE golinks SET locked = ?, updated_at = ?, version = version + 1 \
+             WHERE short_link = ?",
+        )
+            .bind(locked)
+            .bind(chrono::Utc::now().to_rfc3339())
             .bind(short_link)
             .execute(&self.pool)
             .await
             .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
 
-        if result.rows_affected() == 0 {
-            return Err(StorageError::NotFound);
+        if result.rows_affected() == 0 {
+            return Err(StorageError::NotFound);
+        }
+
+        self.get(short_link).await
+    }
+
+    async fn apply_batch(
+        &self,
+        operations: Vec<BatchOperation>,
+        atomic: bool,
+    ) -> StorageResult<Vec<BatchResult>> {
+        use sqlx::Connection;
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        let mut results = Vec::with_capacity(operations.len());
+        for operation in operations {
+            // Each operation gets a savepoint so a failed one leaves nothing behind
+            let mut op_tx = tx
+                .begin()
+                .await
+                .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+            let result = match operation {
+                BatchOperation::Create(golink) => {
+                    insert_golink(&mut op_tx, &golink).await.map(|_| Some(golink))
+                }
+                BatchOperation::Replace(golink) => {
+                    replace_golink(&mut op_tx, &golink).await.map(|_| Some(golink))
+                }
+                BatchOperation::Update {
+                    short_link,
+                    url,
+                    unlocked_only,
+                } => match refuse_locked(&mut op_tx, &short_link, unlocked_only).await {
+                    Ok(()) => update_golink_url(&mut op_tx, &short_link, &url)
+                        .await
+                        .map(Some),
+                    Err(e) => Err(e),
+                },
+                BatchOperation::Delete {
+                    short_link,
+                    unlocked_only,
+                } => match refuse_locked(&mut op_tx, &short_link, unlocked_only).await {
+                    Ok(()) => delete_golink(&mut op_tx, &short_link, None).await.map(|_| None),
+                    Err(e) => Err(e),
+                },
+            };
+            let finished = if result.is_ok() {
+                op_tx.commit().await
+            } else {
+                op_tx.rollback().await
+            };
+            finished.map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+            results.push(result);
+        }
+
+        let finished = if atomic && results.iter().any(Result::is_err) {
+            tx.rollback().await
+        } else {
+            tx.commit().await
+        };
+        finished.map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        Ok(results)
+    }
+
+    async fn apply_changes(&self, short_link: &str, changes: GolinkChanges) -> StorageResult<Golink> {
+        self.apply_changes_at(short_link, None, changes).await
+    }
+
+    async fn apply_changes_if(
+        &self,
+        short_link: &str,
+        version: i64,
+        changes: GolinkChanges,
+    ) -> StorageResult<Golink> {
+        self.apply_changes_at(short_link, Some(version), changes).await
+    }
+
+    async fn delete_if(&self, short_link: &str, version: i64) -> StorageResult<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        delete_golink(&mut tx, short_link, Some(version)).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))
+    }
+
+    async fn record_clicks(&self, clicks: Vec<ClickCount>) -> StorageResult<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        for click in clicks {
+            let result = sqlx::query(
+                "UPDATE golinks SET clicks = clicks + ?, last_used_at = COALESCE(?, last_used_at) \
+                 WHERE short_link = ?",
+            )
+            .bind(click.count)
+            .bind(&click.last_used_at)
+            .bind(&click.short_link)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+            if result.rows_affected() == 0 {
+                continue;
+            }
+
+            for ((day, referrer, client), count) in &click.sources {
+                sqlx::query(
+                    "INSERT INTO click_sources (short_link, day_start, referrer, client, clicks) \
+                     VALUES (?, ?, ?, ?, ?) \
+                     ON CONFLICT (short_link, day_start, referrer, client) \
+                     DO UPDATE SET clicks = clicks + excluded.clicks",
+                )
+                .bind(&click.short_link)
+                .bind(day)
+                .bind(referrer.as_deref().unwrap_or(""))
+                .bind(client.as_str())
+                .bind(count)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+            }
+
+            let daily = click.daily();
+            let series = [(Granularity::Hour, &click.hourly), (Granularity::Day, &daily)];
+            for (granularity, counts) in series {
+                for (start, count) in counts {
+                    sqlx::query(
+                        "INSERT INTO click_buckets (short_link, granularity, bucket_start, clicks) \
+                         VALUES (?, ?, ?, ?) \
+                         ON CONFLICT (short_link, granularity, bucket_start) \
+                         DO UPDATE SET clicks = clicks + excluded.clicks",
+                    )
+                    .bind(&click.short_link)
+                    .bind(granularity.as_str())
+                    .bind(start)
+                    .bind(count)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+                }
+            }
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))
+    }
+
+    async fn get_click_buckets(
+        &self,
+        short_link: &str,
+        granularity: Granularity,
+        from: i64,
+        to: i64,
+    ) -> StorageResult<Vec<ClickBucket>> {
+        let rows: Vec<(i64, i64)> = sqlx::query_as(
+            "SELECT bucket_start, clicks FROM click_buckets \
+             WHERE short_link = ? AND granularity = ? AND bucket_start >= ? AND bucket_start < ? \
+             ORDER BY bucket_start",
+        )
+        .bind(short_link)
+        .bind(granularity.as_str())
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(start, clicks)| ClickBucket { start, clicks })
+            .collect())
+    }
+
+    async fn add_favorite(&self, user: &str, short_link: &str) -> StorageResult<Favorite> {
+        // Only golinks that exist can be starred, the SELECT yields no row otherwise
+        sqlx::query(
+            "INSERT OR IGNORE INTO favorites (user, short_link, favorited_at) \
+             SELECT ?, short_link, ? FROM golinks WHERE short_link = ?",
+        )
+        .bind(user)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(short_link)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        sqlx::query_as::<_, Favorite>(&format!(
+            "SELECT {}, favorited_at FROM golinks JOIN favorites USING (short_link) \
+             WHERE user = ? AND short_link = ?",
+            GOLINK_COLUMNS
+        ))
+        .bind(user)
+        .bind(short_link)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| StorageError::DatabaseError(e.to_string()))?
+        .ok_or(StorageError::NotFound)
+    }
+
+    async fn remove_favorite(&self, user: &str, short_link: &str) -> StorageResult<()> {
+        let result = sqlx::query("DELETE FROM favorites WHERE user = ? AND short_link = ?")
+            .bind(user)
+            .bind(short_link)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(StorageError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn get_favorites(&self, user: &str) -> StorageResult<Vec<Favorite>> {
+        sqlx::query_as::<_, Favorite>(&format!(
+            "SELECT {}, favorited_at FROM golinks JOIN favorites USING (short_link) \
+             WHERE user = ? ORDER BY favorited_at DESC, short_link",
+            GOLINK_COLUMNS
+        ))
+        .bind(user)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StorageError::DatabaseError(e.to_string()))
+    }
+
+    async fn rename(
+        &self,
+        short_link: &str,
+        new_short_link: &str,
+        keep_alias: bool,
+    ) -> StorageResult<Golink> {
+        self.rename_at(short_link, new_short_link, keep_alias, None).await
+    }
+
+    async fn rename_if(
+        &self,
+        short_link: &str,
+        new_short_link: &str,
+        keep_alias: bool,
+        version: i64,
+    ) -> StorageResult<Golink> {
+        self.rename_at(short_link, new_short_link, keep_alias, Some(version))
+            .await
+    }
+
+    async fn resolve_alias(&self, alias: &str) -> StorageResult<String> {
+        sqlx::query_scalar("SELECT short_link FROM aliases WHERE alias = ?")
+            .bind(alias)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?
+            .ok_or(StorageError::NotFound)
+    }
+
+    async fn create_collection(&self, collection: Collection) -> StorageResult<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        // The name must not be taken by a golink or alias either
+        let result = sqlx::query(
+            "INSERT INTO collections (id, name, title, description, created_at, updated_at) \
+             SELECT ?, ?, ?, ?, ?, ? WHERE NOT EXISTS (SELECT 1 FROM golinks WHERE short_link = ?) \
+             AND NOT EXISTS (SELECT 1 FROM aliases WHERE alias = ?)",
+        )
+        .bind(&collection.id)
+        .bind(&collection.name)
+        .bind(&collection.title)
+        .bind(&collection.description)
+        .bind(&collection.created_at)
+        .bind(&collection.updated_at)
+        .bind(&collection.name)
+        .bind(&collection.name)
+        .execute(&mut *tx)
+        .await;
+
+        match result {
+            Ok(result) if result.rows_affected() == 0 => return Err(StorageError::AlreadyExists),
+            Ok(_) => {}
+            Err(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => {
+                return Err(StorageError::AlreadyExists);
+            }
+            Err(e) => return Err(StorageError::DatabaseError(e.to_string())),
+        }
+
+        insert_collection_links(&mut tx, &collection.name, &collection.links).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))
+    }
+
+    async fn get_collection(&self, name: &str) -> StorageResult<Collection> {
+        let mut collection = sqlx::query_as::<_, Collection>(&format!(
+            "SELECT {} FROM collections WHERE name = ?",
+            COLLECTION_COLUMNS
+        ))
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| StorageError::DatabaseError(e.to_string()))?
+        .ok_or(StorageError::NotFound)?;
+
+        collection.links = sqlx::query_scalar(
+            "SELECT short_link FROM collection_links WHERE collection = ? ORDER BY position",
+        )
+        .bind(name)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        Ok(collection)
+    }
+
+    async fn get_collections(&self) -> StorageResult<Vec<Collection>> {
+        let mut collections = sqlx::query_as::<_, Collection>(&format!(
+            "SELECT {} FROM collections ORDER BY name",
+            COLLECTION_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        let links: Vec<(String, String)> = sqlx::query_as(
+            "SELECT collection, short_link FROM collection_links ORDER BY collection, position",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        let mut members: HashMap<String, Vec<String>> = HashMap::new();
+        for (collection, short_link) in links {
+            members.entry(collection).or_default().push(short_link);
+        }
+        for collection in &mut collections {
+            collection.links = members.remove(&collection.name).unwrap_or_default();
+        }
+
+        Ok(collections)
+    }
+
+    async fn update_collection(
+        &self,
+        name: &str,
+        title: String,
+        description: Option<String>,
+        links: Vec<String>,
+    ) -> StorageResult<Collection> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        let result = sqlx::query(
+            "UPDATE collections SET title = ?, description = ?, updated_at = ? WHERE name = ?",
+        )
+        .bind(&title)
+        .bind(&description)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(name)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(StorageError::NotFound);
+        }
+
+        sqlx::query("DELETE FROM collection_links WHERE collection = ?")
+            .bind(name)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        insert_collection_links(&mut tx, name, &links).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        self.get_collection(name).await
+    }
+
+    async fn delete_collection(&self, name: &str) -> StorageResult<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        let result = sqlx::query("DELETE FROM collections WHERE name = ?")
+            .bind(name)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(StorageError::NotFound);
+        }
+
+        sqlx::query("DELETE FROM collection_links WHERE collection = ?")
+            .bind(name)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))
+    }
+
+    async fn search(&self, query: &str, limit: usize) -> StorageResult<Vec<SearchHit>> {
+        let tokens = search::query_tokens(query);
+        if tokens.is_empty() {
+            return Ok(Vec::new());
+        }
+        // Every word as a quoted prefix query, so nothing in it is read as FTS5 syntax
+        let fts_query = tokens
+            .iter()
+            .map(|token| format!("\"{}\"*", token))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let weights = search::SEARCH_FIELDS
+            .iter()
+            .map(|(_, weight)| weight.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let columns = GOLINK_COLUMNS
+            .split(", ")
+            .map(|column| format!("g.{}", column))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let mut hits = sqlx::query_as::<_, SearchHit>(&format!(
+            "SELECT {}, -bm25(golinks_fts, {}) AS score \
+             FROM golinks_fts JOIN golinks g ON g.rowid = golinks_fts.rowid \
+             WHERE golinks_fts MATCH ? ORDER BY score DESC, g.short_link LIMIT ?",
+            columns, weights
+        ))
+        .bind(fts_query)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        for hit in &mut hits {
+            hit.highlight = search::highlight(&hit.golink, &tokens);
+        }
+        Ok(hits)
+    }
+
+    async fn suggest(&self, prefix: &str, limit: usize) -> StorageResult<Vec<Golink>> {
+        // A range rather than LIKE, so the lookup uses the unique index on
+        // short_link whatever characters the prefix has
+        let rows = sqlx::query_as::<_, Golink>(&format!(
+            "SELECT {} FROM golinks WHERE short_link >= ? AND short_link < ? \
+             ORDER BY clicks DESC, short_link LIMIT ?",
+            GOLINK_COLUMNS
+        ))
+        .bind(prefix)
+        .bind(format!("{}{}", prefix, char::MAX))
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        Ok(rows)
+    }
+
+    async fn get_stale(&self, cutoff: &str) -> StorageResult<Vec<Golink>> {
+        let rows = sqlx::query_as::<_, Golink>(&format!(
+            "SELECT {} FROM golinks \
+             WHERE (last_used_at IS NULL OR last_used_at < ?) AND created_at < ? AND updated_at < ? \
+             ORDER BY short_link",
+            GOLINK_COLUMNS
+        ))
+        .bind(cutoff)
+        .bind(cutoff)
+        .bind(cutoff)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        Ok(rows)
+    }
+
+    async fn set_deprecated(&self, short_links: &[String], deprecated: bool) -> StorageResult<usize> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        let mut found = 0;
+        for short_link in short_links {
+            let result = sqlx::query(
+                "UPDATE golinks SET deprecated = ?, version = version + 1 WHERE short_link = ?",
+            )
+                .bind(deprecated)
+                .bind(short_link)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+            found += result.rows_affected() as usize;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        Ok(found)
+    }
+
+    async fn get_click_totals(
+        &self,
+        granularity: Granularity,
+        from: i64,
+        to: i64,
+    ) -> StorageResult<HashMap<String, i64>> {
+        let rows: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT short_link, SUM(clicks) FROM click_buckets \
+             WHERE granularity = ? AND bucket_start >= ? AND bucket_start < ? \
+             GROUP BY short_link",
+        )
+        .bind(granularity.as_str())
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        Ok(rows.into_iter().collect())
+    }
+
+    async fn get_click_sources(
+        &self,
+        short_link: &str,
+        from: i64,
+        to: i64,
+    ) -> StorageResult<Vec<SourceCount>> {
+        let rows: Vec<(String, String, i64)> = sqlx::query_as(
+            "SELECT referrer, client, SUM(clicks) FROM click_sources \
+             WHERE short_link = ? AND day_start >= ? AND day_start < ? \
+             GROUP BY referrer, client",
+        )
+        .bind(short_link)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(referrer, client, clicks)| {
+                Some(SourceCount {
+                    referrer: (!referrer.is_empty()).then_some(referrer),
+                    client: ClientClass::parse(&client)?,
+                    clicks,
+                })
+            })
+            .collect())
+    }
+
+    async fn prune_click_buckets(&self, granularity: Granularity, before: i64) -> StorageResult<()> {
+        sqlx::query("DELETE FROM click_buckets WHERE granularity = ? AND bucket_start < ?")
+            .bind(granularity.as_str())
+            .bind(before)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+
+        if granularity == Granularity::Day {
+            sqlx::query("DELETE FROM click_sources WHERE day_start < ?")
+                .bind(before)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    async fn revision(&self) -> StorageResult<Revision> {
+        let (instance, counter, changed_at) = sqlx::query_as::<_, (String, i64, String)>(
+            "SELECT instance, counter, changed_at FROM revision WHERE id = 1",
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+        Ok(Revision {
+            instance,
+            counter,
+            changed_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::Golink;
+    #[cfg(feature = "sqlite-tests")]
+    use tempfile::NamedTempFile;
+
+    fn create_test_collection(name: &str, links: &[&str]) -> Collection {
+        let now = chrono::Utc::now().to_rfc3339();
+        Collection {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            title: "Onboarding".to_string(),
+            description: None,
+            links: links.iter().map(|link| link.to_string()).collect(),
+            created_at: now.clone(),
+            updated_at: now,
+        }
+    }
+
+    fn create_test_golink(short_link: &str, url: &str) -> Golink {
+        let now = chrono::Utc::now().to_rfc3339();
+        Golink {
+            id: uuid::Uuid::new_v4().to_string(),
+            short_link: short_link.to_string(),
+            url: url.to_string(),
+            created_at: now.clone(),
+            updated_at: now,
+            ..Default::default()
+        }
+    }
+
+    mod hashmap_storage_tests {
+        use super::*;
+
+        #[tokio::test]
+        async fn test_create_and_get_golink() {
+            let storage = HashMapStorage::new();
+            let golink = create_test_golink("go/test", "https://example.com");
+
+            // Test create
+            let result = storage.create(golink.clone()).await;
+            assert!(result.is_ok());
+
+            // Test get
+            let retrieved = storage.get(&golink.short_link).await;
+            assert!(retrieved.is_ok());
+            let retrieved_golink = retrieved.unwrap();
+            assert_eq!(retrieved_golink.short_link, golink.short_link);
+            assert_eq!(retrieved_golink.url, golink.url);
+        }
+
+        #[tokio::test]
+        async fn test_create_duplicate_returns_error() {
+            let storage = HashMapStorage::new();
+            let golink = create_test_golink("go/test", "https://example.com");
+
+            // Create first time
+            let result1 = storage.create(golink.clone()).await;
+            assert!(result1.is_ok());
+
+            // Create second time should fail
+            let result2 = storage.create(golink.clone()).await;
+            assert!(matches!(result2, Err(StorageError::AlreadyExists)));
+        }
+
+        #[tokio::test]
+        async fn test_get_nonexistent_returns_not_found() {
+            let storage = HashMapStorage::new();
+            let result = storage.get("go/nonexistent").await;
+            assert!(matches!(result, Err(StorageError::NotFound)));
+        }
+
+        #[tokio::test]
+        async fn test_get_all_golinks() {
+            let storage = HashMapStorage::new();
+            let golink1 = create_test_golink("go/test1", "https://example1.com");
+            let golink2 = create_test_golink("go/test2", "https://example2.com");
+
+            storage.create(golink1.clone()).await.unwrap();
+            storage.create(golink2.clone()).await.unwrap();
+
+            let all_golinks = storage.get_all().await.unwrap();
+            assert_eq!(all_golinks.len(), 2);
+        }
+
+        #[tokio::test]
+        async fn test_update_golink() {
+            let storage = HashMapStorage::new();
+            let golink = create_test_golink("go/test", "https://example.com");
+
+            storage.create(golink.clone()).await.unwrap();
+
+            let updated = storage
+                .update(&golink.short_link, "https://updated.com".to_string())
+                .await;
+            assert!(updated.is_ok());
+            let updated_golink = updated.unwrap();
+            assert_eq!(updated_golink.url, "https://updated.com");
+        }
+
+        #[tokio::test]
+        async fn test_update_nonexistent_returns_not_found() {
+            let storage = HashMapStorage::new();
+            let result = storage
+                .update("go/nonexistent", "https://example.com".to_string())
+                .await;
+            assert!(matches!(result, Err(StorageError::NotFound)));
+        }
+
+        #[tokio::test]
+        async fn test_delete_golink() {
+            let storage = HashMapStorage::new();
+            let golink = create_test_golink("go/test", "https://example.com");
+
+            storage.create(golink.clone()).await.unwrap();
+
+            let result = storage.delete(&golink.short_link).await;
+            assert!(result.is_ok());
+
+            // Verify it's deleted
+            let get_result = storage.get(&golink.short_link).await;
+            assert!(matches!(get_result, Err(StorageError::NotFound)));
+        }
+
+        #[tokio::test]
+        async fn test_delete_nonexistent_returns_not_found() {
+            let storage = HashMapStorage::new();
+            let result = storage.delete("go/nonexistent").await;
+            assert!(matches!(result, Err(StorageError::NotFound)));
+        }
+
+        #[tokio::test]
+        async fn test_exists() {
+            let storage = HashMapStorage::new();
+            let golink = create_test_golink("go/test", "https://example.com");
+
+            // Should not exist initially
+            let exists_before = storage.exists(&golink.short_link).await.unwrap();
+            assert!(!exists_before);
+
+            // Create and check exists
+            storage.create(golink.clone()).await.unwrap();
+            let exists_after = storage.exists(&golink.short_link).await.unwrap();
+            assert!(exists_after);
+        }
+
+        #[tokio::test]
+        async fn test_name_taken() {
+            let storage = HashMapStorage::new();
+            for name in ["go/docs", "go/wiki"] {
+                storage
+                    .create(create_test_golink(name, "https://example.com"))
+                    .await
+                    .unwrap();
+            }
+            storage
+                .create_collection(create_test_collection("go/onboarding", &["go/docs"]))
+                .await
+                .unwrap();
+            storage
+                .rename("go/wiki", "go/handbook", true)
+                .await
+                .unwrap();
+
+            for name in ["go/docs", "go/onboarding", "go/wiki", "go/handbook"] {
+                assert!(storage.name_taken(name).await.unwrap(), "{}", name);
+            }
+            assert!(!storage.exists("go/onboarding").await.unwrap());
+            assert!(!storage.exists("go/wiki").await.unwrap());
+            assert!(!storage.name_taken("go/free").await.unwrap());
+        }
+
+        #[tokio::test]
+        async fn test_record_clicks() {
+            let storage = HashMapStorage::new();
+            let golink = create_test_golink("go/test", "https://example.com");
+            storage.create(golink.clone()).await.unwrap();
+
+            let used_at = chrono::Utc::now();
+            let mut clicks = ClickCount::new("go/test");
+            let mut missing = ClickCount::new("go/nonexistent");
+            let source = ClickSource::from_headers(None, Some("curl/8.0"));
+            for _ in 0..3 {
+                clicks.add(used_at, &source);
+            }
+            missing.add(used_at, &source);
+            storage.record_clicks(vec![clicks, missing]).await.unwrap();
+
+            let retrieved = storage.get(&golink.short_link).await.unwrap();
+            assert_eq!(retrieved.clicks, 3);
+            assert_eq!(retrieved.last_used_at, Some(used_at.to_rfc3339()));
+        }
+
+        #[tokio::test]
+        async fn test_click_buckets() {
+            let storage = HashMapStorage::new();
+            storage
+                .create(create_test_golink("go/test", "https://example.com"))
+                .await
+                .unwrap();
+
+            // 2024-01-01T00:00:00Z, two clicks in the first hour and one the next day
+            let day = 1_704_067_200;
+            let mut clicks = ClickCount::new("go/test");
+            let source = ClickSource::from_headers(None, Some("curl/8.0"));
+            for offset in [60, 1800, 86400 + 3600] {
+                clicks.add(chrono::DateTime::from_timestamp(day + offset, 0).unwrap(), &source);
+            }
+            storage.record_clicks(vec![clicks]).await.unwrap();
+
+            let hourly = storage
+                .get_click_buckets("go/test", Granularity::Hour, day, day + 2 * 86400)
+                .await
+                .unwrap();
+            assert_eq!(
+                hourly,
+                vec![
+                    ClickBucket { start: day, clicks: 2 },
+                    ClickBucket { start: day + 86400 + 3600, clicks: 1 },
+                ]
+            );
+
+            let daily = storage
+                .get_click_buckets("go/test", Granularity::Day, day, day + 2 * 86400)
+                .await
+                .unwrap();
+            assert_eq!(
+                daily,
+                vec![
+                    ClickBucket { start: day, clicks: 2 },
+                    ClickBucket { start: day + 86400, clicks: 1 },
+                ]
+            );
+
+            // Pruning hourly buckets leaves the daily rollup alone
+            storage
+                .prune_click_buckets(Granularity::Hour, day + 86400)
+                .await
+                .unwrap();
+            let hourly = storage
+                .get_click_buckets("go/test", Granularity::Hour, day, day + 2 * 86400)
+                .await
+                .unwrap();
+            assert_eq!(hourly.len(), 1);
+            let daily = storage
+                .get_click_buckets("go/test", Granularity::Day, day, day + 2 * 86400)
+                .await
+                .unwrap();
+            assert_eq!(daily.len(), 2);
+        }
+
+        #[tokio::test]
+        async fn test_click_sources() {
+            let storage = HashMapStorage::new();
+            storage
+                .create(create_test_golink("go/test", "https://example.com"))
+                .await
+                .unwrap();
+
+            let at = chrono::DateTime::from_timestamp(1_704_067_200 + 3600, 0).unwrap();
+            let docs = ClickSource::from_headers(
+                Some("https://docs.example.com/page"),
+                Some("Mozilla/5.0 (X11; Linux x86_64)"),
+            );
+            let bot = ClickSource::from_headers(None, Some("Slackbot-LinkExpanding 1.0"));
+            let mut clicks = ClickCount::new("go/test");
+            clicks.add(at, &docs);
+            clicks.add(at, &docs);
+            clicks.add(at, &bot);
+            storage.record_clicks(vec![clicks]).await.unwrap();
+
+            // Bots are excluded from the headline count
+            assert_eq!(storage.get("go/test").await.unwrap().clicks, 2);
+
+            let mut sources = storage
+                .get_click_sources("go/test", 1_704_067_200, 1_704_067_200 + 86400)
+                .await
+                .unwrap();
+            sources.sort_by_key(|source| source.client);
+            assert_eq!(
+                sources,
+                vec![
+                    SourceCount {
+                        referrer: Some("docs.example.com".to_string()),
+                        client: ClientClass::Browser,
+                        clicks: 2,
+                    },
+                    SourceCount {
+                        referrer: None,
+                        client: ClientClass::Bot,
+                        clicks: 1,
+                    },
+                ]
+            );
+
+            storage
+                .prune_click_buckets(Granularity::Day, 1_704_067_200 + 86400)
+                .await
+                .unwrap();
+            let sources = storage
+                .get_click_sources("go/test", 1_704_067_200, 1_704_067_200 + 86400)
+                .await
+                .unwrap();
+            assert!(sources.is_empty());
+        }
+
+        #[tokio::test]
+        async fn test_click_totals() {
+            let storage = HashMapStorage::new();
+            let day = 1_704_067_200;
+            let source = ClickSource::from_headers(None, Some("curl/8.0"));
+            let mut batch = Vec::new();
+            for (name, offsets) in [("go/a", vec![0, 3600, 86400]), ("go/b", vec![7200])] {
+                storage
+                    .create(create_test_golink(name, "https://example.com"))
+                    .await
+                    .unwrap();
+                let mut clicks = ClickCount::new(name);
+                for offset in offsets {
+                    clicks.add(chrono::DateTime::from_timestamp(day + offset, 0).unwrap(), &source);
+                }
+                batch.push(clicks);
+            }
+            storage.record_clicks(batch).await.unwrap();
+
+            let totals = storage
+                .get_click_totals(Granularity::Day, day, day + 86400)
+                .await
+                .unwrap();
+            assert_eq!(totals.len(), 2);
+            assert_eq!(totals["go/a"], 2);
+            assert_eq!(totals["go/b"], 1);
+
+            let totals = storage
+                .get_click_totals(Granularity::Hour, day + 3600, day + 2 * 86400)
+                .await
+                .unwrap();
+            assert_eq!(totals["go/a"], 2);
+            assert_eq!(totals["go/b"], 1);
+        }
+
+        #[tokio::test]
+        async fn test_get_stale_and_set_deprecated() {
+            let storage = HashMapStorage::new();
+            let old = "2024-01-01T00:00:00+00:00".to_string();
+            let recent = "2024-06-01T00:00:00+00:00".to_string();
+            let cutoff = "2024-03-01T00:00:00+00:00";
+
+            let golinks = [
+                ("go/unused", old.clone(), None),
+                ("go/clicked", old.clone(), Some(recent.clone())),
+                ("go/edited", recent.clone(), None),
+                ("go/forgotten", old.clone(), Some(old.clone())),
+            ];
+            for (name, updated_at, last_used_at) in golinks {
+                let mut golink = create_test_golink(name, "https://example.com");
+                golink.created_at = old.clone();
+                golink.updated_at = updated_at;
+                golink.last_used_at = last_used_at;
+                storage.create(golink).await.unwrap();
+            }
+
+            let stale = storage.get_stale(cutoff).await.unwrap();
+            let names: Vec<&str> = stale.iter().map(|g| g.short_link.as_str()).collect();
+            assert_eq!(names, vec!["go/forgotten", "go/unused"]);
+
+            let found = storage
+                .set_deprecated(&["go/unused".to_string(), "go/nonexistent".to_string()], true)
+                .await
+                .unwrap();
+            assert_eq!(found, 1);
+            let golink = storage.get("go/unused").await.unwrap();
+            assert!(golink.deprecated);
+            // Deprecating isn't a modification, the golink stays stale, but it
+            // changes what the golink returns so its version moves on
+            assert_eq!(golink.updated_at, old);
+            let untouched = storage.get("go/forgotten").await.unwrap();
+            assert_eq!(golink.version, untouched.version + 1);
+        }
+
+        #[tokio::test]
+        async fn test_favorites_per_user() {
+            let storage = HashMapStorage::new();
+            for name in ["go/docs", "go/wiki"] {
+                storage
+                    .create(create_test_golink(name, "https://example.com"))
+                    .await
+                    .unwrap();
+            }
+
+            let first = storage.add_favorite("alice", "go/docs").await.unwrap();
+            assert_eq!(first.golink.short_link, "go/docs");
+            // Starring again keeps the original timestamp
+            let again = storage.add_favorite("alice", "go/docs").await.unwrap();
+            assert_eq!(again.favorited_at, first.favorited_at);
+            storage.add_favorite("bob", "go/wiki").await.unwrap();
+
+            assert!(matches!(
+                storage.add_favorite("alice", "go/nonexistent").await,
+                Err(StorageError::NotFound)
+            ));
+
+            let favorites = storage.get_favorites("alice").await.unwrap();
+            assert_eq!(favorites.len(), 1);
+            assert_eq!(favorites[0].golink.short_link, "go/docs");
+            assert!(storage.get_favorites("carol").await.unwrap().is_empty());
+
+            storage.remove_favorite("alice", "go/docs").await.unwrap();
+            assert!(storage.get_favorites("alice").await.unwrap().is_empty());
+            assert!(matches!(
+                storage.remove_favorite("alice", "go/docs").await,
+                Err(StorageError::NotFound)
+            ));
+
+            // Deleting a golink removes it from everyone's favorites
+            storage.delete("go/wiki").await.unwrap();
+            assert!(storage.get_favorites("bob").await.unwrap().is_empty());
+        }
+
+        #[tokio::test]
+        async fn test_collection_crud() {
+            let storage = HashMapStorage::new();
+            for name in ["go/docs", "go/wiki", "go/chat"] {
+                storage
+                    .create(create_test_golink(name, "https://example.com"))
+                    .await
+                    .unwrap();
+            }
+
+            let collection = create_test_collection("go/onboarding", &["go/wiki", "go/docs"]);
+            storage.create_collection(collection).await.unwrap();
+
+            // Collections and golinks share one namespace
+            assert!(matches!(
+                storage
+                    .create_collection(create_test_collection("go/onboarding", &[]))
+                    .await,
+                Err(StorageError::AlreadyExists)
+            ));
+            assert!(matches!(
+                storage
+                    .create_collection(create_test_collection("go/docs", &[]))
+                    .await,
+                Err(StorageError::AlreadyExists)
+            ));
+            assert!(matches!(
+                storage
+                    .create(create_test_golink("go/onboarding", "https://example.com"))
+                    .await,
+                Err(StorageError::AlreadyExists)
+            ));
+
+            let collection = storage.get_collection("go/onboarding").await.unwrap();
+            assert_eq!(collection.links, vec!["go/wiki", "go/docs"]);
+
+            let updated = storage
+                .update_collection(
+                    "go/onboarding",
+                    "Engineering onboarding".to_string(),
+                    Some("Start here".to_string()),
+                    vec!["go/chat".to_string(), "go/docs".to_string()],
+                )
+                .await
+                .unwrap();
+            assert_eq!(updated.title, "Engineering onboarding");
+            assert_eq!(updated.links, vec!["go/chat", "go/docs"]);
+
+            // Deleting a golink removes it from collections
+            storage.delete("go/chat").await.unwrap();
+            let collections = storage.get_collections().await.unwrap();
+            assert_eq!(collections.len(), 1);
+            assert_eq!(collections[0].links, vec!["go/docs"]);
+            assert_eq!(collections[0].description.as_deref(), Some("Start here"));
+
+            storage.delete_collection("go/onboarding").await.unwrap();
+            assert!(matches!(
+                storage.get_collection("go/onboarding").await,
+                Err(StorageError::NotFound)
+            ));
+            assert!(matches!(
+                storage.delete_collection("go/onboarding").await,
+                Err(StorageError::NotFound)
+            ));
+        }
+
+        #[tokio::test]
+        async fn test_apply_changes() {
+            let storage = HashMapStorage::new();
+            let mut golink = create_test_golink("go/test", "https://example.com");
+            golink.owner = Some("alice".to_string());
+            golink.updated_at = "2024-01-01T00:00:00+00:00".to_string();
+            storage.create(golink).await.unwrap();
+
+            let changes = GolinkChanges {
+                url: Some("https://new.example.com".to_string()),
+                owner: Some(None),
+                deprecated: Some(true),
+                ..Default::default()
+            };
+            let updated = storage.apply_changes("go/test", changes).await.unwrap();
+            assert_eq!(updated.url, "https://new.example.com");
+            assert_eq!(updated.owner, None);
+            assert!(updated.deprecated);
+            assert!(!updated.locked);
+            assert!(updated.updated_at.as_str() > "2024-01-01T00:00:00+00:00");
+
+            let golink = storage.get("go/test").await.unwrap();
+            assert_eq!(golink.url, "https://new.example.com");
+            assert_eq!(golink.owner, None);
+
+            assert!(matches!(
+                storage
+                    .apply_changes("go/nonexistent", GolinkChanges::default())
+                    .await,
+                Err(StorageError::NotFound)
+            ));
+        }
+
+        #[tokio::test]
+        async fn test_rename_if() {
+            let storage = HashMapStorage::new();
+            storage
+                .create(create_test_golink("go/old", "https://example.com"))
+                .await
+                .unwrap();
+            let version = storage.get("go/old").await.unwrap().version;
+            storage.set_locked("go/old", true).await.unwrap();
+
+            assert!(matches!(
+                storage.rename_if("go/old", "go/new", false, version).await,
+                Err(StorageError::VersionMismatch)
+            ));
+            assert!(matches!(
+                storage.rename_if("go/missing", "go/new", false, version).await,
+                Err(StorageError::NotFound)
+            ));
+            assert!(storage.exists("go/old").await.unwrap());
+
+            let current = storage.get("go/old").await.unwrap().version;
+            let renamed = storage.rename_if("go/old", "go/new", false, current).await.unwrap();
+            assert_eq!(renamed.version, current + 1);
+        }
+
+        #[tokio::test]
+        async fn test_rename_keeps_history() {
+            let storage = HashMapStorage::new();
+            storage
+                .create(create_test_golink("go/oldteam-wiki", "https://wiki.example.com"))
+                .await
+                .unwrap();
+            storage
+                .create(create_test_golink("go/other", "https://example.com"))
+                .await
+                .unwrap();
+            let original = storage.get("go/oldteam-wiki").await.unwrap();
+
+            let mut click = ClickCount::new("go/oldteam-wiki");
+            click.add(chrono::Utc::now(), &ClickSource::from_headers(None, None));
+            storage.record_clicks(vec![click]).await.unwrap();
+            storage.add_favorite("alice", "go/oldteam-wiki").await.unwrap();
+            storage
+                .create_collection(create_test_collection("go/onboarding", &["go/oldteam-wiki"]))
+                .await
+                .unwrap();
+
+            // The new name must be free
+            assert!(matches!(
+                storage.rename("go/oldteam-wiki", "go/other", true).await,
+                Err(StorageError::AlreadyExists)
+            ));
+            assert!(matches!(
+                storage.rename("go/missing", "go/new", true).await,
+                Err(StorageError::NotFound)
+            ));
+
+            let renamed = storage
+                .rename("go/oldteam-wiki", "go/platform-wiki", true)
+                .await
+                .unwrap();
+            assert_eq!(renamed.id, original.id);
+            assert_eq!(renamed.created_at, original.created_at);
+            assert_eq!(renamed.short_link, "go/platform-wiki");
+            assert_eq!(renamed.clicks, 1);
+
+            assert!(matches!(
+                storage.get("go/oldteam-wiki").await,
+                Err(StorageError::NotFound)
+            ));
+            assert_eq!(
+                storage.resolve_alias("go/oldteam-wiki").await.unwrap(),
+                "go/platform-wiki"
+            );
+            let buckets = storage
+                .get_click_buckets("go/platform-wiki", Granularity::Day, 0, i64::MAX)
+                .await
+                .unwrap();
+            assert_eq!(buckets.len(), 1);
+            let favorites = storage.get_favorites("alice").await.unwrap();
+            assert_eq!(favorites[0].golink.short_link, "go/platform-wiki");
+            let collection = storage.get_collection("go/onboarding").await.unwrap();
+            assert_eq!(collection.links, vec!["go/platform-wiki"]);
+
+            // Aliases take up their name
+            assert!(matches!(
+                storage
+                    .create(create_test_golink("go/oldteam-wiki", "https://example.com"))
+                    .await,
+                Err(StorageError::AlreadyExists)
+            ));
+
+            // Renaming again follows existing aliases, renaming back frees the alias
+            storage
+                .rename("go/platform-wiki", "go/oldteam-wiki", false)
+                .await
+                .unwrap();
+            assert!(matches!(
+                storage.resolve_alias("go/oldteam-wiki").await,
+                Err(StorageError::NotFound)
+            ));
+            assert!(matches!(
+                storage.resolve_alias("go/platform-wiki").await,
+                Err(StorageError::NotFound)
+            ));
+
+            // Deleting a golink drops its aliases
+            storage
+                .rename("go/oldteam-wiki", "go/platform-wiki", true)
+                .await
+                .unwrap();
+            storage.delete("go/platform-wiki").await.unwrap();
+            assert!(matches!(
+                storage.resolve_alias("go/oldteam-wiki").await,
+                Err(StorageError::NotFound)
+            ));
+        }
+
+        #[tokio::test]
+        async fn test_apply_batch() {
+            let storage = HashMapStorage::new();
+            storage
+                .create(create_test_golink("go/existing", "https://example.com"))
+                .await
+                .unwrap();
+
+            let operations = vec![
+                BatchOperation::Create(create_test_golink("go/new", "https://new.example.com")),
+                BatchOperation::Update {
+                    short_link: "go/existing".to_string(),
+                    url: "https://updated.example.com".to_string(),
+                    unlocked_only: false,
+                },
+                BatchOperation::Delete {
+                    short_link: "go/missing".to_string(),
+                    unlocked_only: false,
+                },
+            ];
+
+            // Atomic batches with a failure write nothing
+            let results = storage.apply_batch(operations.clone(), true).await.unwrap();
+            assert!(matches!(results[0], Ok(Some(_))));
+            assert!(matches!(results[1], Ok(Some(_))));
+            assert!(matches!(results[2], Err(StorageError::NotFound)));
+            assert!(!storage.exists("go/new").await.unwrap());
+            assert_eq!(
+                storage.get("go/existing").await.unwrap().url,
+                "https://example.com"
+            );
+
+            // Otherwise the successful operations are kept
+            let results = storage.apply_batch(operations, false).await.unwrap();
+            assert!(results[0].is_ok() && results[1].is_ok() && results[2].is_err());
+            assert!(storage.exists("go/new").await.unwrap());
+            assert_eq!(
+                storage.get("go/existing").await.unwrap().url,
+                "https://updated.example.com"
+            );
+
+            // Later operations see the effect of earlier ones
+            let operations = vec![
+                BatchOperation::Delete {
+                    short_link: "go/new".to_string(),
+                    unlocked_only: false,
+                },
+                BatchOperation::Create(create_test_golink("go/new", "https://again.example.com")),
+                BatchOperation::Create(create_test_golink("go/existing", "https://example.com")),
+            ];
+            let results = storage.apply_batch(operations, false).await.unwrap();
+            assert!(matches!(results[0], Ok(None)));
+            assert!(results[1].is_ok());
+            assert!(matches!(results[2], Err(StorageError::AlreadyExists)));
+            assert_eq!(
+                storage.get("go/new").await.unwrap().url,
+                "https://again.example.com"
+            );
+        }
+
+        #[tokio::test]
+        async fn test_apply_batch_unlocked_only() {
+            let storage = HashMapStorage::new();
+            storage
+                .create(create_test_golink("go/locked", "https://example.com"))
+                .await
+                .unwrap();
+            storage.set_locked("go/locked", true).await.unwrap();
+
+            let operations = vec![
+                BatchOperation::Update {
+                    short_link: "go/locked".to_string(),
+                    url: "https://updated.example.com".to_string(),
+                    unlocked_only: true,
+                },
+                BatchOperation::Delete {
+                    short_link: "go/locked".to_string(),
+                    unlocked_only: true,
+                },
+                BatchOperation::Delete {
+                    short_link: "go/missing".to_string(),
+                    unlocked_only: true,
+                },
+            ];
+            let results = storage.apply_batch(operations, false).await.unwrap();
+            assert!(matches!(results[0], Err(StorageError::Locked)));
+            assert!(matches!(results[1], Err(StorageError::Locked)));
+            assert!(matches!(results[2], Err(StorageError::NotFound)));
+            assert_eq!(
+                storage.get("go/locked").await.unwrap().url,
+                "https://example.com"
+            );
+
+            let operations = vec![BatchOperation::Delete {
+                short_link: "go/locked".to_string(),
+                unlocked_only: false,
+            }];
+            let results = storage.apply_batch(operations, false).await.unwrap();
+            assert!(matches!(results[0], Ok(None)));
+        }
+
+        #[tokio::test]
+        async fn test_apply_batch_replace() {
+            let storage = HashMapStorage::new();
+            storage
+                .create(create_test_golink("go/existing", "https://example.com"))
+                .await
+                .unwrap();
+            storage
+                .create_collection(create_test_collection("go/onboarding", &[]))
+                .await
+                .unwrap();
+
+            let mut replacement = create_test_golink("go/existing", "https://replaced.example.com");
+            replacement.owner = Some("alice".to_string());
+            replacement.clicks = 7;
+            let operations = vec![
+                BatchOperation::Replace(replacement.clone()),
+                BatchOperation::Replace(create_test_golink("go/new", "https://new.example.com")),
+                BatchOperation::Replace(create_test_golink("go/onboarding", "https://example.com")),
+            ];
+            let results = storage.apply_batch(operations, false).await.unwrap();
+            assert!(results[0].is_ok() && results[1].is_ok());
+            assert!(matches!(results[2], Err(StorageError::AlreadyExists)));
+
+            let golink = storage.get("go/existing").await.unwrap();
+            assert_eq!(golink.id, replacement.id);
+            assert_eq!(golink.url, "https://replaced.example.com");
+            assert_eq!(golink.owner.as_deref(), Some("alice"));
+            assert_eq!(golink.clicks, 7);
+            assert!(storage.exists("go/new").await.unwrap());
+        }
+
+        #[tokio::test]
+        async fn test_tags_are_persisted() {
+            let storage = HashMapStorage::new();
+            let mut golink = create_test_golink("go/tagged", "https://example.com");
+            golink.tags = vec!["docs".to_string(), "on-call".to_string()];
+            storage.create(golink).await.unwrap();
+
+            let golink = storage.get("go/tagged").await.unwrap();
+            assert_eq!(golink.tags, vec!["docs", "on-call"]);
+
+            let changes = GolinkChanges {
+                tags: Some(vec!["ops".to_string()]),
+                ..Default::default()
+            };
+            let updated = storage.apply_changes("go/tagged", changes).await.unwrap();
+            assert_eq!(updated.tags, vec!["ops"]);
+            assert_eq!(storage.get_all().await.unwrap()[0].tags, vec!["ops"]);
+        }
+
+        #[tokio::test]
+        async fn test_search() {
+            let storage = HashMapStorage::new();
+            let mut grafana = create_test_golink("go/grafana", "https://grafana.example.com/d/abc");
+            grafana.tags = vec!["dashboards".to_string()];
+            storage.create(grafana).await.unwrap();
+            let mut oncall = create_test_golink("go/oncall", "https://pager.example.com");
+            oncall.description = Some("Grafana alerts for the on-call rotation".to_string());
+            storage.create(oncall).await.unwrap();
+            storage
+                .create(create_test_golink("go/wiki", "https://wiki.example.com"))
+                .await
+                .unwrap();
+
+            let hits = storage.search("grafana", 10).await.unwrap();
+            let names: Vec<&str> = hits.iter().map(|hit| hit.golink.short_link.as_str()).collect();
+            assert_eq!(names, vec!["go/grafana", "go/oncall"]);
+            assert!(hits[0].score > hits[1].score);
+            assert_eq!(hits[0].highlight.field, "short_link");
+            assert_eq!(hits[0].highlight.fragment, "go/<mark>grafana</mark>");
+            assert_eq!(hits[1].highlight.field, "description");
+
+            // Prefixes of every word have to match
+            assert_eq!(storage.search("dash graf", 10).await.unwrap().len(), 1);
+            assert!(storage.search("wiki grafana", 10).await.unwrap().is_empty());
+            assert_eq!(storage.search("example", 2).await.unwrap().len(), 2);
+
+            // The index follows updates, renames and deletes
+            storage
+                .update("go/wiki", "https://confluence.example.com".to_string())
+                .await
+                .unwrap();
+            assert_eq!(storage.search("confluence", 10).await.unwrap().len(), 1);
+            assert_eq!(storage.search("wiki.example", 10).await.unwrap().len(), 1);
+            storage.rename("go/wiki", "go/kb", false).await.unwrap();
+            let hits = storage.search("kb", 10).await.unwrap();
+            assert_eq!(hits[0].golink.short_link, "go/kb");
+            storage.delete("go/kb").await.unwrap();
+            assert!(storage.search("confluence", 10).await.unwrap().is_empty());
+        }
+
+        #[tokio::test]
+        async fn test_list_sorts_and_filters() {
+            let storage = HashMapStorage::new();
+            let fixtures = [
+                ("go/beta", "https://Docs.example.com/beta", "2024-01-02T00:00:00+00:00", 5),
+                ("go/alpha", "https://docs.example.com:8080/alpha", "2024-01-04T00:00:00+00:00", 5),
+                ("go/gamma", "https://example.com/?next=https://docs.example.com/", "2024-01-03T00:00:00+00:00", 1),
+                ("go/delta", "https://docs.example.community/50%_off", "2024-01-02T00:00:00+00:00", 9),
+            ];
+            for (name, url, created_at, clicks) in fixtures {
+                let mut golink = create_test_golink(name, url);
+                golink.created_at = created_at.to_string();
+                golink.clicks = clicks;
+                storage.create(golink).await.unwrap();
+            }
+            let names = |golinks: Vec<Golink>| -> Vec<String> {
+                golinks.into_iter().map(|g| g.short_link).collect()
+            };
+
+            let query = ListQuery {
+                sort: GolinkSort::ShortLink,
+                ..Default::default()
+            };
+            let (golinks, total) = storage.list(&query, 1, Some(2)).await.unwrap();
+            assert_eq!(total, 4);
+            assert_eq!(names(golinks), vec!["go/beta", "go/delta"]);
+
+            // Equal clicks fall back to creation time, in the requested direction
+            let query = ListQuery {
+                sort: GolinkSort::Clicks,
+                direction: Some(SortDirection::Asc),
+                ..Default::default()
+            };
+            let (golinks, _) = storage.list(&query, 0, None).await.unwrap();
+            assert_eq!(names(golinks), vec!["go/gamma", "go/beta", "go/alpha", "go/delta"]);
+
+            // Equal creation times fall back to the name
+            let (golinks, _) = storage.list(&ListQuery::default(), 0, None).await.unwrap();
+            assert_eq!(names(golinks), vec!["go/alpha", "go/gamma", "go/delta", "go/beta"]);
+
+            let mut query = ListQuery::default();
+            query.filter.host = Some("docs.example.com".to_string());
+            let (golinks, total) = storage.list(&query, 0, None).await.unwrap();
+            assert_eq!(total, 2);
+            assert_eq!(names(golinks), vec!["go/alpha", "go/beta"]);
+
+            let mut query = ListQuery::default();
+            query.filter.url_contains = Some("%_OFF".to_string());
+            let (golinks, _) = storage.list(&query, 0, None).await.unwrap();
+            assert_eq!(names(golinks), vec!["go/delta"]);
+
+            let mut query = ListQuery::default();
+            query.filter.url_contains = Some("_".to_string());
+            let (golinks, _) = storage.list(&query, 0, None).await.unwrap();
+            assert_eq!(names(golinks), vec!["go/delta"]);
+
+            let mut query = ListQuery::default();
+            query.filter.created_after = Some("2024-01-02T00:00:00+00:00".to_string());
+            query.filter.created_before = Some("2024-01-04T00:00:00+00:00".to_string());
+            let (golinks, total) = storage.list(&query, 0, Some(1)).await.unwrap();
+            assert_eq!(total, 1);
+            assert_eq!(names(golinks), vec!["go/gamma"]);
+        }
+
+        #[tokio::test]
+        async fn test_list_after_cursor() {
+            let storage = HashMapStorage::new();
+            let fixtures = [
+                ("go/a", "2024-01-01T00:00:00+00:00", 3, None),
+                ("go/b", "2024-01-02T00:00:00+00:00", 3, Some("2024-02-01T00:00:00+00:00")),
+                ("go/c", "2024-01-02T00:00:00+00:00", 0, None),
+                ("go/d", "2024-01-03T00:00:00+00:00", 7, Some("2024-02-01T00:00:00+00:00")),
+                ("go/e", "2024-01-04T00:00:00+00:00", 1, Some("2024-02-03T00:00:00+00:00")),
+            ];
+            for (name, created_at, clicks, last_used_at) in fixtures {
+                let mut golink = create_test_golink(name, "https://example.com");
+                golink.created_at = created_at.to_string();
+                golink.updated_at = created_at.to_string();
+                golink.clicks = clicks;
+                golink.last_used_at = last_used_at.map(String::from);
+                storage.create(golink).await.unwrap();
+            }
+
+            // Walking two at a time visits every golink once, in listing order
+            let sorts = [
+                GolinkSort::ShortLink,
+                GolinkSort::CreatedAt,
+                GolinkSort::UpdatedAt,
+                GolinkSort::Clicks,
+                GolinkSort::LastUsedAt,
+            ];
+            for sort in sorts {
+                for direction in [SortDirection::Asc, SortDirection::Desc] {
+                    let mut query = ListQuery {
+                        sort,
+                        direction: Some(direction),
+                        ..Default::default()
+                    };
+                    let (all, _) = storage.list(&query, 0, None).await.unwrap();
+                    let all: Vec<String> = all.into_iter().map(|g| g.short_link).collect();
+                    let mut walked = Vec::new();
+                    loop {
+                        let (page, total) = storage.list(&query, 0, Some(2)).await.unwrap();
+                        assert_eq!(total, 5);
+                        let Some(last) = page.last() else { break };
+                        query.after = Some(Cursor::decode(&Cursor::after(&query, last).encode()).unwrap());
+                        walked.extend(page.into_iter().map(|g| g.short_link));
+                    }
+                    assert_eq!(walked, all, "{:?} {:?}", sort, direction);
+                }
+            }
+
+            // Golinks created before the cursor position don't shift later pages
+            let mut query = ListQuery {
+                sort: GolinkSort::ShortLink,
+                ..Default::default()
+            };
+            let (page, _) = storage.list(&query, 0, Some(2)).await.unwrap();
+            query.after = Some(Cursor::after(&query, page.last().unwrap()));
+            storage
+                .create(create_test_golink("go/aa", "https://example.com"))
+                .await
+                .unwrap();
+            let (page, total) = storage.list(&query, 0, Some(2)).await.unwrap();
+            assert_eq!(total, 6);
+            let names: Vec<&str> = page.iter().map(|g| g.short_link.as_str()).collect();
+            assert_eq!(names, vec!["go/c", "go/d"]);
+        }
+
+        #[tokio::test]
+        async fn test_compare_and_swap() {
+            let storage = HashMapStorage::new();
+            let mut golink = create_test_golink("go/oncall", "https://example.com/oncall");
+            golink.version = 1;
+            storage.create(golink).await.unwrap();
+
+            // Every edit bumps the version
+            let golink = storage
+                .update("go/oncall", "https://example.com/pager".to_string())
+                .await
+                .unwrap();
+            assert_eq!(golink.version, 2);
+            let golink = storage.set_locked("go/oncall", false).await.unwrap();
+            assert_eq!(golink.version, 3);
+
+            let changes = GolinkChanges {
+                url: Some("https://example.com/stale".to_string()),
+                ..Default::default()
+            };
+            let result = storage.apply_changes_if("go/oncall", 2, changes.clone()).await;
+            assert!(matches!(result, Err(StorageError::VersionMismatch)));
+            let golink = storage.get("go/oncall").await.unwrap();
+            assert_eq!(golink.url, "https://example.com/pager");
+            assert_eq!(golink.version, 3);
+
+            let golink = storage.apply_changes_if("go/oncall", 3, changes.clone()).await.unwrap();
+            assert_eq!(golink.url, "https://example.com/stale");
+            assert_eq!(golink.version, 4);
+            let result = storage.apply_changes_if("go/missing", 1, changes).await;
+            assert!(matches!(result, Err(StorageError::NotFound)));
+
+            let result = storage.delete_if("go/oncall", 3).await;
+            assert!(matches!(result, Err(StorageError::VersionMismatch)));
+            assert!(storage.exists("go/oncall").await.unwrap());
+            storage.delete_if("go/oncall", 4).await.unwrap();
+            assert!(!storage.exists("go/oncall").await.unwrap());
+            let result = storage.delete_if("go/oncall", 4).await;
+            assert!(matches!(result, Err(StorageError::NotFound)));
+        }
+
+        #[tokio::test]
+        async fn test_revision() {
+            let storage = HashMapStorage::new();
+            let start = storage.revision().await.unwrap();
+
+            storage
+                .create(create_test_golink("go/test", "https://example.com"))
+                .await
+                .unwrap();
+            let created = storage.revision().await.unwrap();
+            assert_eq!(created.instance, start.instance);
+            assert!(created.counter > start.counter);
+            assert!(created.changed_at >= start.changed_at);
+            assert_ne!(created.etag(), start.etag());
+
+            // Reads and no-op writes leave it alone
+            storage.get("go/test").await.unwrap();
+            storage.get_all().await.unwrap();
+            assert!(storage.delete("go/missing").await.is_err());
+            assert_eq!(storage.revision().await.unwrap().etag(), created.etag());
+
+            // Clicks change the list, so they count too
+            let mut clicks = ClickCount::new("go/test");
+            clicks.add(chrono::Utc::now(), &ClickSource::from_headers(None, Some("curl/8.0")));
+            storage.record_clicks(vec![clicks]).await.unwrap();
+            let clicked = storage.revision().await.unwrap();
+            assert!(clicked.counter > created.counter);
+
+            storage.delete("go/test").await.unwrap();
+            let deleted = storage.revision().await.unwrap();
+            assert_eq!(deleted.instance, start.instance);
+            assert!(deleted.counter > clicked.counter);
+        }
+
+        #[tokio::test]
+        async fn test_suggest() {
+            let storage = HashMapStorage::new();
+            for (name, clicks) in [
+                ("go/deploy", 5),
+                ("go/design", 9),
+                ("go/de", 0),
+                ("go/docs", 7),
+                ("go/Dev", 3),
+                ("go/dex", 5),
+            ] {
+                let mut golink = create_test_golink(name, "https://example.com");
+                golink.clicks = clicks;
+                storage.create(golink).await.unwrap();
+            }
+            let names = |golinks: Vec<Golink>| -> Vec<String> {
+                golinks.into_iter().map(|g| g.short_link).collect()
+            };
+
+            // Most clicked first, ties by name
+            assert_eq!(
+                names(storage.suggest("go/de", 10).await.unwrap()),
+                vec!["go/design", "go/deploy", "go/dex", "go/de"]
+            );
+            assert_eq!(
+                names(storage.suggest("go/de", 2).await.unwrap()),
+                vec!["go/design", "go/deploy"]
+            );
+            assert_eq!(names(storage.suggest("go/D", 10).await.unwrap()), vec!["go/Dev"]);
+            assert_eq!(storage.suggest("", 10).await.unwrap().len(), 6);
+            assert!(storage.suggest("go/x", 10).await.unwrap().is_empty());
+            assert!(storage.suggest("go/de%", 10).await.unwrap().is_empty());
+
+            // Renamed and deleted golinks move along
+            storage.rename("go/design", "go/ux", false).await.unwrap();
+            storage.delete("go/deploy").await.unwrap();
+            assert_eq!(
+                names(storage.suggest("go/de", 10).await.unwrap()),
+                vec!["go/dex", "go/de"]
+            );
+        }
+
+        #[tokio::test]
+        async fn test_get_paginated_sorted_by_clicks() {
+            let storage = HashMapStorage::new();
+            for (name, clicks) in [("go/rare", 1), ("go/popular", 10), ("go/unused", 0)] {
+                let mut golink = create_test_golink(name, "https://example.com");
+                golink.clicks = clicks;
+                storage.create(golink).await.unwrap();
+            }
+
+            let (golinks, total) = storage.get_paginated(1, 2, GolinkSort::Clicks).await.unwrap();
+            assert_eq!(total, 3);
+            let names: Vec<&str> = golinks.iter().map(|g| g.short_link.as_str()).collect();
+            assert_eq!(names, vec!["go/popular", "go/rare"]);
         }
 
-        // Fetch the updated record
-        self.get(short_link).await
-    }
+        #[tokio::test]
+        async fn test_set_locked() {
+            let storage = HashMapStorage::new();
+            let golink = create_test_golink("go/test", "https://example.com");
 
-    async fn delete(&self, short_link: &str) -> StorageResult<()> {
-        let result = sqlx::query("DELETE FROM golinks WHERE short_link = ?")
-            .bind(short_link)
-            .execute(&self.pool)
-            .await
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
+            storage.create(golink.clone()).await.unwrap();
+            assert!(!storage.get(&golink.short_link).await.unwrap().locked);
 
-        if result.rows_affected() == 0 {
-            return Err(StorageError::NotFound);
-        }
+            let locked = storage.set_locked(&golink.short_link, true).await.unwrap();
+            assert!(locked.locked);
+            assert!(storage.get(&golink.short_link).await.unwrap().locked);
 
-        Ok(())
+            let result = storage.set_locked("go/nonexistent", true).await;
+            assert!(matches!(result, Err(StorageError::NotFound)));
+        }
     }
 
-    async fn exists(&self, short_link: &str) -> StorageResult<bool> {
-        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM golinks WHERE short_link = ?")
-            .bind(short_link)
-            .fetch_one(&self.pool)
-            .await
-            .map_err(|e| StorageError::DatabaseError(e.to_string()))?;
-
-        Ok(count > 0)
-    }
-}
+    #[test]
+    fn test_click_source_from_headers() {
+        let source = ClickSource::from_headers(
+            Some("https://user@Wiki.Example.com:8443/page?q=1"),
+            Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15"),
+        );
+        assert_eq!(source.referrer.as_deref(), Some("wiki.example.com"));
+        assert_eq!(source.client, ClientClass::Browser);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::service::Golink;
-    use tempfile::NamedTempFile;
+        let source = ClickSource::from_headers(None, Some("curl/8.4.0"));
+        assert_eq!(source.referrer, None);
+        assert_eq!(source.client, ClientClass::Cli);
 
-    fn create_test_golink(short_link: &str, url: &str) -> Golink {
-        Golink {
-            id: uuid::Uuid::new_v4().to_string(),
-            short_link: short_link.to_string(),
-            url: url.to_string(),
-            created_at: chrono::Utc::now().to_rfc3339(),
-        }
+        let source = ClickSource::from_headers(
+            Some("not a url"),
+            Some("Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)"),
+        );
+        assert_eq!(source.client, ClientClass::Bot);
+        assert_eq!(ClickSource::from_headers(None, None).client, ClientClass::Cli);
     }
 
-    mod hashmap_storage_tests {
+    #[cfg(feature = "sqlite-tests")]
+    mod sqlite_storage_tests {
         use super::*;
 
+        // The temp file is returned alongside the storage so the database
+        // isn't deleted while the pool still opens new connections to it
+        async fn create_test_sqlite_storage() -> (SqliteStorage, NamedTempFile) {
+            let temp_file = NamedTempFile::new().unwrap();
+            let db_path = temp_file.path().to_str().unwrap();
+            // Use file:// prefix for SQLite URLs in tests
+            let db_url = format!("sqlite://{}?mode=rwc", db_path);
+            (SqliteStorage::new(&db_url).await.unwrap(), temp_file)
+        }
+
         #[tokio::test]
         async fn test_create_and_get_golink() {
-            let storage = HashMapStorage::new();
+            let (storage, _db) = create_test_sqlite_storage().await;
             let golink = create_test_golink("go/test", "https://example.com");
 
             // Test create
@@ -309,7 +4056,7 @@ mod tests {
 
         #[tokio::test]
         async fn test_create_duplicate_returns_error() {
-            let storage = HashMapStorage::new();
+            let (storage, _db) = create_test_sqlite_storage().await;
             let golink = create_test_golink("go/test", "https://example.com");
 
             // Create first time
@@ -323,14 +4070,14 @@ mod tests {
 
         #[tokio::test]
         async fn test_get_nonexistent_returns_not_found() {
-            let storage = HashMapStorage::new();
+            let (storage, _db) = create_test_sqlite_storage().await;
             let result = storage.get("go/nonexistent").await;
             assert!(matches!(result, Err(StorageError::NotFound)));
         }
 
         #[tokio::test]
         async fn test_get_all_golinks() {
-            let storage = HashMapStorage::new();
+            let (storage, _db) = create_test_sqlite_storage().await;
             let golink1 = create_test_golink("go/test1", "https://example1.com");
             let golink2 = create_test_golink("go/test2", "https://example2.com");
 
@@ -343,7 +4090,7 @@ mod tests {
 
         #[tokio::test]
         async fn test_update_golink() {
-            let storage = HashMapStorage::new();
+            let (storage, _db) = create_test_sqlite_storage().await;
             let golink = create_test_golink("go/test", "https://example.com");
 
             storage.create(golink.clone()).await.unwrap();
@@ -358,7 +4105,7 @@ mod tests {
 
         #[tokio::test]
         async fn test_update_nonexistent_returns_not_found() {
-            let storage = HashMapStorage::new();
+            let (storage, _db) = create_test_sqlite_storage().await;
             let result = storage
                 .update("go/nonexistent", "https://example.com".to_string())
                 .await;
@@ -367,7 +4114,7 @@ mod tests {
 
         #[tokio::test]
         async fn test_delete_golink() {
-            let storage = HashMapStorage::new();
+            let (storage, _db) = create_test_sqlite_storage().await;
             let golink = create_test_golink("go/test", "https://example.com");
 
             storage.create(golink.clone()).await.unwrap();
@@ -382,14 +4129,14 @@ mod tests {
 
         #[tokio::test]
         async fn test_delete_nonexistent_returns_not_found() {
-            let storage = HashMapStorage::new();
+            let (storage, _db) = create_test_sqlite_storage().await;
             let result = storage.delete("go/nonexistent").await;
             assert!(matches!(result, Err(StorageError::NotFound)));
         }
 
         #[tokio::test]
         async fn test_exists() {
-            let storage = HashMapStorage::new();
+            let (storage, _db) = create_test_sqlite_storage().await;
             let golink = create_test_golink("go/test", "https://example.com");
 
             // Should not exist initially
@@ -401,130 +4148,986 @@ mod tests {
             let exists_after = storage.exists(&golink.short_link).await.unwrap();
             assert!(exists_after);
         }
-    }
 
-    #[cfg(feature = "sqlite-tests")]
-    mod sqlite_storage_tests {
-        use super::*;
+        #[tokio::test]
+        async fn test_name_taken() {
+            let (storage, _db) = create_test_sqlite_storage().await;
+            for name in ["go/docs", "go/wiki"] {
+                storage
+                    .create(create_test_golink(name, "https://example.com"))
+                    .await
+                    .unwrap();
+            }
+            storage
+                .create_collection(create_test_collection("go/onboarding", &["go/docs"]))
+                .await
+                .unwrap();
+            storage
+                .rename("go/wiki", "go/handbook", true)
+                .await
+                .unwrap();
 
-        async fn create_test_sqlite_storage() -> SqliteStorage {
-            let temp_file = NamedTempFile::new().unwrap();
-            let db_path = temp_file.path().to_str().unwrap();
-            // Use file:// prefix for SQLite URLs in tests
-            let db_url = format!("sqlite://{}?mode=rwc", db_path);
-            SqliteStorage::new(&db_url).await.unwrap()
+            for name in ["go/docs", "go/onboarding", "go/wiki", "go/handbook"] {
+                assert!(storage.name_taken(name).await.unwrap(), "{}", name);
+            }
+            assert!(!storage.exists("go/onboarding").await.unwrap());
+            assert!(!storage.exists("go/wiki").await.unwrap());
+            assert!(!storage.name_taken("go/free").await.unwrap());
         }
 
         #[tokio::test]
-        async fn test_create_and_get_golink() {
-            let storage = create_test_sqlite_storage().await;
+        async fn test_record_clicks() {
+            let (storage, _db) = create_test_sqlite_storage().await;
             let golink = create_test_golink("go/test", "https://example.com");
+            storage.create(golink.clone()).await.unwrap();
 
-            // Test create
-            let result = storage.create(golink.clone()).await;
-            assert!(result.is_ok());
+            let used_at = chrono::Utc::now();
+            let mut clicks = ClickCount::new("go/test");
+            let mut missing = ClickCount::new("go/nonexistent");
+            let source = ClickSource::from_headers(None, Some("curl/8.0"));
+            for _ in 0..3 {
+                clicks.add(used_at, &source);
+            }
+            missing.add(used_at, &source);
+            storage.record_clicks(vec![clicks, missing]).await.unwrap();
 
-            // Test get
-            let retrieved = storage.get(&golink.short_link).await;
-            assert!(retrieved.is_ok());
-            let retrieved_golink = retrieved.unwrap();
-            assert_eq!(retrieved_golink.short_link, golink.short_link);
-            assert_eq!(retrieved_golink.url, golink.url);
+            let retrieved = storage.get(&golink.short_link).await.unwrap();
+            assert_eq!(retrieved.clicks, 3);
+            assert_eq!(retrieved.last_used_at, Some(used_at.to_rfc3339()));
         }
 
         #[tokio::test]
-        async fn test_create_duplicate_returns_error() {
-            let storage = create_test_sqlite_storage().await;
-            let golink = create_test_golink("go/test", "https://example.com");
+        async fn test_click_buckets() {
+            let (storage, _db) = create_test_sqlite_storage().await;
+            storage
+                .create(create_test_golink("go/test", "https://example.com"))
+                .await
+                .unwrap();
 
-            // Create first time
-            let result1 = storage.create(golink.clone()).await;
-            assert!(result1.is_ok());
+            // 2024-01-01T00:00:00Z, two clicks in the first hour and one the next day
+            let day = 1_704_067_200;
+            let mut clicks = ClickCount::new("go/test");
+            let source = ClickSource::from_headers(None, Some("curl/8.0"));
+            for offset in [60, 1800, 86400 + 3600] {
+                clicks.add(chrono::DateTime::from_timestamp(day + offset, 0).unwrap(), &source);
+            }
+            storage.record_clicks(vec![clicks]).await.unwrap();
 
-            // Create second time should fail
-            let result2 = storage.create(golink.clone()).await;
-            assert!(matches!(result2, Err(StorageError::AlreadyExists)));
+            let hourly = storage
+                .get_click_buckets("go/test", Granularity::Hour, day, day + 2 * 86400)
+                .await
+                .unwrap();
+            assert_eq!(
+                hourly,
+                vec![
+                    ClickBucket { start: day, clicks: 2 },
+                    ClickBucket { start: day + 86400 + 3600, clicks: 1 },
+                ]
+            );
+
+            let daily = storage
+                .get_click_buckets("go/test", Granularity::Day, day, day + 2 * 86400)
+                .await
+                .unwrap();
+            assert_eq!(
+                daily,
+                vec![
+                    ClickBucket { start: day, clicks: 2 },
+                    ClickBucket { start: day + 86400, clicks: 1 },
+                ]
+            );
+
+            // Pruning hourly buckets leaves the daily rollup alone
+            storage
+                .prune_click_buckets(Granularity::Hour, day + 86400)
+                .await
+                .unwrap();
+            let hourly = storage
+                .get_click_buckets("go/test", Granularity::Hour, day, day + 2 * 86400)
+                .await
+                .unwrap();
+            assert_eq!(hourly.len(), 1);
+            let daily = storage
+                .get_click_buckets("go/test", Granularity::Day, day, day + 2 * 86400)
+                .await
+                .unwrap();
+            assert_eq!(daily.len(), 2);
         }
 
         #[tokio::test]
-        async fn test_get_nonexistent_returns_not_found() {
-            let storage = create_test_sqlite_storage().await;
-            let result = storage.get("go/nonexistent").await;
-            assert!(matches!(result, Err(StorageError::NotFound)));
+        async fn test_click_sources() {
+            let (storage, _db) = create_test_sqlite_storage().await;
+            storage
+                .create(create_test_golink("go/test", "https://example.com"))
+                .await
+                .unwrap();
+
+            let at = chrono::DateTime::from_timestamp(1_704_067_200 + 3600, 0).unwrap();
+            let docs = ClickSource::from_headers(
+                Some("https://docs.example.com/page"),
+                Some("Mozilla/5.0 (X11; Linux x86_64)"),
+            );
+            let bot = ClickSource::from_headers(None, Some("Slackbot-LinkExpanding 1.0"));
+            let mut clicks = ClickCount::new("go/test");
+            clicks.add(at, &docs);
+            clicks.add(at, &docs);
+            clicks.add(at, &bot);
+            storage.record_clicks(vec![clicks]).await.unwrap();
+
+            // Bots are excluded from the headline count
+            assert_eq!(storage.get("go/test").await.unwrap().clicks, 2);
+
+            let mut sources = storage
+                .get_click_sources("go/test", 1_704_067_200, 1_704_067_200 + 86400)
+                .await
+                .unwrap();
+            sources.sort_by_key(|source| source.client);
+            assert_eq!(
+                sources,
+                vec![
+                    SourceCount {
+                        referrer: Some("docs.example.com".to_string()),
+                        client: ClientClass::Browser,
+                        clicks: 2,
+                    },
+                    SourceCount {
+                        referrer: None,
+                        client: ClientClass::Bot,
+                        clicks: 1,
+                    },
+                ]
+            );
+
+            storage
+                .prune_click_buckets(Granularity::Day, 1_704_067_200 + 86400)
+                .await
+                .unwrap();
+            let sources = storage
+                .get_click_sources("go/test", 1_704_067_200, 1_704_067_200 + 86400)
+                .await
+                .unwrap();
+            assert!(sources.is_empty());
         }
 
         #[tokio::test]
-        async fn test_get_all_golinks() {
-            let storage = create_test_sqlite_storage().await;
-            let golink1 = create_test_golink("go/test1", "https://example1.com");
-            let golink2 = create_test_golink("go/test2", "https://example2.com");
+        async fn test_click_totals() {
+            let (storage, _db) = create_test_sqlite_storage().await;
+            let day = 1_704_067_200;
+            let source = ClickSource::from_headers(None, Some("curl/8.0"));
+            let mut batch = Vec::new();
+            for (name, offsets) in [("go/a", vec![0, 3600, 86400]), ("go/b", vec![7200])] {
+                storage
+                    .create(create_test_golink(name, "https://example.com"))
+                    .await
+                    .unwrap();
+                let mut clicks = ClickCount::new(name);
+                for offset in offsets {
+                    clicks.add(chrono::DateTime::from_timestamp(day + offset, 0).unwrap(), &source);
+                }
+                batch.push(clicks);
+            }
+            storage.record_clicks(batch).await.unwrap();
 
-            storage.create(golink1.clone()).await.unwrap();
-            storage.create(golink2.clone()).await.unwrap();
+            let totals = storage
+                .get_click_totals(Granularity::Day, day, day + 86400)
+                .await
+                .unwrap();
+            assert_eq!(totals.len(), 2);
+            assert_eq!(totals["go/a"], 2);
+            assert_eq!(totals["go/b"], 1);
 
-            let all_golinks = storage.get_all().await.unwrap();
-            assert_eq!(all_golinks.len(), 2);
+            let totals = storage
+                .get_click_totals(Granularity::Hour, day + 3600, day + 2 * 86400)
+                .await
+                .unwrap();
+            assert_eq!(totals["go/a"], 2);
+            assert_eq!(totals["go/b"], 1);
         }
 
         #[tokio::test]
-        async fn test_update_golink() {
-            let storage = create_test_sqlite_storage().await;
-            let golink = create_test_golink("go/test", "https://example.com");
+        async fn test_get_stale_and_set_deprecated() {
+            let (storage, _db) = create_test_sqlite_storage().await;
+            let old = "2024-01-01T00:00:00+00:00".to_string();
+            let recent = "2024-06-01T00:00:00+00:00".to_string();
+            let cutoff = "2024-03-01T00:00:00+00:00";
 
-            storage.create(golink.clone()).await.unwrap();
+            let golinks = [
+                ("go/unused", old.clone(), None),
+                ("go/clicked", old.clone(), Some(recent.clone())),
+                ("go/edited", recent.clone(), None),
+                ("go/forgotten", old.clone(), Some(old.clone())),
+            ];
+            for (name, updated_at, last_used_at) in golinks {
+                let mut golink = create_test_golink(name, "https://example.com");
+                golink.created_at = old.clone();
+                golink.updated_at = updated_at;
+                golink.last_used_at = last_used_at;
+                storage.create(golink).await.unwrap();
+            }
+
+            let stale = storage.get_stale(cutoff).await.unwrap();
+            let names: Vec<&str> = stale.iter().map(|g| g.short_link.as_str()).collect();
+            assert_eq!(names, vec!["go/forgotten", "go/unused"]);
+
+            let found = storage
+                .set_deprecated(&["go/unused".to_string(), "go/nonexistent".to_string()], true)
+                .await
+                .unwrap();
+            assert_eq!(found, 1);
+            let golink = storage.get("go/unused").await.unwrap();
+            assert!(golink.deprecated);
+            // Deprecating isn't a modification, the golink stays stale, but it
+            // changes what the golink returns so its version moves on
+            assert_eq!(golink.updated_at, old);
+            let untouched = storage.get("go/forgotten").await.unwrap();
+            assert_eq!(golink.version, untouched.version + 1);
+        }
+
+        #[tokio::test]
+        async fn test_favorites_per_user() {
+            let (storage, _db) = create_test_sqlite_storage().await;
+            for name in ["go/docs", "go/wiki"] {
+                storage
+                    .create(create_test_golink(name, "https://example.com"))
+                    .await
+                    .unwrap();
+            }
+
+            let first = storage.add_favorite("alice", "go/docs").await.unwrap();
+            assert_eq!(first.golink.short_link, "go/docs");
+            // Starring again keeps the original timestamp
+            let again = storage.add_favorite("alice", "go/docs").await.unwrap();
+            assert_eq!(again.favorited_at, first.favorited_at);
+            storage.add_favorite("bob", "go/wiki").await.unwrap();
+
+            assert!(matches!(
+                storage.add_favorite("alice", "go/nonexistent").await,
+                Err(StorageError::NotFound)
+            ));
+
+            let favorites = storage.get_favorites("alice").await.unwrap();
+            assert_eq!(favorites.len(), 1);
+            assert_eq!(favorites[0].golink.short_link, "go/docs");
+            assert!(storage.get_favorites("carol").await.unwrap().is_empty());
+
+            storage.remove_favorite("alice", "go/docs").await.unwrap();
+            assert!(storage.get_favorites("alice").await.unwrap().is_empty());
+            assert!(matches!(
+                storage.remove_favorite("alice", "go/docs").await,
+                Err(StorageError::NotFound)
+            ));
+
+            // Deleting a golink removes it from everyone's favorites
+            storage.delete("go/wiki").await.unwrap();
+            assert!(storage.get_favorites("bob").await.unwrap().is_empty());
+        }
+
+        #[tokio::test]
+        async fn test_collection_crud() {
+            let (storage, _db) = create_test_sqlite_storage().await;
+            for name in ["go/docs", "go/wiki", "go/chat"] {
+                storage
+                    .create(create_test_golink(name, "https://example.com"))
+                    .await
+                    .unwrap();
+            }
+
+            let collection = create_test_collection("go/onboarding", &["go/wiki", "go/docs"]);
+            storage.create_collection(collection).await.unwrap();
+
+            // Collections and golinks share one namespace
+            assert!(matches!(
+                storage
+                    .create_collection(create_test_collection("go/onboarding", &[]))
+                    .await,
+                Err(StorageError::AlreadyExists)
+            ));
+            assert!(matches!(
+                storage
+                    .create_collection(create_test_collection("go/docs", &[]))
+                    .await,
+                Err(StorageError::AlreadyExists)
+            ));
+            assert!(matches!(
+                storage
+                    .create(create_test_golink("go/onboarding", "https://example.com"))
+                    .await,
+                Err(StorageError::AlreadyExists)
+            ));
+
+            let collection = storage.get_collection("go/onboarding").await.unwrap();
+            assert_eq!(collection.links, vec!["go/wiki", "go/docs"]);
 
             let updated = storage
-                .update(&golink.short_link, "https://updated.com".to_string())
-                .await;
-            assert!(updated.is_ok());
-            let updated_golink = updated.unwrap();
-            assert_eq!(updated_golink.url, "https://updated.com");
+                .update_collection(
+                    "go/onboarding",
+                    "Engineering onboarding".to_string(),
+                    Some("Start here".to_string()),
+                    vec!["go/chat".to_string(), "go/docs".to_string()],
+                )
+                .await
+                .unwrap();
+            assert_eq!(updated.title, "Engineering onboarding");
+            assert_eq!(updated.links, vec!["go/chat", "go/docs"]);
+
+            // Deleting a golink removes it from collections
+            storage.delete("go/chat").await.unwrap();
+            let collections = storage.get_collections().await.unwrap();
+            assert_eq!(collections.len(), 1);
+            assert_eq!(collections[0].links, vec!["go/docs"]);
+            assert_eq!(collections[0].description.as_deref(), Some("Start here"));
+
+            storage.delete_collection("go/onboarding").await.unwrap();
+            assert!(matches!(
+                storage.get_collection("go/onboarding").await,
+                Err(StorageError::NotFound)
+            ));
+            assert!(matches!(
+                storage.delete_collection("go/onboarding").await,
+                Err(StorageError::NotFound)
+            ));
         }
 
         #[tokio::test]
-        async fn test_update_nonexistent_returns_not_found() {
-            let storage = create_test_sqlite_storage().await;
-            let result = storage
-                .update("go/nonexistent", "https://example.com".to_string())
-                .await;
-            assert!(matches!(result, Err(StorageError::NotFound)));
+        async fn test_apply_changes() {
+            let (storage, _db) = create_test_sqlite_storage().await;
+            let mut golink = create_test_golink("go/test", "https://example.com");
+            golink.owner = Some("alice".to_string());
+            golink.updated_at = "2024-01-01T00:00:00+00:00".to_string();
+            storage.create(golink).await.unwrap();
+
+            let changes = GolinkChanges {
+                url: Some("https://new.example.com".to_string()),
+                owner: Some(None),
+                deprecated: Some(true),
+                ..Default::default()
+            };
+            let updated = storage.apply_changes("go/test", changes).await.unwrap();
+            assert_eq!(updated.url, "https://new.example.com");
+            assert_eq!(updated.owner, None);
+            assert!(updated.deprecated);
+            assert!(!updated.locked);
+            assert!(updated.updated_at.as_str() > "2024-01-01T00:00:00+00:00");
+
+            let golink = storage.get("go/test").await.unwrap();
+            assert_eq!(golink.url, "https://new.example.com");
+            assert_eq!(golink.owner, None);
+
+            assert!(matches!(
+                storage
+                    .apply_changes("go/nonexistent", GolinkChanges::default())
+                    .await,
+                Err(StorageError::NotFound)
+            ));
         }
 
         #[tokio::test]
-        async fn test_delete_golink() {
-            let storage = create_test_sqlite_storage().await;
-            let golink = create_test_golink("go/test", "https://example.com");
+        async fn test_rename_if() {
+            let (storage, _db) = create_test_sqlite_storage().await;
+            storage
+                .create(create_test_golink("go/old", "https://example.com"))
+                .await
+                .unwrap();
+            let version = storage.get("go/old").await.unwrap().version;
+            storage.set_locked("go/old", true).await.unwrap();
 
-            storage.create(golink.clone()).await.unwrap();
+            assert!(matches!(
+                storage.rename_if("go/old", "go/new", false, version).await,
+                Err(StorageError::VersionMismatch)
+            ));
+            assert!(matches!(
+                storage.rename_if("go/missing", "go/new", false, version).await,
+                Err(StorageError::NotFound)
+            ));
+            assert!(storage.exists("go/old").await.unwrap());
 
-            let result = storage.delete(&golink.short_link).await;
-            assert!(result.is_ok());
+            let current = storage.get("go/old").await.unwrap().version;
+            let renamed = storage.rename_if("go/old", "go/new", false, current).await.unwrap();
+            assert_eq!(renamed.version, current + 1);
+        }
 
-            // Verify it's deleted
-            let get_result = storage.get(&golink.short_link).await;
-            assert!(matches!(get_result, Err(StorageError::NotFound)));
+        #[tokio::test]
+        async fn test_rename_keeps_history() {
+            let (storage, _db) = create_test_sqlite_storage().await;
+            storage
+                .create(create_test_golink("go/oldteam-wiki", "https://wiki.example.com"))
+                .await
+                .unwrap();
+            storage
+                .create(create_test_golink("go/other", "https://example.com"))
+                .await
+                .unwrap();
+            let original = storage.get("go/oldteam-wiki").await.unwrap();
+
+            let mut click = ClickCount::new("go/oldteam-wiki");
+            click.add(chrono::Utc::now(), &ClickSource::from_headers(None, None));
+            storage.record_clicks(vec![click]).await.unwrap();
+            storage.add_favorite("alice", "go/oldteam-wiki").await.unwrap();
+            storage
+                .create_collection(create_test_collection("go/onboarding", &["go/oldteam-wiki"]))
+                .await
+                .unwrap();
+
+            // The new name must be free
+            assert!(matches!(
+                storage.rename("go/oldteam-wiki", "go/other", true).await,
+                Err(StorageError::AlreadyExists)
+            ));
+            assert!(matches!(
+                storage.rename("go/missing", "go/new", true).await,
+                Err(StorageError::NotFound)
+            ));
+
+            let renamed = storage
+                .rename("go/oldteam-wiki", "go/platform-wiki", true)
+                .await
+                .unwrap();
+            assert_eq!(renamed.id, original.id);
+            assert_eq!(renamed.created_at, original.created_at);
+            assert_eq!(renamed.short_link, "go/platform-wiki");
+            assert_eq!(renamed.clicks, 1);
+
+            assert!(matches!(
+                storage.get("go/oldteam-wiki").await,
+                Err(StorageError::NotFound)
+            ));
+            assert_eq!(
+                storage.resolve_alias("go/oldteam-wiki").await.unwrap(),
+                "go/platform-wiki"
+            );
+            let buckets = storage
+                .get_click_buckets("go/platform-wiki", Granularity::Day, 0, i64::MAX)
+                .await
+                .unwrap();
+            assert_eq!(buckets.len(), 1);
+            let favorites = storage.get_favorites("alice").await.unwrap();
+            assert_eq!(favorites[0].golink.short_link, "go/platform-wiki");
+            let collection = storage.get_collection("go/onboarding").await.unwrap();
+            assert_eq!(collection.links, vec!["go/platform-wiki"]);
+
+            // Aliases take up their name
+            assert!(matches!(
+                storage
+                    .create(create_test_golink("go/oldteam-wiki", "https://example.com"))
+                    .await,
+                Err(StorageError::AlreadyExists)
+            ));
+
+            // Renaming again follows existing aliases, renaming back frees the alias
+            storage
+                .rename("go/platform-wiki", "go/oldteam-wiki", false)
+                .await
+                .unwrap();
+            assert!(matches!(
+                storage.resolve_alias("go/oldteam-wiki").await,
+                Err(StorageError::NotFound)
+            ));
+            assert!(matches!(
+                storage.resolve_alias("go/platform-wiki").await,
+                Err(StorageError::NotFound)
+            ));
+
+            // Deleting a golink drops its aliases
+            storage
+                .rename("go/oldteam-wiki", "go/platform-wiki", true)
+                .await
+                .unwrap();
+            storage.delete("go/platform-wiki").await.unwrap();
+            assert!(matches!(
+                storage.resolve_alias("go/oldteam-wiki").await,
+                Err(StorageError::NotFound)
+            ));
         }
 
         #[tokio::test]
-        async fn test_delete_nonexistent_returns_not_found() {
-            let storage = create_test_sqlite_storage().await;
-            let result = storage.delete("go/nonexistent").await;
+        async fn test_apply_batch() {
+            let (storage, _db) = create_test_sqlite_storage().await;
+            storage
+                .create(create_test_golink("go/existing", "https://example.com"))
+                .await
+                .unwrap();
+
+            let operations = vec![
+                BatchOperation::Create(create_test_golink("go/new", "https://new.example.com")),
+                BatchOperation::Update {
+                    short_link: "go/existing".to_string(),
+                    url: "https://updated.example.com".to_string(),
+                    unlocked_only: false,
+                },
+                BatchOperation::Delete {
+                    short_link: "go/missing".to_string(),
+                    unlocked_only: false,
+                },
+            ];
+
+            // Atomic batches with a failure write nothing
+            let results = storage.apply_batch(operations.clone(), true).await.unwrap();
+            assert!(matches!(results[0], Ok(Some(_))));
+            assert!(matches!(results[1], Ok(Some(_))));
+            assert!(matches!(results[2], Err(StorageError::NotFound)));
+            assert!(!storage.exists("go/new").await.unwrap());
+            assert_eq!(
+                storage.get("go/existing").await.unwrap().url,
+                "https://example.com"
+            );
+
+            // Otherwise the successful operations are kept
+            let results = storage.apply_batch(operations, false).await.unwrap();
+            assert!(results[0].is_ok() && results[1].is_ok() && results[2].is_err());
+            assert!(storage.exists("go/new").await.unwrap());
+            assert_eq!(
+                storage.get("go/existing").await.unwrap().url,
+                "https://updated.example.com"
+            );
+
+            // Later operations see the effect of earlier ones
+            let operations = vec![
+                BatchOperation::Delete {
+                    short_link: "go/new".to_string(),
+                    unlocked_only: false,
+                },
+                BatchOperation::Create(create_test_golink("go/new", "https://again.example.com")),
+                BatchOperation::Create(create_test_golink("go/existing", "https://example.com")),
+            ];
+            let results = storage.apply_batch(operations, false).await.unwrap();
+            assert!(matches!(results[0], Ok(None)));
+            assert!(results[1].is_ok());
+            assert!(matches!(results[2], Err(StorageError::AlreadyExists)));
+            assert_eq!(
+                storage.get("go/new").await.unwrap().url,
+                "https://again.example.com"
+            );
+        }
+
+        #[tokio::test]
+        async fn test_apply_batch_unlocked_only() {
+            let (storage, _db) = create_test_sqlite_storage().await;
+            storage
+                .create(create_test_golink("go/locked", "https://example.com"))
+                .await
+                .unwrap();
+            storage.set_locked("go/locked", true).await.unwrap();
+
+            let operations = vec![
+                BatchOperation::Update {
+                    short_link: "go/locked".to_string(),
+                    url: "https://updated.example.com".to_string(),
+                    unlocked_only: true,
+                },
+                BatchOperation::Delete {
+                    short_link: "go/locked".to_string(),
+                    unlocked_only: true,
+                },
+                BatchOperation::Delete {
+                    short_link: "go/missing".to_string(),
+                    unlocked_only: true,
+                },
+            ];
+            let results = storage.apply_batch(operations, false).await.unwrap();
+            assert!(matches!(results[0], Err(StorageError::Locked)));
+            assert!(matches!(results[1], Err(StorageError::Locked)));
+            assert!(matches!(results[2], Err(StorageError::NotFound)));
+            assert_eq!(
+                storage.get("go/locked").await.unwrap().url,
+                "https://example.com"
+            );
+
+            let operations = vec![BatchOperation::Delete {
+                short_link: "go/locked".to_string(),
+                unlocked_only: false,
+            }];
+            let results = storage.apply_batch(operations, false).await.unwrap();
+            assert!(matches!(results[0], Ok(None)));
+        }
+
+        #[tokio::test]
+        async fn test_apply_batch_replace() {
+            let (storage, _db) = create_test_sqlite_storage().await;
+            storage
+                .create(create_test_golink("go/existing", "https://example.com"))
+                .await
+                .unwrap();
+            storage
+                .create_collection(create_test_collection("go/onboarding", &[]))
+                .await
+                .unwrap();
+
+            let mut replacement = create_test_golink("go/existing", "https://replaced.example.com");
+            replacement.owner = Some("alice".to_string());
+            replacement.clicks = 7;
+            let operations = vec![
+                BatchOperation::Replace(replacement.clone()),
+                BatchOperation::Replace(create_test_golink("go/new", "https://new.example.com")),
+                BatchOperation::Replace(create_test_golink("go/onboarding", "https://example.com")),
+            ];
+            let results = storage.apply_batch(operations, false).await.unwrap();
+            assert!(results[0].is_ok() && results[1].is_ok());
+            assert!(matches!(results[2], Err(StorageError::AlreadyExists)));
+
+            let golink = storage.get("go/existing").await.unwrap();
+            assert_eq!(golink.id, replacement.id);
+            assert_eq!(golink.url, "https://replaced.example.com");
+            assert_eq!(golink.owner.as_deref(), Some("alice"));
+            assert_eq!(golink.clicks, 7);
+            assert!(storage.exists("go/new").await.unwrap());
+        }
+
+        #[tokio::test]
+        async fn test_tags_are_persisted() {
+            let (storage, _db) = create_test_sqlite_storage().await;
+            let mut golink = create_test_golink("go/tagged", "https://example.com");
+            golink.tags = vec!["docs".to_string(), "on-call".to_string()];
+            storage.create(golink).await.unwrap();
+
+            let golink = storage.get("go/tagged").await.unwrap();
+            assert_eq!(golink.tags, vec!["docs", "on-call"]);
+
+            let changes = GolinkChanges {
+                tags: Some(vec!["ops".to_string()]),
+                ..Default::default()
+            };
+            let updated = storage.apply_changes("go/tagged", changes).await.unwrap();
+            assert_eq!(updated.tags, vec!["ops"]);
+            assert_eq!(storage.get_all().await.unwrap()[0].tags, vec!["ops"]);
+        }
+
+        #[tokio::test]
+        async fn test_search() {
+            let (storage, _db) = create_test_sqlite_storage().await;
+            let mut grafana = create_test_golink("go/grafana", "https://grafana.example.com/d/abc");
+            grafana.tags = vec!["dashboards".to_string()];
+            storage.create(grafana).await.unwrap();
+            let mut oncall = create_test_golink("go/oncall", "https://pager.example.com");
+            oncall.description = Some("Grafana alerts for the on-call rotation".to_string());
+            storage.create(oncall).await.unwrap();
+            storage
+                .create(create_test_golink("go/wiki", "https://wiki.example.com"))
+                .await
+                .unwrap();
+
+            let hits = storage.search("grafana", 10).await.unwrap();
+            let names: Vec<&str> = hits.iter().map(|hit| hit.golink.short_link.as_str()).collect();
+            assert_eq!(names, vec!["go/grafana", "go/oncall"]);
+            assert!(hits[0].score > hits[1].score);
+            assert_eq!(hits[0].highlight.field, "short_link");
+            assert_eq!(hits[0].highlight.fragment, "go/<mark>grafana</mark>");
+            assert_eq!(hits[1].highlight.field, "description");
+
+            // Prefixes of every word have to match
+            assert_eq!(storage.search("dash graf", 10).await.unwrap().len(), 1);
+            assert!(storage.search("wiki grafana", 10).await.unwrap().is_empty());
+            assert_eq!(storage.search("example", 2).await.unwrap().len(), 2);
+
+            // The index follows updates, renames and deletes
+            storage
+                .update("go/wiki", "https://confluence.example.com".to_string())
+                .await
+                .unwrap();
+            assert_eq!(storage.search("confluence", 10).await.unwrap().len(), 1);
+            assert_eq!(storage.search("wiki.example", 10).await.unwrap().len(), 1);
+            storage.rename("go/wiki", "go/kb", false).await.unwrap();
+            let hits = storage.search("kb", 10).await.unwrap();
+            assert_eq!(hits[0].golink.short_link, "go/kb");
+            storage.delete("go/kb").await.unwrap();
+            assert!(storage.search("confluence", 10).await.unwrap().is_empty());
+        }
+
+        #[tokio::test]
+        async fn test_list_sorts_and_filters() {
+            let (storage, _db) = create_test_sqlite_storage().await;
+            let fixtures = [
+                ("go/beta", "https://Docs.example.com/beta", "2024-01-02T00:00:00+00:00", 5),
+                ("go/alpha", "https://docs.example.com:8080/alpha", "2024-01-04T00:00:00+00:00", 5),
+                ("go/gamma", "https://example.com/?next=https://docs.example.com/", "2024-01-03T00:00:00+00:00", 1),
+                ("go/delta", "https://docs.example.community/50%_off", "2024-01-02T00:00:00+00:00", 9),
+            ];
+            for (name, url, created_at, clicks) in fixtures {
+                let mut golink = create_test_golink(name, url);
+                golink.created_at = created_at.to_string();
+                golink.clicks = clicks;
+                storage.create(golink).await.unwrap();
+            }
+            let names = |golinks: Vec<Golink>| -> Vec<String> {
+                golinks.into_iter().map(|g| g.short_link).collect()
+            };
+
+            let query = ListQuery {
+                sort: GolinkSort::ShortLink,
+                ..Default::default()
+            };
+            let (golinks, total) = storage.list(&query, 1, Some(2)).await.unwrap();
+            assert_eq!(total, 4);
+            assert_eq!(names(golinks), vec!["go/beta", "go/delta"]);
+
+            // Equal clicks fall back to creation time, in the requested direction
+            let query = ListQuery {
+                sort: GolinkSort::Clicks,
+                direction: Some(SortDirection::Asc),
+                ..Default::default()
+            };
+            let (golinks, _) = storage.list(&query, 0, None).await.unwrap();
+            assert_eq!(names(golinks), vec!["go/gamma", "go/beta", "go/alpha", "go/delta"]);
+
+            // Equal creation times fall back to the name
+            let (golinks, _) = storage.list(&ListQuery::default(), 0, None).await.unwrap();
+            assert_eq!(names(golinks), vec!["go/alpha", "go/gamma", "go/delta", "go/beta"]);
+
+            let mut query = ListQuery::default();
+            query.filter.host = Some("docs.example.com".to_string());
+            let (golinks, total) = storage.list(&query, 0, None).await.unwrap();
+            assert_eq!(total, 2);
+            assert_eq!(names(golinks), vec!["go/alpha", "go/beta"]);
+
+            let mut query = ListQuery::default();
+            query.filter.url_contains = Some("%_OFF".to_string());
+            let (golinks, _) = storage.list(&query, 0, None).await.unwrap();
+            assert_eq!(names(golinks), vec!["go/delta"]);
+
+            let mut query = ListQuery::default();
+            query.filter.url_contains = Some("_".to_string());
+            let (golinks, _) = storage.list(&query, 0, None).await.unwrap();
+            assert_eq!(names(golinks), vec!["go/delta"]);
+
+            let mut query = ListQuery::default();
+            query.filter.created_after = Some("2024-01-02T00:00:00+00:00".to_string());
+            query.filter.created_before = Some("2024-01-04T00:00:00+00:00".to_string());
+            let (golinks, total) = storage.list(&query, 0, Some(1)).await.unwrap();
+            assert_eq!(total, 1);
+            assert_eq!(names(golinks), vec!["go/gamma"]);
+        }
+
+        #[tokio::test]
+        async fn test_list_after_cursor() {
+            let (storage, _db) = create_test_sqlite_storage().await;
+            let fixtures = [
+                ("go/a", "2024-01-01T00:00:00+00:00", 3, None),
+                ("go/b", "2024-01-02T00:00:00+00:00", 3, Some("2024-02-01T00:00:00+00:00")),
+                ("go/c", "2024-01-02T00:00:00+00:00", 0, None),
+                ("go/d", "2024-01-03T00:00:00+00:00", 7, Some("2024-02-01T00:00:00+00:00")),
+                ("go/e", "2024-01-04T00:00:00+00:00", 1, Some("2024-02-03T00:00:00+00:00")),
+            ];
+            for (name, created_at, clicks, last_used_at) in fixtures {
+                let mut golink = create_test_golink(name, "https://example.com");
+                golink.created_at = created_at.to_string();
+                golink.updated_at = created_at.to_string();
+                golink.clicks = clicks;
+                golink.last_used_at = last_used_at.map(String::from);
+                storage.create(golink).await.unwrap();
+            }
+
+            // Walking two at a time visits every golink once, in listing order
+            let sorts = [
+                GolinkSort::ShortLink,
+                GolinkSort::CreatedAt,
+                GolinkSort::UpdatedAt,
+                GolinkSort::Clicks,
+                GolinkSort::LastUsedAt,
+            ];
+            for sort in sorts {
+                for direction in [SortDirection::Asc, SortDirection::Desc] {
+                    let mut query = ListQuery {
+                        sort,
+                        direction: Some(direction),
+                        ..Default::default()
+                    };
+                    let (all, _) = storage.list(&query, 0, None).await.unwrap();
+                    let all: Vec<String> = all.into_iter().map(|g| g.short_link).collect();
+                    let mut walked = Vec::new();
+                    loop {
+                        let (page, total) = storage.list(&query, 0, Some(2)).await.unwrap();
+                        assert_eq!(total, 5);
+                        let Some(last) = page.last() else { break };
+                        query.after = Some(Cursor::decode(&Cursor::after(&query, last).encode()).unwrap());
+                        walked.extend(page.into_iter().map(|g| g.short_link));
+                    }
+                    assert_eq!(walked, all, "{:?} {:?}", sort, direction);
+                }
+            }
+
+            // Golinks created before the cursor position don't shift later pages
+            let mut query = ListQuery {
+                sort: GolinkSort::ShortLink,
+                ..Default::default()
+            };
+            let (page, _) = storage.list(&query, 0, Some(2)).await.unwrap();
+            query.after = Some(Cursor::after(&query, page.last().unwrap()));
+            storage
+                .create(create_test_golink("go/aa", "https://example.com"))
+                .await
+                .unwrap();
+            let (page, total) = storage.list(&query, 0, Some(2)).await.unwrap();
+            assert_eq!(total, 6);
+            let names: Vec<&str> = page.iter().map(|g| g.short_link.as_str()).collect();
+            assert_eq!(names, vec!["go/c", "go/d"]);
+        }
+
+        #[tokio::test]
+        async fn test_compare_and_swap() {
+            let (storage, _db) = create_test_sqlite_storage().await;
+            let mut golink = create_test_golink("go/oncall", "https://example.com/oncall");
+            golink.version = 1;
+            storage.create(golink).await.unwrap();
+
+            // Every edit bumps the version
+            let golink = storage
+                .update("go/oncall", "https://example.com/pager".to_string())
+                .await
+                .unwrap();
+            assert_eq!(golink.version, 2);
+            let golink = storage.set_locked("go/oncall", false).await.unwrap();
+            assert_eq!(golink.version, 3);
+
+            let changes = GolinkChanges {
+                url: Some("https://example.com/stale".to_string()),
+                ..Default::default()
+            };
+            let result = storage.apply_changes_if("go/oncall", 2, changes.clone()).await;
+            assert!(matches!(result, Err(StorageError::VersionMismatch)));
+            let golink = storage.get("go/oncall").await.unwrap();
+            assert_eq!(golink.url, "https://example.com/pager");
+            assert_eq!(golink.version, 3);
+
+            let golink = storage.apply_changes_if("go/oncall", 3, changes.clone()).await.unwrap();
+            assert_eq!(golink.url, "https://example.com/stale");
+            assert_eq!(golink.version, 4);
+            let result = storage.apply_changes_if("go/missing", 1, changes).await;
+            assert!(matches!(result, Err(StorageError::NotFound)));
+
+            let result = storage.delete_if("go/oncall", 3).await;
+            assert!(matches!(result, Err(StorageError::VersionMismatch)));
+            assert!(storage.exists("go/oncall").await.unwrap());
+            storage.delete_if("go/oncall", 4).await.unwrap();
+            assert!(!storage.exists("go/oncall").await.unwrap());
+            let result = storage.delete_if("go/oncall", 4).await;
             assert!(matches!(result, Err(StorageError::NotFound)));
         }
 
         #[tokio::test]
-        async fn test_exists() {
-            let storage = create_test_sqlite_storage().await;
-            let golink = create_test_golink("go/test", "https://example.com");
+        async fn test_revision() {
+            let (storage, _db) = create_test_sqlite_storage().await;
+            let start = storage.revision().await.unwrap();
 
-            // Should not exist initially
-            let exists_before = storage.exists(&golink.short_link).await.unwrap();
-            assert!(!exists_before);
+            storage
+                .create(create_test_golink("go/test", "https://example.com"))
+                .await
+                .unwrap();
+            let created = storage.revision().await.unwrap();
+            assert_eq!(created.instance, start.instance);
+            assert!(created.counter > start.counter);
+            assert!(created.changed_at >= start.changed_at);
+            assert_ne!(created.etag(), start.etag());
+
+            // Reads and no-op writes leave it alone
+            storage.get("go/test").await.unwrap();
+            storage.get_all().await.unwrap();
+            assert!(storage.delete("go/missing").await.is_err());
+            assert_eq!(storage.revision().await.unwrap().etag(), created.etag());
+
+            // Clicks change the list, so they count too
+            let mut clicks = ClickCount::new("go/test");
+            clicks.add(chrono::Utc::now(), &ClickSource::from_headers(None, Some("curl/8.0")));
+            storage.record_clicks(vec![clicks]).await.unwrap();
+            let clicked = storage.revision().await.unwrap();
+            assert!(clicked.counter > created.counter);
+
+            storage.delete("go/test").await.unwrap();
+            let deleted = storage.revision().await.unwrap();
+            assert_eq!(deleted.instance, start.instance);
+            assert!(deleted.counter > clicked.counter);
+        }
+
+        #[tokio::test]
+        async fn test_suggest() {
+            let (storage, _db) = create_test_sqlite_storage().await;
+            for (name, clicks) in [
+                ("go/deploy", 5),
+                ("go/design", 9),
+                ("go/de", 0),
+                ("go/docs", 7),
+                ("go/Dev", 3),
+                ("go/dex", 5),
+            ] {
+                let mut golink = create_test_golink(name, "https://example.com");
+                golink.clicks = clicks;
+                storage.create(golink).await.unwrap();
+            }
+            let names = |golinks: Vec<Golink>| -> Vec<String> {
+                golinks.into_iter().map(|g| g.short_link).collect()
+            };
+
+            // Most clicked first, ties by name
+            assert_eq!(
+                names(storage.suggest("go/de", 10).await.unwrap()),
+                vec!["go/design", "go/deploy", "go/dex", "go/de"]
+            );
+            assert_eq!(
+                names(storage.suggest("go/de", 2).await.unwrap()),
+                vec!["go/design", "go/deploy"]
+            );
+            assert_eq!(names(storage.suggest("go/D", 10).await.unwrap()), vec!["go/Dev"]);
+            assert_eq!(storage.suggest("", 10).await.unwrap().len(), 6);
+            assert!(storage.suggest("go/x", 10).await.unwrap().is_empty());
+            assert!(storage.suggest("go/de%", 10).await.unwrap().is_empty());
+
+            // Renamed and deleted golinks move along
+            storage.rename("go/design", "go/ux", false).await.unwrap();
+            storage.delete("go/deploy").await.unwrap();
+            assert_eq!(
+                names(storage.suggest("go/de", 10).await.unwrap()),
+                vec!["go/dex", "go/de"]
+            );
+        }
+
+        #[tokio::test]
+        async fn test_suggest_uses_index() {
+            let (storage, _db) = create_test_sqlite_storage().await;
+            let plan: Vec<(i64, i64, i64, String)> = sqlx::query_as(
+                "EXPLAIN QUERY PLAN SELECT * FROM golinks WHERE short_link >= ? AND short_link < ? \
+                 ORDER BY clicks DESC, short_link LIMIT ?",
+            )
+            .bind("go/de")
+            .bind(format!("go/de{}", char::MAX))
+            .bind(10)
+            .fetch_all(&storage.pool)
+            .await
+            .unwrap();
+            assert!(
+                plan.iter()
+                    .any(|(_, _, _, detail)| detail.contains("USING INDEX") && detail.contains("short_link>?")),
+                "{:?}",
+                plan
+            );
+        }
+
+        #[tokio::test]
+        async fn test_get_paginated_sorted_by_clicks() {
+            let (storage, _db) = create_test_sqlite_storage().await;
+            for (name, clicks) in [("go/rare", 1), ("go/popular", 10), ("go/unused", 0)] {
+                let mut golink = create_test_golink(name, "https://example.com");
+                golink.clicks = clicks;
+                storage.create(golink).await.unwrap();
+            }
+
+            let (golinks, total) = storage.get_paginated(1, 2, GolinkSort::Clicks).await.unwrap();
+            assert_eq!(total, 3);
+            let names: Vec<&str> = golinks.iter().map(|g| g.short_link.as_str()).collect();
+            assert_eq!(names, vec!["go/popular", "go/rare"]);
+        }
+
+        #[tokio::test]
+        async fn test_set_locked() {
+            let (storage, _db) = create_test_sqlite_storage().await;
+            let golink = create_test_golink("go/test", "https://example.com");
 
-            // Create and check exists
             storage.create(golink.clone()).await.unwrap();
-            let exists_after = storage.exists(&golink.short_link).await.unwrap();
-            assert!(exists_after);
+            assert!(!storage.get(&golink.short_link).await.unwrap().locked);
+
+            let locked = storage.set_locked(&golink.short_link, true).await.unwrap();
+            assert!(locked.locked);
+            assert!(storage.get(&golink.short_link).await.unwrap().locked);
+
+            let result = storage.set_locked("go/nonexistent", true).await;
+            assert!(matches!(result, Err(StorageError::NotFound)));
         }
 
         #[tokio::test]
@@ -0,0 +1,41 @@
+use crate::service::{GOLINK_PATTERN, INVALID_PATTERN, TOKEN_COOKIE};
+
+// Single page served at `/ui` for people who don't use the API directly. It
+// has no external assets and talks to the JSON API under `/api/v1`, so it goes
+// through the same handlers and authentication as any other client.
+const UI_PAGE: &str = include_str!("ui.html");
+
+// The page with the golink pattern filled in, so names are validated in the
+// browser exactly as `validate_golink_pattern` will, and with the name of the
+// cookie redirects read the token from
+fn render_ui() -> String {
+    UI_PAGE
+        .replace("__GOLINK_PATTERN__", &serde_json::to_string(GOLINK_PATTERN).unwrap())
+        .replace("__INVALID_PATTERN__", &serde_json::to_string(INVALID_PATTERN).unwrap())
+        .replace("__TOKEN_COOKIE__", &serde_json::to_string(TOKEN_COOKIE).unwrap())
+}
+
+pub async fn get_ui() -> warp::reply::Html<String> {
+    warp::reply::html(render_ui())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_ui() {
+        let page = render_ui();
+        assert!(!page.contains("__GOLINK_PATTERN__"));
+        assert!(!page.contains("__INVALID_PATTERN__"));
+        assert!(!page.contains("__TOKEN_COOKIE__"));
+        assert!(page.contains(r#"document.cookie = "golink_token" + "=""#));
+        assert!(page.contains(r#"new RegExp("^go/[a-zA-Z0-9_-]+$")"#));
+        assert!(page.contains(r#""Invalid golink pattern. Must match 'go/[a-zA-Z0-9_-]+'""#));
+
+        // Everything the page needs is inline
+        for external in ["src=\"http", "href=\"http", "<link", "@import", "url("] {
+            assert!(!page.contains(external), "{}", external);
+        }
+    }
+}
@@ -1,37 +1,42 @@
-use golink::service::{CreateGolink, UpdateGolink};
+use golink::analytics::ClickRecorder;
+use golink::service::{CreateGolink, Role, Storage, UpdateGolink};
 use golink::storage::HashMapStorage;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+use tokio::sync::Mutex;
 use warp::Filter;
 use warp::test::request;
 
 // Mutex to ensure authentication tests run sequentially to avoid environment variable conflicts
-static AUTH_TEST_LOCK: Mutex<()> = Mutex::new(());
+static AUTH_TEST_LOCK: Mutex<()> = Mutex::const_new(());
 
 // Helper function to create routes with in-memory storage (no auth)
 fn create_app_no_auth() -> impl warp::Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
     let storage = Arc::new(HashMapStorage::new());
 
     let create_route = warp::path("golinks")
+        .and(warp::path::end())
         .and(warp::post())
         .and(warp::body::json())
         .and(golink::service::with_storage(storage.clone()))
-        .and_then(golink::service::create_golink);
+        .then(golink::service::create_golink);
 
     let get_all_route = warp::path("golinks")
         .and(warp::path::end())
         .and(warp::get())
         .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and(golink::conditional::with_conditional())
         .and(golink::service::with_storage(storage.clone()))
-        .and_then(golink::service::get_all_golinks);
+        .then(golink::service::get_all_golinks);
 
     let get_route = warp::path("golinks")
         .and(warp::path::param::<String>())
         .and(warp::path::param::<String>())
         .and(warp::path::end())
         .and(warp::get())
+        .and(golink::conditional::with_conditional())
         .and(golink::service::with_storage(storage.clone()))
-        .and_then(|prefix: String, name: String, storage| {
-            golink::service::get_golink(format!("{}/{}", prefix, name), storage)
+        .then(|prefix: String, name: String, conditional, storage| {
+            golink::service::get_golink(format!("{}/{}", prefix, name), conditional, storage)
         });
 
     let update_route = warp::path("golinks")
@@ -41,9 +46,9 @@ fn create_app_no_auth() -> impl warp::Filter<Extract = impl warp::Reply, Error =
         .and(warp::put())
         .and(warp::body::json())
         .and(golink::service::with_storage(storage.clone()))
-        .and_then(
+        .then(
             |prefix: String, name: String, update_data: UpdateGolink, storage| {
-                golink::service::update_golink(format!("{}/{}", prefix, name), update_data, storage)
+                golink::service::update_golink(format!("{}/{}", prefix, name), update_data, Role::Admin, None, storage)
             },
         );
 
@@ -53,11 +58,11 @@ fn create_app_no_auth() -> impl warp::Filter<Extract = impl warp::Reply, Error =
         .and(warp::path::end())
         .and(warp::delete())
         .and(golink::service::with_storage(storage.clone()))
-        .and_then(|prefix: String, name: String, storage| {
-            golink::service::delete_golink(format!("{}/{}", prefix, name), storage)
+        .then(|prefix: String, name: String, storage| {
+            golink::service::delete_golink(format!("{}/{}", prefix, name), Role::Admin, None, storage)
         });
 
-    // IMPORTANT: Route order must match main.rs exactly for accurate testing!
+    // IMPORTANT: Route order must match routes.rs exactly for accurate testing!
     // Specific routes must come before general routes to ensure correct precedence.
     create_route
         .or(get_route)        // Specific: /golinks/{prefix}/{name}
@@ -72,19 +77,21 @@ fn create_app_with_auth() -> impl warp::Filter<Extract = impl warp::Reply> + Clo
     let storage = Arc::new(HashMapStorage::new());
 
     let create_route = warp::path("golinks")
+        .and(warp::path::end())
         .and(warp::post())
         .and(golink::service::with_auth())
         .and(warp::body::json())
         .and(golink::service::with_storage(storage.clone()))
-        .and_then(golink::service::create_golink);
+        .then(golink::service::create_golink);
 
     let get_all_route = warp::path("golinks")
         .and(warp::path::end())
         .and(warp::get())
         .and(golink::service::with_auth())
         .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and(golink::conditional::with_conditional())
         .and(golink::service::with_storage(storage.clone()))
-        .and_then(golink::service::get_all_golinks);
+        .then(golink::service::get_all_golinks);
 
     let get_route = warp::path("golinks")
         .and(warp::path::param::<String>())
@@ -92,9 +99,10 @@ fn create_app_with_auth() -> impl warp::Filter<Extract = impl warp::Reply> + Clo
         .and(warp::path::end())
         .and(warp::get())
         .and(golink::service::with_auth())
+        .and(golink::conditional::with_conditional())
         .and(golink::service::with_storage(storage.clone()))
-        .and_then(|prefix: String, name: String, storage| {
-            golink::service::get_golink(format!("{}/{}", prefix, name), storage)
+        .then(|prefix: String, name: String, conditional, storage| {
+            golink::service::get_golink(format!("{}/{}", prefix, name), conditional, storage)
         });
 
     let update_route = warp::path("golinks")
@@ -102,12 +110,12 @@ fn create_app_with_auth() -> impl warp::Filter<Extract = impl warp::Reply> + Clo
         .and(warp::path::param::<String>())
         .and(warp::path::end())
         .and(warp::put())
-        .and(golink::service::with_auth())
+        .and(golink::service::with_role())
         .and(warp::body::json())
         .and(golink::service::with_storage(storage.clone()))
-        .and_then(
-            |prefix: String, name: String, update_data: UpdateGolink, storage| {
-                golink::service::update_golink(format!("{}/{}", prefix, name), update_data, storage)
+        .then(
+            |prefix: String, name: String, role: Role, update_data: UpdateGolink, storage| {
+                golink::service::update_golink(format!("{}/{}", prefix, name), update_data, role, None, storage)
             },
         );
 
@@ -116,13 +124,13 @@ fn create_app_with_auth() -> impl warp::Filter<Extract = impl warp::Reply> + Clo
         .and(warp::path::param::<String>())
         .and(warp::path::end())
         .and(warp::delete())
-        .and(golink::service::with_auth())
+        .and(golink::service::with_role())
         .and(golink::service::with_storage(storage.clone()))
-        .and_then(|prefix: String, name: String, storage| {
-            golink::service::delete_golink(format!("{}/{}", prefix, name), storage)
+        .then(|prefix: String, name: String, role: Role, storage| {
+            golink::service::delete_golink(format!("{}/{}", prefix, name), role, None, storage)
         });
 
-    // IMPORTANT: Route order must match main.rs exactly for accurate testing!
+    // IMPORTANT: Route order must match routes.rs exactly for accurate testing!
     // Specific routes must come before general routes to ensure correct precedence.
     create_route
         .or(get_route)        // Specific: /golinks/{prefix}/{name}
@@ -130,7 +138,7 @@ fn create_app_with_auth() -> impl warp::Filter<Extract = impl warp::Reply> + Clo
         .or(delete_route)     // Specific: /golinks/{prefix}/{name}
         .or(get_all_route)    // General: /golinks (must be last)
         .with(warp::cors().allow_any_origin())
-        .recover(golink::service::handle_auth_rejection)
+        .recover(golink::error::handle_rejection)
 }
 
 // For backward compatibility, keep the original function name
@@ -145,6 +153,7 @@ async fn test_create_golink_api() {
     let create_req = CreateGolink {
         short_link: "go/test".to_string(),
         url: "https://example.com".to_string(),
+        ..Default::default()
     };
 
     let resp = request()
@@ -171,6 +180,7 @@ async fn test_create_invalid_golink_pattern() {
     let create_req = CreateGolink {
         short_link: "invalid".to_string(),
         url: "https://example.com".to_string(),
+        ..Default::default()
     };
 
     let resp = request()
@@ -213,6 +223,7 @@ async fn test_create_and_get_golink() {
     let create_req = CreateGolink {
         short_link: "go/test".to_string(),
         url: "https://example.com".to_string(),
+        ..Default::default()
     };
 
     let create_resp = request()
@@ -248,6 +259,7 @@ async fn test_get_nonexistent_golink() {
     let create_req = CreateGolink {
         short_link: "go/random".to_string(),
         url: "https://random.com".to_string(),
+        ..Default::default()
     };
 
     let create_resp = request()
@@ -280,6 +292,7 @@ async fn test_create_duplicate_golink() {
     let create_req = CreateGolink {
         short_link: "go/test".to_string(),
         url: "https://example.com".to_string(),
+        ..Default::default()
     };
 
     // Create first time
@@ -316,6 +329,7 @@ async fn test_update_golink() {
     let create_req = CreateGolink {
         short_link: "go/test".to_string(),
         url: "https://example.com".to_string(),
+        ..Default::default()
     };
 
     let create_resp = request()
@@ -377,6 +391,7 @@ async fn test_delete_golink() {
     let create_req = CreateGolink {
         short_link: "go/test".to_string(),
         url: "https://example.com".to_string(),
+        ..Default::default()
     };
 
     let create_resp = request()
@@ -437,6 +452,7 @@ async fn test_full_crud_workflow() {
     let create_req = CreateGolink {
         short_link: "go/example".to_string(),
         url: "https://example.com".to_string(),
+        ..Default::default()
     };
 
     let create_resp = request()
@@ -493,6 +509,7 @@ async fn test_get_golinks_with_pagination() {
         let create_req = CreateGolink {
             short_link: format!("go/{}", name),
             url: format!("https://example{}.com", i + 1),
+            ..Default::default()
         };
 
         let create_resp = request()
@@ -565,12 +582,110 @@ async fn test_get_golinks_with_pagination() {
     assert_eq!(pagination["page_size"], 2);
     assert_eq!(pagination["total_items"], 5);
     assert_eq!(pagination["total_pages"], 3);
+    assert!(pagination.get("next_cursor").is_none());
+
+    // Following cursors from the first page visits every golink once
+    let mut path = "/golinks?page_size=2".to_string();
+    let mut seen = Vec::new();
+    loop {
+        let resp = request().method("GET").path(&path).reply(&app).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+        for golink in body["data"].as_array().unwrap() {
+            seen.push(golink["short_link"].as_str().unwrap().to_string());
+        }
+        match body["pagination"]["next_cursor"].as_str() {
+            Some(cursor) => path = format!("/golinks?page_size=2&cursor={}", cursor),
+            None => break,
+        }
+    }
+    seen.sort();
+    assert_eq!(seen, vec!["go/fifth", "go/first", "go/fourth", "go/second", "go/third"]);
+
+    // A cursor doesn't carry over to another ordering
+    let resp = request()
+        .method("GET")
+        .path("/golinks?page_size=2")
+        .reply(&app)
+        .await;
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    let cursor = body["pagination"]["next_cursor"].as_str().unwrap().to_string();
+    let resp = request()
+        .method("GET")
+        .path(&format!("/golinks?page_size=2&sort=clicks&cursor={}", cursor))
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 400);
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["code"], "invalid_parameter");
+
+    let resp = request()
+        .method("GET")
+        .path("/golinks?cursor=zz")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn test_get_golinks_sorted_and_filtered() {
+    let app = create_app();
+
+    for (name, url) in [
+        ("go/wiki", "https://wiki.example.com/home"),
+        ("go/docs", "https://docs.example.com/"),
+        ("go/api", "https://docs.example.com/api"),
+    ] {
+        let create_req = CreateGolink {
+            short_link: name.to_string(),
+            url: url.to_string(),
+            ..Default::default()
+        };
+        let create_resp = request()
+            .method("POST")
+            .path("/golinks")
+            .json(&create_req)
+            .reply(&app)
+            .await;
+        assert_eq!(create_resp.status(), 201);
+    }
+
+    let resp = request()
+        .method("GET")
+        .path("/golinks?sort=short_link&direction=desc&host=docs.example.com&page_size=1")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["data"][0]["short_link"], "go/docs");
+    assert_eq!(body["pagination"]["total_items"], 2);
+
+    let resp = request()
+        .method("GET")
+        .path("/golinks?sort=short_link&url_contains=API")
+        .reply(&app)
+        .await;
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    let names: Vec<&str> = body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|g| g["short_link"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["go/api"]);
+
+    let resp = request()
+        .method("GET")
+        .path("/golinks?direction=sideways")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 400);
 }
 
 // Authentication Tests
 #[tokio::test]
 async fn test_create_golink_with_auth_success() {
-    let _guard = AUTH_TEST_LOCK.lock().unwrap();
+    let _guard = AUTH_TEST_LOCK.lock().await;
     
     // Set up authentication
     unsafe { std::env::set_var("AUTH_TOKEN", "test-token-123"); }
@@ -579,6 +694,7 @@ async fn test_create_golink_with_auth_success() {
     let create_req = CreateGolink {
         short_link: "go/test".to_string(),
         url: "https://example.com".to_string(),
+        ..Default::default()
     };
 
     let resp = request()
@@ -602,7 +718,7 @@ async fn test_create_golink_with_auth_success() {
 
 #[tokio::test]
 async fn test_create_golink_with_auth_invalid_token() {
-    let _guard = AUTH_TEST_LOCK.lock().unwrap();
+    let _guard = AUTH_TEST_LOCK.lock().await;
     
     // Set up authentication
     unsafe { std::env::set_var("AUTH_TOKEN", "test-token-123"); }
@@ -611,6 +727,7 @@ async fn test_create_golink_with_auth_invalid_token() {
     let create_req = CreateGolink {
         short_link: "go/test".to_string(),
         url: "https://example.com".to_string(),
+        ..Default::default()
     };
 
     let resp = request()
@@ -633,7 +750,7 @@ async fn test_create_golink_with_auth_invalid_token() {
 
 #[tokio::test]
 async fn test_create_golink_with_auth_missing_token() {
-    let _guard = AUTH_TEST_LOCK.lock().unwrap();
+    let _guard = AUTH_TEST_LOCK.lock().await;
     
     // Set up authentication
     unsafe { std::env::set_var("AUTH_TOKEN", "test-token-123"); }
@@ -642,6 +759,7 @@ async fn test_create_golink_with_auth_missing_token() {
     let create_req = CreateGolink {
         short_link: "go/test".to_string(),
         url: "https://example.com".to_string(),
+        ..Default::default()
     };
 
     let resp = request()
@@ -663,7 +781,7 @@ async fn test_create_golink_with_auth_missing_token() {
 
 #[tokio::test]
 async fn test_get_golinks_with_auth_success() {
-    let _guard = AUTH_TEST_LOCK.lock().unwrap();
+    let _guard = AUTH_TEST_LOCK.lock().await;
     
     // Set up authentication
     unsafe { std::env::set_var("AUTH_TOKEN", "test-token-123"); }
@@ -673,6 +791,7 @@ async fn test_get_golinks_with_auth_success() {
     let create_req = CreateGolink {
         short_link: "go/test".to_string(),
         url: "https://example.com".to_string(),
+        ..Default::default()
     };
 
     let create_resp = request()
@@ -706,7 +825,7 @@ async fn test_get_golinks_with_auth_success() {
 
 #[tokio::test]
 async fn test_get_golinks_with_auth_unauthorized() {
-    let _guard = AUTH_TEST_LOCK.lock().unwrap();
+    let _guard = AUTH_TEST_LOCK.lock().await;
     
     // Set up authentication
     unsafe { std::env::set_var("AUTH_TOKEN", "test-token-123"); }
@@ -729,7 +848,7 @@ async fn test_get_golinks_with_auth_unauthorized() {
 
 #[tokio::test]
 async fn test_auth_workflow_end_to_end() {
-    let _guard = AUTH_TEST_LOCK.lock().unwrap();
+    let _guard = AUTH_TEST_LOCK.lock().await;
     
     // Set up authentication
     unsafe { std::env::set_var("AUTH_TOKEN", "test-token-123"); }
@@ -739,6 +858,7 @@ async fn test_auth_workflow_end_to_end() {
     let create_req = CreateGolink {
         short_link: "go/example".to_string(),
         url: "https://example.com".to_string(),
+        ..Default::default()
     };
 
     let create_resp = request()
@@ -787,3 +907,1251 @@ async fn test_auth_workflow_end_to_end() {
     // Clean up
     unsafe { std::env::remove_var("AUTH_TOKEN"); }
 }
+
+// Locked golink tests run against the full route set from routes.rs
+#[tokio::test]
+async fn test_locked_golink_requires_admin() {
+    let _guard = AUTH_TEST_LOCK.lock().await;
+
+    unsafe {
+        std::env::set_var("AUTH_TOKEN", "test-token-123");
+        std::env::set_var("ADMIN_TOKEN", "admin-token-456");
+    }
+    let storage: Storage = Arc::new(HashMapStorage::new());
+    let app = golink::routes::routes(storage.clone(), ClickRecorder::new(storage));
+
+    let create_req = CreateGolink {
+        short_link: "go/benefits".to_string(),
+        url: "https://benefits.example.com".to_string(),
+        ..Default::default()
+    };
+
+    let create_resp = request()
+        .method("POST")
+        .path("/golinks")
+        .header("content-type", "application/json")
+        .header("authorization", "Bearer test-token-123")
+        .json(&create_req)
+        .reply(&app)
+        .await;
+    assert_eq!(create_resp.status(), 201);
+
+    let body: serde_json::Value = serde_json::from_slice(create_resp.body()).unwrap();
+    assert_eq!(body["locked"], false);
+
+    // 1. Regular users can't lock golinks
+    let lock_resp = request()
+        .method("PUT")
+        .path("/admin/golinks/go/benefits/lock")
+        .header("content-type", "application/json")
+        .header("authorization", "Bearer test-token-123")
+        .json(&serde_json::json!({"locked": true}))
+        .reply(&app)
+        .await;
+    assert_eq!(lock_resp.status(), 403);
+
+    // 2. Admins can
+    let lock_resp = request()
+        .method("PUT")
+        .path("/admin/golinks/go/benefits/lock")
+        .header("content-type", "application/json")
+        .header("authorization", "Bearer admin-token-456")
+        .json(&serde_json::json!({"locked": true}))
+        .reply(&app)
+        .await;
+    assert_eq!(lock_resp.status(), 200);
+
+    let body: serde_json::Value = serde_json::from_slice(lock_resp.body()).unwrap();
+    assert_eq!(body["locked"], true);
+
+    // 3. Lock state is visible to everyone
+    let get_resp = request()
+        .method("GET")
+        .path("/golinks/go/benefits")
+        .header("authorization", "Bearer test-token-123")
+        .reply(&app)
+        .await;
+    assert_eq!(get_resp.status(), 200);
+
+    let body: serde_json::Value = serde_json::from_slice(get_resp.body()).unwrap();
+    assert_eq!(body["locked"], true);
+
+    // 4. Regular users can no longer update or delete it
+    let update_req = UpdateGolink {
+        url: "https://evil.example.com".to_string(),
+    };
+
+    let update_resp = request()
+        .method("PUT")
+        .path("/golinks/go/benefits")
+        .header("content-type", "application/json")
+        .header("authorization", "Bearer test-token-123")
+        .json(&update_req)
+        .reply(&app)
+        .await;
+    assert_eq!(update_resp.status(), 403);
+
+    let delete_resp = request()
+        .method("DELETE")
+        .path("/golinks/go/benefits")
+        .header("authorization", "Bearer test-token-123")
+        .reply(&app)
+        .await;
+    assert_eq!(delete_resp.status(), 403);
+
+    // 5. Admins still can
+    let update_resp = request()
+        .method("PUT")
+        .path("/golinks/go/benefits")
+        .header("content-type", "application/json")
+        .header("authorization", "Bearer admin-token-456")
+        .json(&UpdateGolink {
+            url: "https://benefits.example.com/new".to_string(),
+        })
+        .reply(&app)
+        .await;
+    assert_eq!(update_resp.status(), 200);
+
+    let delete_resp = request()
+        .method("DELETE")
+        .path("/golinks/go/benefits")
+        .header("authorization", "Bearer admin-token-456")
+        .reply(&app)
+        .await;
+    assert_eq!(delete_resp.status(), 200);
+
+    // Clean up
+    unsafe {
+        std::env::remove_var("AUTH_TOKEN");
+        std::env::remove_var("ADMIN_TOKEN");
+    }
+}
+
+#[tokio::test]
+async fn test_redirect_with_auth_accepts_cookie() {
+    let _guard = AUTH_TEST_LOCK.lock().await;
+    unsafe { std::env::set_var("AUTH_TOKEN", "test token;123"); }
+
+    let storage: Storage = Arc::new(HashMapStorage::new());
+    let app = golink::routes::routes(storage.clone(), ClickRecorder::new(storage));
+
+    let create_req = CreateGolink {
+        short_link: "go/docs".to_string(),
+        url: "https://docs.example.com".to_string(),
+        ..Default::default()
+    };
+    let resp = request()
+        .method("POST")
+        .path("/api/v1/golinks")
+        .header("authorization", "Bearer test token;123")
+        .json(&create_req)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 201);
+
+    // A browser has no Authorization header, only the web UI's cookie
+    let resp = request().method("GET").path("/go/docs").reply(&app).await;
+    assert_eq!(resp.status(), 401);
+
+    let resp = request()
+        .method("GET")
+        .path("/go/docs")
+        .header("cookie", "theme=dark; golink_token=test%20token%3B123")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 302);
+    assert_eq!(resp.headers()["location"], "https://docs.example.com");
+
+    let resp = request()
+        .method("GET")
+        .path("/go/docs")
+        .header("cookie", "golink_token=wrong")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 401);
+
+    let resp = request()
+        .method("GET")
+        .path("/go/docs")
+        .header("authorization", "Bearer test token;123")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 302);
+
+    // The cookie is only good for pages the browser opens itself
+    let resp = request()
+        .method("GET")
+        .path("/api/v1/golinks/go/docs")
+        .header("cookie", "golink_token=test%20token%3B123")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 401);
+
+    unsafe { std::env::remove_var("AUTH_TOKEN"); }
+}
+
+#[tokio::test]
+async fn test_redirect_counts_clicks() {
+    let _guard = AUTH_TEST_LOCK.lock().await;
+
+    let storage: Storage = Arc::new(HashMapStorage::new());
+    let clicks = ClickRecorder::new(storage.clone());
+    let app = golink::routes::routes(storage.clone(), clicks.clone());
+
+    for name in ["popular", "rare"] {
+        let create_req = CreateGolink {
+            short_link: format!("go/{}", name),
+            url: format!("https://{}.example.com", name),
+            ..Default::default()
+        };
+
+        let create_resp = request()
+            .method("POST")
+            .path("/golinks")
+            .header("content-type", "application/json")
+            .json(&create_req)
+            .reply(&app)
+            .await;
+        assert_eq!(create_resp.status(), 201);
+    }
+
+    // 1. Follow the golinks
+    for path in ["/go/popular", "/go/popular", "/go/rare"] {
+        let resp = request().method("GET").path(path).reply(&app).await;
+        assert_eq!(resp.status(), 302);
+    }
+
+    let resp = request().method("GET").path("/go/popular").reply(&app).await;
+    assert_eq!(resp.status(), 302);
+    assert_eq!(resp.headers()["location"], "https://popular.example.com");
+
+    let resp = request().method("GET").path("/go/missing").reply(&app).await;
+    assert_eq!(resp.status(), 404);
+
+    // 2. Counts show up once flushed
+    clicks.flush().await.unwrap();
+
+    let resp = request()
+        .method("GET")
+        .path("/golinks/go/popular")
+        .reply(&app)
+        .await;
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["clicks"], 3);
+    assert!(body["last_used_at"].is_string());
+
+    // 3. List can be sorted by clicks
+    let resp = request()
+        .method("GET")
+        .path("/golinks?sort=clicks")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 200);
+
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    let names: Vec<&str> = body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|g| g["short_link"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["go/popular", "go/rare"]);
+}
+
+#[tokio::test]
+async fn test_favorites_are_per_identity() {
+    let _guard = AUTH_TEST_LOCK.lock().await;
+
+    unsafe {
+        std::env::set_var("AUTH_TOKEN", "test-token-123");
+        std::env::set_var("USER_TOKENS", "alice=alice-token,bob=bob-token");
+    }
+    let storage: Storage = Arc::new(HashMapStorage::new());
+    let app = golink::routes::routes(storage.clone(), ClickRecorder::new(storage));
+
+    let create_req = CreateGolink {
+        short_link: "go/handbook".to_string(),
+        url: "https://handbook.example.com".to_string(),
+        ..Default::default()
+    };
+    let create_resp = request()
+        .method("POST")
+        .path("/golinks")
+        .header("content-type", "application/json")
+        .header("authorization", "Bearer alice-token")
+        .json(&create_req)
+        .reply(&app)
+        .await;
+    assert_eq!(create_resp.status(), 201);
+
+    // 1. The shared token has no identity to attach favorites to
+    let resp = request()
+        .method("PUT")
+        .path("/golinks/go/handbook/favorite")
+        .header("authorization", "Bearer test-token-123")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 401);
+
+    // 2. Alice stars the golink
+    let resp = request()
+        .method("PUT")
+        .path("/golinks/go/handbook/favorite")
+        .header("authorization", "Bearer alice-token")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 200);
+
+    let resp = request()
+        .method("GET")
+        .path("/me/favorites")
+        .header("authorization", "Bearer alice-token")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body[0]["short_link"], "go/handbook");
+    assert!(body[0]["favorited_at"].is_string());
+
+    // 3. Bob's favorites are separate
+    let resp = request()
+        .method("GET")
+        .path("/me/favorites")
+        .header("authorization", "Bearer bob-token")
+        .reply(&app)
+        .await;
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body, serde_json::json!([]));
+
+    // 4. Alice removes the star
+    let resp = request()
+        .method("DELETE")
+        .path("/golinks/go/handbook/favorite")
+        .header("authorization", "Bearer alice-token")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 200);
+
+    let resp = request()
+        .method("DELETE")
+        .path("/golinks/go/handbook/favorite")
+        .header("authorization", "Bearer alice-token")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 404);
+
+    unsafe {
+        std::env::remove_var("AUTH_TOKEN");
+        std::env::remove_var("USER_TOKENS");
+    }
+}
+
+#[tokio::test]
+async fn test_collection_resolves_to_landing_page() {
+    let _guard = AUTH_TEST_LOCK.lock().await;
+
+    let storage: Storage = Arc::new(HashMapStorage::new());
+    let app = golink::routes::routes(storage.clone(), ClickRecorder::new(storage));
+
+    for (short_link, url) in [
+        ("go/handbook", "https://handbook.example.com"),
+        ("go/oncall", "https://oncall.example.com"),
+    ] {
+        let create_req = CreateGolink {
+            short_link: short_link.to_string(),
+            url: url.to_string(),
+            ..Default::default()
+        };
+        let resp = request()
+            .method("POST")
+            .path("/golinks")
+            .header("content-type", "application/json")
+            .json(&create_req)
+            .reply(&app)
+            .await;
+        assert_eq!(resp.status(), 201);
+    }
+
+    // 1. Create a collection
+    let resp = request()
+        .method("POST")
+        .path("/collections")
+        .header("content-type", "application/json")
+        .json(&serde_json::json!({
+            "name": "go/onboarding",
+            "title": "New engineer essentials",
+            "links": ["go/oncall", "go/handbook"]
+        }))
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 201);
+
+    let resp = request()
+        .method("GET")
+        .path("/collections/go/onboarding")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["links"], serde_json::json!(["go/oncall", "go/handbook"]));
+
+    // 2. The collection name resolves to a landing page
+    let resp = request()
+        .method("GET")
+        .path("/go/onboarding")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 200);
+    assert!(
+        resp.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/html")
+    );
+    let html = String::from_utf8(resp.body().to_vec()).unwrap();
+    assert!(html.contains("New engineer essentials"));
+    assert!(html.contains("href=\"/go/oncall\""));
+
+    // 3. Golinks can't take the collection's name
+    let create_req = CreateGolink {
+        short_link: "go/onboarding".to_string(),
+        url: "https://example.com".to_string(),
+        ..Default::default()
+    };
+    let resp = request()
+        .method("POST")
+        .path("/golinks")
+        .header("content-type", "application/json")
+        .json(&create_req)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 409);
+
+    // 4. Deleted collections no longer resolve
+    let resp = request()
+        .method("DELETE")
+        .path("/collections/go/onboarding")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 200);
+
+    let resp = request()
+        .method("GET")
+        .path("/go/onboarding")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 404);
+}
+
+#[tokio::test]
+async fn test_patch_golink_with_merge_patch() {
+    let _guard = AUTH_TEST_LOCK.lock().await;
+
+    let storage: Storage = Arc::new(HashMapStorage::new());
+    let app = golink::routes::routes(storage.clone(), ClickRecorder::new(storage));
+
+    let create_req = CreateGolink {
+        short_link: "go/roadmap".to_string(),
+        url: "https://roadmap.example.com".to_string(),
+        owner: Some("product".to_string()),
+        ..Default::default()
+    };
+    let resp = request()
+        .method("POST")
+        .path("/golinks")
+        .header("content-type", "application/json")
+        .json(&create_req)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 201);
+
+    // 1. Only the fields in the patch change
+    let resp = request()
+        .method("PATCH")
+        .path("/golinks/go/roadmap")
+        .header("content-type", "application/merge-patch+json")
+        .body(r#"{"url": "https://roadmap.example.com/2025"}"#)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["url"], "https://roadmap.example.com/2025");
+    assert_eq!(body["owner"], "product");
+
+    // 2. null removes optional fields
+    let resp = request()
+        .method("PATCH")
+        .path("/golinks/go/roadmap")
+        .header("content-type", "application/merge-patch+json")
+        .body(r#"{"owner": null}"#)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert!(body["owner"].is_null());
+
+    // 3. Immutable fields are rejected
+    let resp = request()
+        .method("PATCH")
+        .path("/golinks/go/roadmap")
+        .header("content-type", "application/merge-patch+json")
+        .body(r#"{"created_at": "2020-01-01T00:00:00+00:00"}"#)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 400);
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["error"], "Field 'created_at' is immutable");
+
+    let resp = request()
+        .method("PATCH")
+        .path("/golinks/go/missing")
+        .header("content-type", "application/merge-patch+json")
+        .body(r#"{"url": "https://example.com"}"#)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 404);
+}
+
+#[tokio::test]
+async fn test_if_match_prevents_lost_updates() {
+    let _guard = AUTH_TEST_LOCK.lock().await;
+
+    let storage: Storage = Arc::new(HashMapStorage::new());
+    let app = golink::routes::routes(storage.clone(), ClickRecorder::new(storage));
+
+    let create_req = CreateGolink {
+        short_link: "go/oncall".to_string(),
+        url: "https://pager.example.com".to_string(),
+        ..Default::default()
+    };
+    let resp = request()
+        .method("POST")
+        .path("/golinks")
+        .json(&create_req)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 201);
+    let created_etag = resp.headers()["etag"].to_str().unwrap().to_string();
+
+    let resp = request()
+        .method("GET")
+        .path("/golinks/go/oncall")
+        .reply(&app)
+        .await;
+    // Reads get the weak form of the tag, since clicks don't bump the version
+    assert_eq!(resp.headers()["etag"], format!("W/{}", created_etag).as_str());
+
+    // Both editors start from the same version, only the first one wins
+    let resp = request()
+        .method("PUT")
+        .path("/golinks/go/oncall")
+        .header("if-match", &created_etag)
+        .json(&serde_json::json!({"url": "https://oncall.example.com/alice"}))
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 200);
+    let updated_etag = resp.headers()["etag"].to_str().unwrap().to_string();
+    assert_ne!(updated_etag, created_etag);
+
+    let resp = request()
+        .method("PUT")
+        .path("/golinks/go/oncall")
+        .header("if-match", &created_etag)
+        .json(&serde_json::json!({"url": "https://oncall.example.com/bob"}))
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 412);
+
+    let resp = request()
+        .method("PATCH")
+        .path("/golinks/go/oncall")
+        .header("if-match", &created_etag)
+        .body(r#"{"description": "Who is on call"}"#)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 412);
+
+    let resp = request()
+        .method("DELETE")
+        .path("/golinks/go/oncall")
+        .header("if-match", &created_etag)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 412);
+
+    let resp = request()
+        .method("GET")
+        .path("/golinks/go/oncall")
+        .reply(&app)
+        .await;
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["url"], "https://oncall.example.com/alice");
+    assert_eq!(body["version"], 2);
+
+    // Any of several tags may match, and `*` matches whatever is there
+    let resp = request()
+        .method("PATCH")
+        .path("/golinks/go/oncall")
+        .header("if-match", format!("{}, {}", created_etag, updated_etag))
+        .body(r#"{"description": "Who is on call"}"#)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 200);
+
+    // The weak tag from a read guards a write just the same
+    let resp = request()
+        .method("GET")
+        .path("/golinks/go/oncall")
+        .reply(&app)
+        .await;
+    let read_etag = resp.headers()["etag"].to_str().unwrap().to_string();
+    assert!(read_etag.starts_with("W/"));
+
+    let resp = request()
+        .method("PATCH")
+        .path("/golinks/go/oncall")
+        .header("if-match", &read_etag)
+        .body(r#"{"description": "Who is on call this week"}"#)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 200);
+
+    let resp = request()
+        .method("PATCH")
+        .path("/golinks/go/oncall")
+        .header("if-match", &read_etag)
+        .body(r#"{"description": "Who is on call today"}"#)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 412);
+
+    let resp = request()
+        .method("DELETE")
+        .path("/golinks/go/oncall")
+        .header("if-match", "*")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 200);
+
+    let resp = request()
+        .method("PUT")
+        .path("/golinks/go/oncall")
+        .header("if-match", "*")
+        .json(&serde_json::json!({"url": "https://oncall.example.com/carol"}))
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 412);
+}
+
+#[tokio::test]
+async fn test_conditional_get() {
+    let _guard = AUTH_TEST_LOCK.lock().await;
+
+    let storage: Storage = Arc::new(HashMapStorage::new());
+    let app = golink::routes::routes(storage.clone(), ClickRecorder::new(storage));
+
+    let create_req = CreateGolink {
+        short_link: "go/docs".to_string(),
+        url: "https://docs.example.com".to_string(),
+        ..Default::default()
+    };
+    let resp = request()
+        .method("POST")
+        .path("/golinks")
+        .json(&create_req)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 201);
+
+    let resp = request().method("GET").path("/golinks").reply(&app).await;
+    assert_eq!(resp.status(), 200);
+    let list_etag = resp.headers()["etag"].to_str().unwrap().to_string();
+    let list_modified = resp.headers()["last-modified"].to_str().unwrap().to_string();
+    assert!(list_modified.ends_with(" GMT"));
+
+    let resp = request()
+        .method("GET")
+        .path("/golinks")
+        .header("if-none-match", &list_etag)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 304);
+    assert!(resp.body().is_empty());
+    assert_eq!(resp.headers()["etag"], list_etag.as_str());
+
+    let resp = request()
+        .method("GET")
+        .path("/golinks?page=1&page_size=10")
+        .header("if-none-match", format!("W/{}", list_etag))
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 304);
+
+    let resp = request()
+        .method("GET")
+        .path("/golinks")
+        .header("if-modified-since", &list_modified)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 304);
+
+    let resp = request()
+        .method("GET")
+        .path("/golinks/go/docs")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 200);
+    let item_etag = resp.headers()["etag"].to_str().unwrap().to_string();
+    assert!(item_etag.starts_with("W/"));
+    assert!(resp.headers().contains_key("last-modified"));
+
+    let resp = request()
+        .method("GET")
+        .path("/golinks/go/docs")
+        .header("if-none-match", &item_etag)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 304);
+    assert!(resp.body().is_empty());
+
+    // Any write moves the list on, and an edit moves the golink on
+    let resp = request()
+        .method("PUT")
+        .path("/golinks/go/docs")
+        .json(&serde_json::json!({"url": "https://docs.example.com/v2"}))
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 200);
+
+    let resp = request()
+        .method("GET")
+        .path("/golinks")
+        .header("if-none-match", &list_etag)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 200);
+    assert_ne!(resp.headers()["etag"], list_etag.as_str());
+    let body: Vec<serde_json::Value> = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body[0]["url"], "https://docs.example.com/v2");
+
+    let resp = request()
+        .method("GET")
+        .path("/golinks/go/docs")
+        .header("if-none-match", &item_etag)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 200);
+    assert_ne!(resp.headers()["etag"], item_etag.as_str());
+
+    // A bad query is still rejected rather than answered with 304
+    let resp = request()
+        .method("GET")
+        .path("/golinks?sort=color")
+        .header("if-none-match", "*")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn test_deprecation_moves_the_etag() {
+    let _guard = AUTH_TEST_LOCK.lock().await;
+
+    let storage: Storage = Arc::new(HashMapStorage::new());
+    let app = golink::routes::routes(storage.clone(), ClickRecorder::new(storage.clone()));
+
+    let create_req = CreateGolink {
+        short_link: "go/legacy".to_string(),
+        url: "https://legacy.example.com".to_string(),
+        ..Default::default()
+    };
+    let resp = request()
+        .method("POST")
+        .path("/golinks")
+        .json(&create_req)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 201);
+    let etag = resp.headers()["etag"].to_str().unwrap().to_string();
+
+    storage
+        .set_deprecated(&["go/legacy".to_string()], true)
+        .await
+        .unwrap();
+
+    // A copy from before the deprecation is neither fresh nor writable over
+    let resp = request()
+        .method("GET")
+        .path("/golinks/go/legacy")
+        .header("if-none-match", &etag)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["deprecated"], true);
+
+    let resp = request()
+        .method("PATCH")
+        .path("/golinks/go/legacy")
+        .header("if-match", &etag)
+        .body(r#"{"deprecated": false}"#)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 412);
+    assert!(storage.get("go/legacy").await.unwrap().deprecated);
+}
+
+#[tokio::test]
+async fn test_openapi_document() {
+    let _guard = AUTH_TEST_LOCK.lock().await;
+
+    let storage: Storage = Arc::new(HashMapStorage::new());
+    let app = golink::routes::routes(storage.clone(), ClickRecorder::new(storage));
+
+    let resp = request().method("GET").path("/openapi.json").reply(&app).await;
+    assert_eq!(resp.status(), 200);
+    let spec: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+
+    let schemas = spec["components"]["schemas"].as_object().unwrap();
+    for name in ["Golink", "CreateGolink", "UpdateGolink", "ErrorResponse"] {
+        assert!(schemas.contains_key(name), "missing schema {}", name);
+    }
+    assert!(schemas.keys().any(|name| name.starts_with("PaginatedResponse")));
+    assert!(schemas["Golink"]["properties"]["short_link"].is_object());
+
+    let get = &spec["paths"]["/golinks/{prefix}/{name}"]["get"];
+    assert_eq!(
+        get["responses"]["200"]["content"]["application/json"]["schema"]["$ref"],
+        "#/components/schemas/Golink"
+    );
+    assert_eq!(
+        get["responses"]["404"]["content"]["application/json"]["schema"]["$ref"],
+        "#/components/schemas/ErrorResponse"
+    );
+    assert!(spec["components"]["securitySchemes"]["bearer_auth"].is_object());
+}
+
+#[tokio::test]
+async fn test_error_codes() {
+    let _guard = AUTH_TEST_LOCK.lock().await;
+
+    let storage: Storage = Arc::new(HashMapStorage::new());
+    let app = golink::routes::routes(storage.clone(), ClickRecorder::new(storage));
+
+    let create_req = CreateGolink {
+        short_link: "invalid".to_string(),
+        url: "https://example.com".to_string(),
+        ..Default::default()
+    };
+    let resp = request()
+        .method("POST")
+        .path("/golinks")
+        .header("content-type", "application/json")
+        .json(&create_req)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 400);
+    assert_eq!(resp.headers()["content-type"], "application/json");
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["code"], "invalid_pattern");
+    assert!(body["error"].as_str().unwrap().contains("go/[a-zA-Z0-9_-]+"));
+
+    let resp = request()
+        .method("GET")
+        .path("/golinks/go/missing")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 404);
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["code"], "golink_not_found");
+
+    // The same error as RFC 7807 problem details
+    let resp = request()
+        .method("GET")
+        .path("/golinks/go/missing")
+        .header("accept", "application/problem+json")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 404);
+    assert_eq!(resp.headers()["content-type"], "application/problem+json");
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["type"], "about:blank");
+    assert_eq!(body["title"], "Not Found");
+    assert_eq!(body["status"], 404);
+    assert_eq!(body["detail"], "Golink not found");
+    assert_eq!(body["code"], "golink_not_found");
+
+    // Rejections from the auth filters carry codes too
+    unsafe { std::env::set_var("AUTH_TOKEN", "test-token-123"); }
+    let resp = request()
+        .method("GET")
+        .path("/golinks")
+        .header("accept", "application/problem+json")
+        .reply(&app)
+        .await;
+    unsafe { std::env::remove_var("AUTH_TOKEN"); }
+    assert_eq!(resp.status(), 401);
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["code"], "missing_token");
+    assert_eq!(body["status"], 401);
+}
+
+#[tokio::test]
+async fn test_unknown_route_is_not_found() {
+    let _guard = AUTH_TEST_LOCK.lock().await;
+
+    let storage: Storage = Arc::new(HashMapStorage::new());
+    let app = golink::routes::routes(storage.clone(), ClickRecorder::new(storage));
+
+    for path in ["/nothing/here", "/golinks/go/docs/unknown", "/go/docs/extra"] {
+        let resp = request().method("GET").path(path).reply(&app).await;
+        assert_eq!(resp.status(), 404, "{}", path);
+        let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(body["code"], "route_not_found");
+    }
+}
+
+#[tokio::test]
+async fn test_wrong_method_is_not_allowed() {
+    let _guard = AUTH_TEST_LOCK.lock().await;
+
+    let storage: Storage = Arc::new(HashMapStorage::new());
+    let app = golink::routes::routes(storage.clone(), ClickRecorder::new(storage));
+
+    let resp = request().method("DELETE").path("/golinks").reply(&app).await;
+    assert_eq!(resp.status(), 405);
+    assert_eq!(resp.headers()["allow"], "POST, GET");
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["code"], "method_not_allowed");
+
+    let resp = request()
+        .method("POST")
+        .path("/golinks/go/docs/favorite")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 405);
+    assert_eq!(resp.headers()["allow"], "PUT, DELETE");
+}
+
+#[tokio::test]
+async fn test_versioned_api() {
+    let _guard = AUTH_TEST_LOCK.lock().await;
+
+    let storage: Storage = Arc::new(HashMapStorage::new());
+    let app = golink::routes::routes(storage.clone(), ClickRecorder::new(storage));
+
+    let create_req = CreateGolink {
+        short_link: "go/docs".to_string(),
+        url: "https://docs.example.com".to_string(),
+        ..Default::default()
+    };
+    let resp = request()
+        .method("POST")
+        .path("/api/v1/golinks")
+        .json(&create_req)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 201);
+    assert!(resp.headers().get("deprecation").is_none());
+
+    let resp = request()
+        .method("GET")
+        .path("/api/v1/golinks/go/docs")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 200);
+    assert!(resp.headers().get("deprecation").is_none());
+    let golink: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(golink["url"], "https://docs.example.com");
+
+    // The unversioned path is a deprecated alias pointing at its successor
+    let resp = request()
+        .method("GET")
+        .path("/golinks/go/docs")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["deprecation"], "true");
+    assert_eq!(
+        resp.headers()["link"],
+        "</api/v1/golinks/go/docs>; rel=\"successor-version\""
+    );
+    let legacy: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(legacy, golink);
+
+    // Errors are deprecated too, but short links are not
+    let resp = request().method("DELETE").path("/golinks").reply(&app).await;
+    assert_eq!(resp.status(), 405);
+    assert_eq!(resp.headers()["deprecation"], "true");
+
+    let resp = request().method("GET").path("/go/docs").reply(&app).await;
+    assert_eq!(resp.status(), 302);
+    assert!(resp.headers().get("deprecation").is_none());
+
+    let resp = request()
+        .method("DELETE")
+        .path("/api/v1/golinks")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 405);
+    assert_eq!(resp.headers()["allow"], "POST, GET");
+
+    let resp = request()
+        .method("GET")
+        .path("/api/v2/golinks")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 404);
+    assert!(resp.headers().get("deprecation").is_none());
+
+    let resp = request()
+        .method("GET")
+        .path("/api/v1/openapi.json")
+        .reply(&app)
+        .await;
+    let spec: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(spec["servers"][0]["url"], "/api/v1");
+}
+
+#[tokio::test]
+async fn test_web_ui() {
+    let _guard = AUTH_TEST_LOCK.lock().await;
+
+    unsafe { std::env::set_var("AUTH_TOKEN", "test-token-123"); }
+    let storage: Storage = Arc::new(HashMapStorage::new());
+    let app = golink::routes::routes(storage.clone(), ClickRecorder::new(storage));
+
+    // The page itself is public, the API calls it makes need the token
+    let resp = request().method("GET").path("/ui").reply(&app).await;
+    assert_eq!(resp.status(), 200);
+    assert!(resp.headers()["content-type"].to_str().unwrap().starts_with("text/html"));
+    assert!(resp.headers().get("deprecation").is_none());
+    let page = String::from_utf8(resp.body().to_vec()).unwrap();
+    assert!(page.contains("const API = \"/api/v1\";"));
+
+    let resp = request().method("GET").path("/api/v1/golinks").reply(&app).await;
+    assert_eq!(resp.status(), 401);
+
+    unsafe { std::env::remove_var("AUTH_TOKEN"); }
+}
+
+#[tokio::test]
+async fn test_malformed_json_is_bad_request() {
+    let _guard = AUTH_TEST_LOCK.lock().await;
+
+    let storage: Storage = Arc::new(HashMapStorage::new());
+    let app = golink::routes::routes(storage.clone(), ClickRecorder::new(storage));
+
+    let resp = request()
+        .method("POST")
+        .path("/golinks")
+        .header("content-type", "application/json")
+        .body(r#"{"short_link": "go/docs""#)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 400);
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["code"], "invalid_body");
+    assert!(body["error"].as_str().unwrap().contains("line 1 column 24"), "{}", body);
+
+    let resp = request()
+        .method("POST")
+        .path("/golinks")
+        .header("content-type", "application/json")
+        .body(r#"{"short_link": "go/docs"}"#)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 400);
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert!(body["error"].as_str().unwrap().contains("missing field `url`"), "{}", body);
+}
+
+#[tokio::test]
+async fn test_body_length_and_type_errors() {
+    let _guard = AUTH_TEST_LOCK.lock().await;
+
+    let storage: Storage = Arc::new(HashMapStorage::new());
+    let app = golink::routes::routes(storage.clone(), ClickRecorder::new(storage));
+
+    // Imports need a Content-Length to enforce their size limit
+    let resp = request()
+        .method("POST")
+        .path("/golinks/import")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 411);
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["code"], "length_required");
+
+    let oversized = vec![b' '; golink::transfer::MAX_IMPORT_BYTES as usize + 1];
+    let resp = request()
+        .method("POST")
+        .path("/golinks/import")
+        .header("content-type", "application/json")
+        .body(oversized)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 413);
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["code"], "payload_too_large");
+
+    let resp = request()
+        .method("POST")
+        .path("/golinks")
+        .header("content-type", "text/plain")
+        .body("go/docs https://docs.example.com")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 415);
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["code"], "unsupported_media_type");
+}
+
+#[tokio::test]
+async fn test_batch_endpoint() {
+    let _guard = AUTH_TEST_LOCK.lock().await;
+
+    let storage: Storage = Arc::new(HashMapStorage::new());
+    let app = golink::routes::routes(storage.clone(), ClickRecorder::new(storage));
+
+    let resp = request()
+        .method("POST")
+        .path("/golinks/batch?atomic=true")
+        .header("content-type", "application/json")
+        .json(&serde_json::json!({
+            "operations": [
+                {"op": "create", "short_link": "go/docs", "url": "https://docs.example.com"},
+                {"op": "create", "short_link": "go/wiki", "url": "https://wiki.example.com"},
+                {"op": "update", "short_link": "go/docs", "url": "https://docs.example.com/v2"}
+            ]
+        }))
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["applied"], true);
+    let statuses: Vec<u64> = body["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|result| result["status"].as_u64().unwrap())
+        .collect();
+    assert_eq!(statuses, vec![201, 201, 200]);
+
+    let resp = request()
+        .method("GET")
+        .path("/golinks/go/docs")
+        .reply(&app)
+        .await;
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["url"], "https://docs.example.com/v2");
+
+    // The batch path isn't mistaken for a golink create
+    let resp = request()
+        .method("GET")
+        .path("/golinks")
+        .reply(&app)
+        .await;
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body.as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_export_and_import_round_trip() {
+    let _guard = AUTH_TEST_LOCK.lock().await;
+
+    let source: Storage = Arc::new(HashMapStorage::new());
+    let app = golink::routes::routes(source.clone(), ClickRecorder::new(source));
+    for short_link in ["go/docs", "go/wiki"] {
+        let resp = request()
+            .method("POST")
+            .path("/golinks")
+            .json(&serde_json::json!({"short_link": short_link, "url": "https://example.com"}))
+            .reply(&app)
+            .await;
+        assert_eq!(resp.status(), 201);
+    }
+
+    let resp = request()
+        .method("GET")
+        .path("/golinks/export?format=ndjson")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["content-type"], "application/x-ndjson");
+    let export = resp.body().clone();
+    assert_eq!(export.iter().filter(|byte| **byte == b'\n').count(), 2);
+
+    let target: Storage = Arc::new(HashMapStorage::new());
+    let app = golink::routes::routes(target.clone(), ClickRecorder::new(target));
+    let resp = request()
+        .method("POST")
+        .path("/golinks/import?on_conflict=fail")
+        .header("content-type", "application/x-ndjson")
+        .body(export.clone())
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["format"], "ndjson");
+    assert_eq!(body["imported"], 2);
+
+    // Importing the same export again conflicts on every row
+    let resp = request()
+        .method("POST")
+        .path("/golinks/import?on_conflict=fail")
+        .header("content-type", "application/x-ndjson")
+        .body(export)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 409);
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["failed"], 2);
+}
+
+#[tokio::test]
+async fn test_search_endpoint() {
+    let _guard = AUTH_TEST_LOCK.lock().await;
+
+    let storage: Storage = Arc::new(HashMapStorage::new());
+    let app = golink::routes::routes(storage.clone(), ClickRecorder::new(storage));
+    for (short_link, url, description) in [
+        ("go/grafana", "https://grafana.example.com", None),
+        ("go/oncall", "https://pager.example.com", Some("Grafana alerts & paging")),
+    ] {
+        let resp = request()
+            .method("POST")
+            .path("/golinks")
+            .json(&serde_json::json!({
+                "short_link": short_link,
+                "url": url,
+                "description": description
+            }))
+            .reply(&app)
+            .await;
+        assert_eq!(resp.status(), 201);
+    }
+
+    let resp = request()
+        .method("GET")
+        .path("/golinks/search?q=alert")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["count"], 1);
+    assert_eq!(body["results"][0]["short_link"], "go/oncall");
+    assert_eq!(body["results"][0]["description"], "Grafana alerts & paging");
+    assert_eq!(
+        body["results"][0]["highlight"]["fragment"],
+        "Grafana <mark>alerts</mark> &amp; paging"
+    );
+
+    let resp = request()
+        .method("GET")
+        .path("/golinks/search")
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), 400);
+}